@@ -3,9 +3,9 @@
 // extern crate num_traits;
 #[macro_use] extern crate log;
 
-use std::{env, path::PathBuf, process};
+use std::{env, process};
 
-use goldparser::{GOLDParser, engine::{Builder, EnhancedGrammarTable}};
+use goldparser::{GOLDParser, engine::{Builder, EnhancedGrammarTable}, grammar::{self, export::Notation}};
 
 
 
@@ -19,10 +19,19 @@ properties  Dump the EGT properties
 dfa         Dump the DFA State Table
 lalr        Dump the LALR State Table
 charset     Dump the character set table
-group       <TBD>
+group       Dump the lexical group table
+conflicts   Report LALR conflicts with the rules involved and an example input
+analyze     Report FIRST/FOLLOW sets, nullable, unreachable, unproductive and
+            left recursive symbols
+decompile   Write GOLD Meta-Language source (.grm) reconstructed from the tables
+ebnf        Write the grammar as W3C EBNF
+antlr       Write the grammar as an ANTLR4 combined grammar (.g4)
+tree-sitter Write the grammar as a tree-sitter grammar.js
+railroad    Write an HTML page with a railroad diagram of every nonterminal
 interactive Run EGT REPL Shell
 
-<egt_file> is the path to the EGT file for your grammar.
+<egt_file> is the path to the EGT file for your grammar, or to its
+GOLD Meta-Language source (.grm) which is compiled first.
 e.g. egtutils rules mygrammar.egt
 ";
 
@@ -35,7 +44,8 @@ fn main() {
     if args.len() != 3 {println!("Wrong number of arguments.\n{}", PROG_INFO); process::exit(0);}
     let cmd = &args[1];
     let egt = gen_egt(&args[2]);
-    println!("Grammar tables loaded.");
+    // on stderr, so the output of decompile and the exports can be saved to a file
+    eprintln!("Grammar tables loaded.");
 
     match cmd.as_str() {
         "symbols" => {
@@ -47,7 +57,19 @@ fn main() {
         "lalr" => println!("[LALR State Table]\n{}",egt.lalr_states),
         "charset" => println!("[Character Set Table]\n{}",egt.charset),
         "counts" => println!("[Total Counts]\n{}",egt.counts),
-        "group" => println!("[Group Table]\n{}","self.groups"),
+        "group" => println!("[Group Table]\n{}",egt.groups),
+        "decompile" => print!("{}", grammar::decompile(&egt)),
+        "ebnf" => print!("{}", grammar::export::export(&egt, Notation::Ebnf)),
+        "antlr" => print!("{}", grammar::export::export(&egt, Notation::Antlr)),
+        "tree-sitter" => print!("{}", grammar::export::export(&egt, Notation::TreeSitter)),
+        "railroad" => print!("{}", grammar::railroad::html(&egt)),
+        "analyze" => println!("[Analysis]\n{}", grammar::analysis::Analysis::new(&egt)),
+        "conflicts" => {
+            let conflicts = grammar::lalr::conflicts(&egt);
+            println!("[LALR Conflicts]");
+            if conflicts.is_empty() { println!("None"); }
+            for conflict in conflicts { println!("{}", conflict.report()); }
+        },
         "interactive" => { interactive(&args[2]).expect("wtf");
 
         },
@@ -55,7 +77,7 @@ fn main() {
         _ => {println!("Unknown command {}.\n{}", cmd.as_str(), PROG_INFO); process::exit(0)}
     }
 
-   // let mut parser = GOLDParser::new(FILE_NAME, SOURCE_NAME, true, true);
+   // let mut parser = GOLDParser::new(FILE_NAME, SOURCE_NAME, true);
     
  //   parser.run();
 }
@@ -69,8 +91,10 @@ fn interactive(egt: &String) -> io::Result<()>{
         "B" => todo!(),
         "P" => {
             print!("Source file: "); io::stdin().read_line(&mut buf)?;
-            // TODO bring in `trim` and `case`
-            let parser = GOLDParser::new(egt.as_str(), buf.as_str(), true, false);
+            // TODO bring in `trim`
+            if let Err(e) = GOLDParser::new(egt.as_str(), buf.as_str(), true) {
+                eprintln!("{e}");
+            }
             
         
         },
@@ -90,9 +114,21 @@ fn interactive(egt: &String) -> io::Result<()>{
 }
 
 fn gen_egt(file: &String) -> EnhancedGrammarTable {
-    let egtfile = PathBuf::from(file);
-    let mut bldr = Builder::new(egtfile.into_os_string());
-    bldr.to_egt()
+    if file.to_ascii_lowercase().ends_with(".grm") {
+        return match grammar::compile_file(file) {
+            Ok(egt) => egt,
+            Err(grammar::GrammarError::Conflicts(conflicts)) => {
+                eprintln!("{file}: the grammar is not LALR(1)\n\n[LALR Conflicts]");
+                for conflict in conflicts { eprintln!("{}", conflict.report()); }
+                process::exit(1)
+            },
+            Err(e) => { eprintln!("{file}: {e}"); process::exit(1) }
+        };
+    }
+    match Builder::from_path(file).and_then(|mut bldr| bldr.to_egt()) {
+        Ok(egt) => egt,
+        Err(e) => { eprintln!("{file}: {e}"); process::exit(1) }
+    }
 }
 
 
@@ -3,10 +3,11 @@
 //! Use this module to build an `EGT` from a binary .egt file
 //! Can be converted directly to a `EGT`
 
-use std::{ffi::OsString, fs::File, io::Read, ops::{Range, RangeInclusive}, char::decode_utf16,};
+use std::{fs::File, io::{self, Read}, ops::RangeInclusive, path::Path};
 
 use enum_primitive::FromPrimitive;
-use utf16string::{WString, LE, WStr, Utf16Error, BE};
+use thiserror::Error;
+use utf16string::{WString, LE};
 
 use crate::{
     engine::{LogicalRecord, RecordType, EntryType, RecordEntry, 
@@ -15,12 +16,48 @@ use crate::{
         states::{InitialStatesRecord, DFAEdge, DFAState, LALRAction, ActionType, LALRState}, 
         charset::CharacterSet, 
         symbol::{Symbol, SymbolType}, 
+        group::{LexicalGroup, AdvanceMode, EndingMode},
         production::ProductionRule, 
         tables::Table,
-        egt::EnhancedGrammarTable, SymbolTable
+        egt::EnhancedGrammarTable, SymbolTable,
+        cgt,
+        validate::Finding,
     }, 
 };
 
+/// Errors raised while decoding an `EnhancedGrammarTable` from its binary form.
+/// Offsets are byte offsets into the EGT file.
+#[derive(Debug, Error)]
+pub enum EgtError {
+    /// The EGT file could not be opened or read
+    #[error("unable to read grammar table: {0}")]
+    Io(#[from] io::Error),
+    /// The file does not start with the `GOLD Parser Tables/v5.0` or `GOLD Parser Tables/v1.0` header
+    #[error("bad header {0:?}, expected \"GOLD Parser Tables/v5.0\" or \"GOLD Parser Tables/v1.0\"")]
+    BadHeader(String),
+    /// The file ended in the middle of a record
+    #[error("truncated record at byte {offset}")]
+    Truncated { offset: usize },
+    /// A record did not start with 'M' or has an unknown record type
+    #[error("unknown record at byte {offset}: {:?}", *.found as char)]
+    BadRecord { offset: usize, found: u8 },
+    /// An entry type byte is unknown
+    #[error("unknown entry type {:?} at byte {offset}", *.found as char)]
+    BadEntry { offset: usize, found: u8 },
+    /// An entry is not of the type the record layout calls for
+    #[error("unexpected {found:?} entry at byte {offset} in {record:?} record, expected {expected:?}")]
+    UnexpectedEntry { offset: usize, record: RecordType, expected: EntryType, found: EntryType },
+    /// A string entry is not valid UTF-16
+    #[error("invalid UTF-16 string at byte {offset}")]
+    InvalidString { offset: usize },
+    /// An index or enumerated value is outside of the table it refers to
+    #[error("index {index} out of range for {table} ({len} entries) at byte {offset} in {record:?} record")]
+    IndexOutOfRange { offset: usize, record: RecordType, table: &'static str, index: usize, len: usize },
+    /// The records decoded but the tables are inconsistent, see `EnhancedGrammarTable::validate()`
+    #[error("invalid grammar table: {}", .0.iter().map(|f| f.to_string()).collect::<Vec<_>>().join("; "))]
+    Invalid(Vec<Finding>),
+}
+
 #[derive(Debug)]
 /// The `Builder`
 pub struct Builder {
@@ -33,229 +70,264 @@ pub struct Builder {
 }
 
 impl Builder {
-    pub fn new(flname: OsString) -> Self {
-        //let mut file = File::open(file).unwrap();
-        let mut file = match File::open(flname) {
-            Ok(f) => f,
-            Err(e) => { // we shouldn't panic in new()
-                panic!("{e}")
-            }
-        };
-        
-        let mut buf = Vec::new(); //vec![0u8];
-        match file.read_to_end(&mut buf) {
-            Ok(sz) => println!("Read {sz} bytes."),
-            Err(e) => panic!("Error reading file: {:?}",e),
-        }
-       // println!("Builder::new(): buf[0] = {} buf[1] = {}", buf[0], buf[1]);
-        Builder {
-            bytes: buf,
-            pos: 0,
-            records: Vec::new(),
-            initialized: false,
-        }
+    /// Reads the EGT file at `path`. The records are not decoded until `init()` or `to_egt()`.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, EgtError> {
+        Self::from_reader(File::open(path)?)
     }
 
+    /// Reads an EGT from any `Read` source until EOF.
+    pub fn from_reader<R: Read>(mut reader: R) -> Result<Self, EgtError> {
+        let mut buf = Vec::new();
+        let sz = reader.read_to_end(&mut buf)?;
+        debug!("Read {sz} bytes.");
+        Ok(Self::from(buf))
+    }
 
-    pub fn to_egt(&mut self) -> EnhancedGrammarTable {
-        //assert!(self.pos == self.bytes.len());
-        if !self.initialized { self.init(); }
-        assert!(self.initialized);
+    /// Uses an EGT already in memory, e.g. one embedded with `include_bytes!`.
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        Self::from(bytes.to_vec())
+    }
+
+
+    pub fn to_egt(&mut self) -> Result<EnhancedGrammarTable, EgtError> {
+        if !self.initialized { self.init()?; }
 
-        let header = self.read_header();
-        let mut egt = EnhancedGrammarTable::new(header.to_string());
-        //let records = self.records;
-        for record in &self.records { //self.records.as_slice() {
-            //DEBUG println!("{:?}", record.kind);
+        let header = self.read_header()?;
+        let cgt = header.to_string() == EnhancedGrammarTable::CGT_HEADER;
+        // CGT files are converted, so the table is always an EGT
+        let mut egt = EnhancedGrammarTable::new(EnhancedGrammarTable::EGT_HEADER.to_string());
+        let mut start_symbol = None;
+        for record in &self.records {
             match record.kind {
-                RecordType::Multi => panic!(),
+                RecordType::Multi => return Err(EgtError::BadRecord { offset: record.offset(0), found: RecordType::Multi as u8 }),
                 RecordType::Property => {
-                    let index = record.entries[0].as_usize();
-                    let name = record.entries[1].string();
-                    let value = record.entries[2].string();
+                    let index = record.integer(0)? as usize;
+                    let name = record.string(1)?;
+                    let value = record.string(2)?;
                     let r = PropertyRecord::new(index,name,value);
-                    //DEBUG println!("{}", r);
-                    //egt.properties[index] = r;
-                    egt.properties.insert(index, r);
-                    //println!("");
+                    egt.properties.push(r);
+                },
+                RecordType::Parameters => {
+                    for (index, name) in cgt::PARAMETERS[..4].iter().enumerate() {
+                        let value = record.string(index)?;
+                        egt.properties.push(PropertyRecord::new(index, name.to_string(), value));
+                    }
+                    let case = if record.bool(4)? { "True" } else { "False" };
+                    egt.properties.push(PropertyRecord::new(4, cgt::PARAMETERS[4].to_string(), case.to_string()));
+                    // resolved once the symbol table is loaded
+                    start_symbol = Some(record);
+                },
+                RecordType::LegacyCounts => {
+                    let s = record.integer(0)?;
+                    let c = record.integer(1)?;
+                    let r = record.integer(2)?;
+                    let d = record.integer(3)?;
+                    let l = record.integer(4)?;
+                    egt.counts = TableCountsRecord::new(s,c,r,d,l,0);
+                    egt.resize();
+                },
+                RecordType::LegacyCharSet => {
+                    let i = record.index(0, "character set table", egt.charset.len())?;
+                    let chars = record.string(1)?;
+                    egt.charset.add(i, CharacterSet::from(chars.as_str()));
                 },
                 RecordType::Counts => {
-                    let s = record.entries[0].integer();
-                    let c = record.entries[1].integer();
-                    let r = record.entries[2].integer();
-                    let d = record.entries[3].integer();
-                    let l = record.entries[4].integer();
-                    let g = record.entries[5].integer();
+                    let s = record.integer(0)?;
+                    let c = record.integer(1)?;
+                    let r = record.integer(2)?;
+                    let d = record.integer(3)?;
+                    let l = record.integer(4)?;
+                    let g = record.integer(5)?;
                     let rec = TableCountsRecord::new(s,c,r,d,l,g);
-                    //DEBUG println!("{}", rec);
                     egt.counts = rec;
                     // sets up our random access through array indexing here
                     egt.resize();
                 },
                 RecordType::CharSet => {
-                    let i = record.entries[0].as_usize();  // index of this charset in CharacterSetTable
-                    let _u = record.entries[1].integer();    // unicode plane
-                    let c = record.entries[2].as_usize();   // number of ranges in this charset
-                    let _empty = &record.entries[3];
-                    //let mut r: Vec<(u16,u16)> = Vec::new();
+                    let i = record.index(0, "character set table", egt.charset.len())?;  // index of this charset in CharacterSetTable
+                    let plane = record.integer(1)?;    // unicode plane
+                    let c = record.integer(2)? as usize;   // number of ranges in this charset
                     let mut r: Vec<RangeInclusive<char>> = Vec::new();
                     let mut idx: usize = 4;
                     for _ in 0..c {
-                        let a = record.entries[idx].integer();
-                        let b = record.entries[idx+1].integer();
-                        let v = decode_utf16([a,b])
-                            .map(|r| r.map_err(|e| e.unpaired_surrogate()))
-                            .collect::<Vec<_>>();
-                            let v0 = v[0].unwrap();
-                            let v1 = v[1].unwrap();
-                        r.push(RangeInclusive::new(v0,v1));
+                        let a = record.integer(idx)?;
+                        let b = record.integer(idx+1)?;
+                        // ranges wholly inside the surrogate block match nothing
+                        r.extend(CharacterSet::decode_range(plane, a, b));
                         idx += 2;
                     }
-                    // let rec = CharacterSetRecord::new(
-                    //     i.integer(), u.integer(), c.integer(), r
-                    // );
                     let rec = CharacterSet::new(r);
-                    //DEBUG println!("{:?}", rec);
-                    //egt.charset[i] = rec;
                     egt.charset.add(i,rec);
                 },
                 RecordType::Symbol => {
-                    let index = record.entries[0].as_usize();
-                    let s = record.entries[1].string();
-                    let t = record.entries[2].integer();
-                    //if  index > SymbolType::Error as usize { panic!("SymbolType out of range."); }
-                    let k = SymbolType::from_u16(t).expect("Bad Symbol Type");
+                    let index = record.index(0, "symbol table", egt.symbols.len())?;
+                    let s = record.string(1)?;
+                    let t = record.integer(2)?;
+                    let k = SymbolType::from_u16(t)
+                        .filter(|k| *k != SymbolType::Undefined)
+                        .ok_or(EgtError::IndexOutOfRange { 
+                            offset: record.offset(2), record: record.kind, table: "symbol types", index: t as usize, len: SymbolType::Undefined as usize 
+                        })?;
 
                     let rec = Symbol::new(index,s,k);
-
-                    //DEBUG println!("{}", rec);
                     egt.symbols.add(rec);
                 },
-                RecordType::Group => todo!(),
+                RecordType::Group => {
+                    let index = record.index(0, "group table", egt.groups.len())?;
+                    let name = record.string(1)?;
+                    let container = record.index(2, "symbol table", egt.symbols.len())?;
+                    let start = record.index(3, "symbol table", egt.symbols.len())?;
+                    let end = record.index(4, "symbol table", egt.symbols.len())?;
+                    let a = record.integer(5)?;
+                    let advance = AdvanceMode::from_u16(a)
+                        .ok_or(EgtError::IndexOutOfRange { 
+                            offset: record.offset(5), record: record.kind, table: "advance modes", index: a as usize, len: 2
+                        })?;
+                    let e = record.integer(6)?;
+                    let ending = EndingMode::from_u16(e)
+                        .ok_or(EgtError::IndexOutOfRange { 
+                            offset: record.offset(6), record: record.kind, table: "ending modes", index: e as usize, len: 2
+                        })?;
+                    let count = record.integer(8)? as usize; // number of groups that can nest in this one
+                    let mut nested: Vec<usize> = Vec::with_capacity(count);
+                    for idx in 9..9+count {
+                        nested.push(record.index(idx, "group table", egt.groups.len())?);
+                    }
+                    let rec = LexicalGroup::new(index, name, container, start, end, advance, ending, nested);
+                    egt.groups.add(rec);
+                },
                 RecordType::Production => {
-                    let index = record.entries[0].as_usize();
-                    let h = record.entries[1].as_usize();
-                    let _empty = &record.entries[2];
-                    //let mut r: Vec<u16> = Vec::new();
-                    let mut symbols: Vec<Symbol> = Vec::new(); //Vec::with_capacity(record.num_entries as usize);
+                    let index = record.index(0, "production table", egt.productions.len())?;
+                    let h = record.index(1, "symbol table", egt.symbols.len())?;
+                    let mut symbols: Vec<Symbol> = Vec::new();
                     let mut idx = 3;
-                    while idx < (record.num_entries-1) as usize {
-                        let ex = record.entries[idx].as_usize();
-                        let sym = egt.symbols[ex].clone();
-                        //symbols[ex] = sym;
-                        //symbols.insert(ex, sym);
-                        symbols.push(sym);
+                    while idx < record.entries.len() {
+                        let ex = record.index(idx, "symbol table", egt.symbols.len())?;
+                        symbols.push(egt.symbols[ex].clone());
                         idx += 1;
                     }
 
                     let head = egt.symbols[h].clone();
                     let rec = ProductionRule::new(index,head,SymbolTable::from(symbols));
-                    //println!("{:?}", rec);
                     egt.productions[index] = rec;
                 },
                 RecordType::InitState => {
-                    let dfa = record.entries[0].integer();
-                    let lalr = record.entries[1].integer();
+                    let dfa = record.integer(0)?;
+                    let lalr = record.integer(1)?;
                     let rec = InitialStatesRecord::new(dfa,lalr);
-                    //DEGBUG println!("{}", rec);
                     egt.initial_states = rec;                 
                 },
                 RecordType::DFA => {
-                    let state_idx = record.entries[0].as_usize(); // index of this DFAState in DFAStateTable
-                    let accepts_symbol = record.entries[1].bool(); // accept state
-                    let ai = record.entries[2].as_usize(); // index into symbol table for accept symbol
-                    let _reserved = &record.entries[3];
+                    let state_idx = record.index(0, "DFA state table", egt.dfa_states.len())?; // index of this DFAState in DFAStateTable
+                    let accepts_symbol = record.bool(1)?; // accept state
                     let mut edges: Vec<DFAEdge> = Vec::new();
                     let mut idx = 4;
-                    //println!("{} DFA[0] {:?} DFA[1] {:?} DFA[2] {:?}",record.num_entries, i, s, ai);
-                    while idx < (record.num_entries - 1) as usize  {
-                        let a = record.entries[idx].as_usize();   // this edge's characterset index in CharacterSetTable
-                        let b = record.entries[idx+1].as_usize(); // index of target state symbol
-                        let _empty = &record.entries[idx+2];
+                    while idx < record.entries.len() {
+                        let a = record.index(idx, "character set table", egt.charset.len())?;   // this edge's characterset index in CharacterSetTable
+                        let b = record.index(idx+1, "DFA state table", egt.dfa_states.len())?; // index of target state symbol
                         let chars = egt.charset[a].clone();
-                        edges.push(DFAEdge { chars, target_state: b});
+                        edges.push(DFAEdge { chars, charset_idx: a, target_state: b});
                         idx += 3;
                     }
                     let mut sym: Symbol = Symbol::default();
                     if accepts_symbol {
+                        let ai = record.index(2, "symbol table", egt.symbols.len())?; // index into symbol table for accept symbol
                         sym = egt.symbols[ai].clone();
                     }
                     let rec = DFAState::new(
                         state_idx, accepts_symbol, sym, edges
                     );
-                    // DEBUG println!("{}", rec);
-                    //egt.dfa_states.insert(state_idx, rec);  
                     egt.dfa_states[state_idx] = rec;      
                 },
                 RecordType::LALR => {
                     // Let's make an LALRState
-                    let index = record.entries[0].as_usize(); // index into LALRStateTable for this state
-                    let _empty = &record.entries[1];
+                    let index = record.index(0, "LALR state table", egt.lalr_states.len())?; // index into LALRStateTable for this state
                     let mut actions: Vec<LALRAction> = Vec::new();
                     let mut idx = 2;
                     // Add any actions associated with this state
-                    while idx < (record.num_entries - 1) as usize {
-                        let a = record.entries[idx].as_usize(); // symbol index
-                        let b = record.entries[idx+1].integer();   // action
-                        let c = record.entries[idx+2].as_usize();  // target index
-                        let _ = &record.entries[idx+3]; // empty
+                    while idx < record.entries.len() {
+                        let a = record.index(idx, "symbol table", egt.symbols.len())?; // symbol index
+                        let b = record.integer(idx+1)?;   // action
+                        let action = ActionType::from_u16(b)
+                            .filter(|a| *a != ActionType::Undefined)
+                            .ok_or(EgtError::IndexOutOfRange { 
+                                offset: record.offset(idx+1), record: record.kind, table: "LALR action types", index: b as usize, len: ActionType::Accept as usize + 1
+                            })?;
+                        let c = match action {
+                            ActionType::Reduce => record.index(idx+2, "production table", egt.productions.len())?,
+                            ActionType::Shift | ActionType::Goto => record.index(idx+2, "LALR state table", egt.lalr_states.len())?,
+                            _ => record.integer(idx+2)? as usize,
+                        };  // target index
                         let symbol = egt.symbols[a].clone();
-                        let action = ActionType::from_u16(b).unwrap();
                         actions.push(LALRAction { symbol, action, target_idx: c });
                         idx += 4;
                     }
                     let rec = LALRState::new(index, actions);
-                    // DEBUG println!("{}", rec);
-                    //egt.lalr_states.insert(index, rec); 
                     egt.lalr_states[index] = rec;
                 },
             }
         }
-        // validate egt (table counts, etc)
+        if let Some(record) = start_symbol {
+            let start = record.index(5, "symbol table", egt.symbols.len())?;
+            let name = egt.symbols[start].name.clone();
+            egt.properties.push(PropertyRecord::new(5, cgt::PARAMETERS[5].to_string(), name));
+        }
+        if cgt {
+            cgt::synthesize_groups(&mut egt);
+        }
+        let findings = egt.validate();
+        if !findings.is_empty() {
+            return Err(EgtError::Invalid(findings));
+        }
 
-        egt
+        Ok(egt)
     }
 
-    pub fn init(&mut self) {
-        assert!(self.pos == 0);
-        assert_eq!(self.read_string(), WString::from("GOLD Parser Tables/v5.0")); 
+    /// Checks the header and decodes `bytes` into `LogicalRecord`s.
+    pub fn init(&mut self) -> Result<(), EgtError> {
+        self.pos = 0;
+        self.records.clear();
+        let header = self.read_string()?;
+        if ![EnhancedGrammarTable::EGT_HEADER, EnhancedGrammarTable::CGT_HEADER].contains(&header.to_string().as_str()) {
+            return Err(EgtError::BadHeader(header.to_string()));
+        }
         
-        let mut entries = 0;
         while self.pos < self.bytes.len() {
             // Read for 'M'
-            let byte = self.read_byte();
-            assert_eq!(byte, 77);
-            //let entries = self.read_u16();
-            let lrec = self.read_logical_record();
-            entries += lrec.num_entries;
-            //println!("{:?}",lrec);
+            let offset = self.pos;
+            let byte = self.read_byte()?;
+            if byte != RecordType::Multi as u8 {
+                return Err(EgtError::BadRecord { offset, found: byte });
+            }
+            let lrec = self.read_logical_record()?;
             self.records.push(lrec);
         }
-        // DEBUG println!("Total Records: {} Entries: {}", self.records.len(), entries);
         self.initialized = true;
+        Ok(())
     }
 
     /// Call after consuming byte 77 ('M') in stream.
     /// * Returns a `LogicalRecord`
-    pub fn read_logical_record(&mut self) -> LogicalRecord {
-        let entries = self.read_u16();
-        let rectype = self.read_record_byte();
-       // println!("@{} record: {:?} entries: {}", self.pos, rectype, entries);
+    pub fn read_logical_record(&mut self) -> Result<LogicalRecord, EgtError> {
+        let entries = self.read_u16()?;
+        let rectype = self.read_record_byte()?;
         let mut lrec = LogicalRecord::new(entries,rectype);
 
         for _ in 1..lrec.num_entries {
-            let byte = self.read_byte();
-            let kind = EntryType::from_u8(byte).unwrap();
-            let entry = self.read_entry(kind);
-            // let e = self.read_record(rectype);
+            let offset = self.pos;
+            let byte = self.read_byte()?;
+            let kind = EntryType::from_u8(byte).ok_or(EgtError::BadEntry { offset, found: byte })?;
+            let entry = self.read_entry(kind)?;
             lrec.entries.push(entry);
+            lrec.offsets.push(offset);
         }
+        lrec.end = self.pos;
 
-        lrec
+        Ok(lrec)
     }
 
     #[inline(always)]
-    fn read_header(&mut self) -> WString<LE> {
+    fn read_header(&mut self) -> Result<WString<LE>, EgtError> {
         let opos = self.pos;
         self.pos = 0;
         let hdr = self.read_string();
@@ -264,91 +336,88 @@ impl Builder {
     }
 
     #[inline(always)]
-    fn read_record_byte(&mut self) -> RecordType {
-        self.read_byte();   // consume the 'b' byte
-        let byte = self.read_byte();
-        //println!("read_record_byte(): {:?}", RecordType::from_u8(byte).unwrap());
-        RecordType::from_u8(byte).unwrap()
+    fn read_record_byte(&mut self) -> Result<RecordType, EgtError> {
+        let offset = self.pos;
+        let kind = self.read_byte()?;   // consume the 'b' byte
+        if kind != EntryType::Byte as u8 {
+            return Err(EgtError::BadEntry { offset, found: kind });
+        }
+        let byte = self.read_byte()?;
+        RecordType::from_u8(byte).ok_or(EgtError::BadRecord { offset: offset + 1, found: byte })
     }
 
-    fn read_entry(&mut self, kind: EntryType) -> RecordEntry {
-        match kind {
+    fn read_entry(&mut self, kind: EntryType) -> Result<RecordEntry, EgtError> {
+        let entry = match kind {
             EntryType::Empty => RecordEntry::Empty,
-            EntryType::Byte => {
-                let b = self.read_byte();
-                let entry = RecordEntry::Byte(b);
-                //println!("@{} => {:?}", self.pos, entry);          
-                entry
-            },
-            EntryType::Boolean => {
-                let b = self.read_byte();
-                let entry = RecordEntry::Bool(b);
-                //println!("@{} => {:?}", self.pos, entry);                      
-                entry
-            },
-            EntryType::Integer =>  {
-                let i = self.read_u16();
-                let entry = RecordEntry::Integer(i);
-                //println!("@{} => {:?}", self.pos, entry);          
-                entry
-            },
-            EntryType::String => {
-                let s = self.read_string();
-                //let l = s.len() + 2;
-                let entry = RecordEntry::String(s);
-                //println!("@{} => {:?}", self.pos, entry);          
-                entry
-            },
-        }
+            EntryType::Byte => RecordEntry::Byte(self.read_byte()?),
+            EntryType::Boolean => RecordEntry::Bool(self.read_byte()?),
+            EntryType::Integer => RecordEntry::Integer(self.read_u16()?),
+            EntryType::String => RecordEntry::String(self.read_string()?),
+        };
+        Ok(entry)
     }
 
-    fn read_string(&mut self) -> WString<LE> {
+    fn read_string(&mut self) -> Result<WString<LE>, EgtError> {
         let start = self.pos;
-        while self.read_u16() != 0 {
+        while self.read_u16()? != 0 {
 
         }
-        let str = WString::from_utf16le((&self.bytes[start..self.pos-2]).to_vec());
-        //self.pos += 2; // adjust for 0x0000 terminal
-        str.unwrap()
+        WString::from_utf16le(self.bytes[start..self.pos-2].to_vec())
+            .map_err(|_| EgtError::InvalidString { offset: start })
     }
-    pub fn read_byte(&mut self) -> u8 {
+    pub fn read_byte(&mut self) -> Result<u8, EgtError> {
+        let byte = self.peek_byte()?;
         self.pos += 1;
-        self.bytes[self.pos-1]
+        Ok(byte)
     }
-    pub fn peek_byte(&self) -> u8 {
-        self.bytes[self.pos]
+    pub fn peek_byte(&self) -> Result<u8, EgtError> {
+        self.bytes.get(self.pos).copied().ok_or(EgtError::Truncated { offset: self.pos })
     }
-    pub fn peek_u16(&self) -> u16 {
-        (self.bytes[self.pos] as u16) | (self.bytes[self.pos+1] as u16) << 8
+    pub fn peek_u16(&self) -> Result<u16, EgtError> {
+        match self.bytes.get(self.pos..self.pos+2) {
+            Some(b) => Ok(u16::from_le_bytes([b[0], b[1]])),
+            None => Err(EgtError::Truncated { offset: self.pos }),
+        }
     }
-    pub fn read_u16(&mut self) -> u16 {
-    //    println!("read_u16: {} {}", self.bytes[pos], self.bytes[pos+1]);
-        let i = (self.bytes[self.pos] as u16) | (self.bytes[self.pos+1] as u16) << 8;
+    pub fn read_u16(&mut self) -> Result<u16, EgtError> {
+        let i = self.peek_u16()?;
         self.pos += 2;
-        i
+        Ok(i)
     }
 
-    pub fn load_egt_file(&mut self, file: OsString) -> Result<usize, std::io::Error> {
-        let mut file = File::open(file).unwrap();
-        file.read_to_end(&mut self.bytes)
+    pub fn load_egt_file<P: AsRef<Path>>(&mut self, file: P) -> Result<usize, EgtError> {
+        let mut file = File::open(file)?;
+        self.initialized = false;
+        Ok(file.read_to_end(&mut self.bytes)?)
     }
 
 }
 
+impl From<Vec<u8>> for Builder {
+    fn from(bytes: Vec<u8>) -> Self {
+        Builder {
+            bytes,
+            pos: 0,
+            records: Vec::new(),
+            initialized: false,
+        }
+    }
+}
+
 
 #[cfg(test)]
 pub mod test {
-    use core::panic;
-    use std::{path::PathBuf, fs::File, io::Read, borrow::Borrow};
+    use std::{fs::File, io::Read};
 
     use enum_primitive::FromPrimitive;
     use utf16string::WString;
 
-    use crate::engine::{RecordType, LogicalRecord, EntryType, RecordEntry};
+    use crate::engine::{RecordType, LogicalRecord, EntryType, RecordEntry, tables::Table,
+        EnhancedGrammarTable, SymbolType, Writer, Parser, parser::{GPParser, GPMessage}};
 
-    use super::Builder;
+    use super::{Builder, EgtError, AdvanceMode, EndingMode};
 
-    const FILE_NAME: &str = r"D:\Users\Gian\prog\repos\RUST\goldparser-rs\.ref\goldparser-test.egt";
+    const FILE_NAME: &str = crate::test::GP_SIMPLE_EGT;
 
 
 
@@ -370,22 +439,22 @@ pub mod test {
         let mut bldr = gen_builder();
         println!("Read {} bytes.", bldr.bytes.len());
         //println!("bytes[0] = {} bytes[1] = {}", bldr.bytes[0], bldr.bytes[1]);
-        let header = bldr.read_string();
+        let header = bldr.read_string().unwrap();
         //print!("header: {:?} => {:?}", header, header.as_bytes());
         assert_eq!(header, WString::from("GOLD Parser Tables/v5.0"));
     }
     #[test]
     fn init() {
         let mut bldr = gen_builder();
-        bldr.init();
+        bldr.init().unwrap();
 
     }
     #[test]
     fn to_egt() {
         let mut bldr = gen_builder();
-        bldr.init();
+        bldr.init().unwrap();
 
-        let egt = bldr.to_egt();
+        let egt = bldr.to_egt().unwrap();
         assert_eq!(egt.header,"GOLD Parser Tables/v5.0");
         println!("--------------------------------------------");
         //println!("{}",egt);
@@ -393,13 +462,13 @@ pub mod test {
     #[test]
     fn read_logical_record() {
         let mut bldr = gen_builder();
-        let header = bldr.read_string();
+        let header = bldr.read_string().unwrap();
         println!("header: {}", header.to_string());
         let mut entries = 0;
         while bldr.pos < bldr.bytes.len() {
-            let byte = bldr.read_byte();
+            let byte = bldr.read_byte().unwrap();
             if byte == 77 {
-                let lrc = bldr.read_logical_record();
+                let lrc = bldr.read_logical_record().unwrap();
                 println!("{:?} : {}", lrc.kind, lrc.num_entries-1);
                 entries += lrc.num_entries;
             }
@@ -409,16 +478,16 @@ pub mod test {
     #[test]
     fn read_logical_record_test() {
         let mut bldr = gen_builder();
-        let hdr = bldr.read_string();
+        let hdr = bldr.read_string().unwrap();
         //let mut pos = hdr.len() + 2;
-        let mut byte = bldr.read_byte();
+        let mut byte = bldr.read_byte().unwrap();
         assert_eq!(byte, 77);
-        let entries = bldr.read_u16();
+        let entries = bldr.read_u16().unwrap();
         println!("@{} record: {:?} entries: {}", bldr.pos, RecordType::from_u8(byte).unwrap(), entries);
         // advance position one + two bytes
         //pos += 3;
-        byte = bldr.read_byte();
-        byte = bldr.read_byte();
+        byte = bldr.read_byte().unwrap();
+        byte = bldr.read_byte().unwrap();
         let rectype: RecordType = RecordType::from_u8(byte).unwrap();
         println!("@{} => {:?}", bldr.pos, rectype);
         let mut lrec = LogicalRecord::new(entries,rectype);
@@ -426,9 +495,9 @@ pub mod test {
         match rectype {
             RecordType::Property => {  
                 for n in 1..lrec.num_entries {
-                    byte = bldr.read_byte();
+                    byte = bldr.read_byte().unwrap();
                     let kind = EntryType::from_u8(byte).unwrap();
-                    let entry = bldr.read_entry(kind);
+                    let entry = bldr.read_entry(kind).unwrap();
                     lrec.entries.push(entry);
                 }      
                 //println!("@{} => {:?}", pos, rectype);
@@ -447,10 +516,197 @@ pub mod test {
         println!("Logical Record: {:#?}", lrec);
     }
 
+    #[test]
+    fn missing_file() {
+        let err = Builder::from_path("no/such/grammar.egt").unwrap_err();
+        assert!(matches!(err, EgtError::Io(_)));
+    }
+    #[test]
+    fn bad_header() {
+        let mut bldr = gen_builder();
+        bldr.bytes[0] = b'X';
+        assert!(matches!(bldr.to_egt(), Err(EgtError::BadHeader(h)) if h.starts_with('X')));
+    }
+    #[test]
+    fn truncated() {
+        let mut bldr = gen_builder();
+        let len = bldr.bytes.len();
+        bldr.bytes.truncate(len - 3);
+        assert!(matches!(bldr.to_egt(), Err(EgtError::Truncated { .. })));
+    }
+    #[test]
+    fn unexpected_entry() {
+        let mut bldr = gen_builder();
+        bldr.init().unwrap();
+        // first property record: 'M' u16 'b' 'p' 'I' index ...
+        let offset = bldr.records[0].offsets[0];
+        assert_eq!(bldr.bytes[offset], EntryType::Integer as u8);
+        bldr.records[0].entries[0] = RecordEntry::Bool(1);
+        match bldr.to_egt() {
+            Err(EgtError::UnexpectedEntry { offset: at, record, expected, found }) => {
+                assert_eq!(at, offset);
+                assert_eq!(record, RecordType::Property);
+                assert_eq!(expected, EntryType::Integer);
+                assert_eq!(found, EntryType::Boolean);
+            },
+            other => panic!("expected UnexpectedEntry, got {:?}", other.err()),
+        }
+    }
+    #[test]
+    fn index_out_of_range() {
+        let mut bldr = gen_builder();
+        bldr.init().unwrap();
+        // point the first production's head at a symbol past the end of the table
+        let rec = bldr.records.iter().position(|r| r.kind == RecordType::Production).unwrap();
+        let offset = bldr.records[rec].offsets[1];
+        bldr.bytes[offset+1] = 0xFF;
+        bldr.bytes[offset+2] = 0x7F;
+        bldr.initialized = false;
+        match bldr.to_egt() {
+            Err(EgtError::IndexOutOfRange { offset: at, record, index, .. }) => {
+                assert_eq!(at, offset);
+                assert_eq!(record, RecordType::Production);
+                assert_eq!(index, 0x7FFF);
+            },
+            other => panic!("expected IndexOutOfRange, got {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn groups() {
+        let egt = Builder::from_path(crate::test::GP_TINY_EGT).unwrap().to_egt().unwrap();
+        assert_eq!(egt.groups.len(), 1);
+        let group = &egt.groups[0];
+        assert_eq!(group.name, "Comment Block");
+        assert_eq!(egt.symbols[group.container_idx].name, "Comment");
+        assert_eq!(egt.symbols[group.start_idx].name, "{");
+        assert_eq!(egt.symbols[group.end_idx].name, "}");
+        assert_eq!(group.advance_mode, AdvanceMode::Character);
+        assert_eq!(group.ending_mode, EndingMode::Closed);
+        assert!(group.nested.is_empty());
+    }
+
+    #[test]
+    fn from_bytes() {
+        const SIMPLE: &[u8] = include_bytes!("../../examples/simple.egt");
+        let egt = Builder::from_bytes(SIMPLE).to_egt().unwrap();
+        assert_eq!(egt.property("Name"), Some("Simple"));
+        let egt = Builder::from_reader(std::io::Cursor::new(SIMPLE)).unwrap().to_egt().unwrap();
+        assert_eq!(egt.symbols.len(), 36);
+        assert!(matches!(Builder::from_bytes(&SIMPLE[..10]).to_egt(), Err(EgtError::Truncated { .. })));
+    }
+
+    #[test]
+    fn cgt_block_comment() {
+        let egt = Builder::from_bytes(&tiny_cgt(false)).to_egt().unwrap();
+        assert_eq!(egt.header, "GOLD Parser Tables/v5.0");
+        assert_eq!(egt.properties.name(), Some("Tiny"));
+        assert_eq!(egt.properties.case_sensitive(), Some(false));
+        assert_eq!(egt.properties.start_symbol(), Some("program"));
+        assert_eq!(egt.groups.len(), 1);
+        let group = &egt.groups[0];
+        assert_eq!(group.name, "Comment Block");
+        assert_eq!(egt.symbols[group.container_idx].name, "Comment");
+        assert_eq!(egt.symbols[group.container_idx].kind, SymbolType::Noise);
+        assert_eq!(egt.symbols[group.start_idx].name, "{");
+        assert_eq!(egt.symbols[group.end_idx].name, "}");
+        assert_eq!(group.ending_mode, EndingMode::Closed);
+        assert!(group.can_nest(0));
+
+        let mut parser = Parser::from_grammar(egt);
+        parser.load_source_str("{ outer { inner } outer } read x; write x");
+        assert!(matches!(parser.parse(), GPMessage::Accept));
+    }
+    #[test]
+    fn cgt_line_comment() {
+        let egt = Builder::from_bytes(&tiny_cgt(true)).to_egt().unwrap();
+        assert_eq!(egt.groups.len(), 1);
+        let group = &egt.groups[0];
+        assert_eq!(group.name, "Comment Line");
+        assert_eq!(egt.symbols[group.start_idx].kind, SymbolType::GroupStart);
+        assert_eq!(egt.symbols[group.end_idx].name, "NewLine");
+        assert_eq!(group.ending_mode, EndingMode::Open);
+        assert_eq!(egt.counts.symtab as usize, egt.symbols.len());
+        assert_eq!(egt.counts.dfatab as usize, egt.dfa_states.len());
+
+        let mut parser = Parser::from_grammar(egt);
+        parser.load_source_str("{ read y; \r\nread x;  { trailing\nwrite x\n");
+        assert!(matches!(parser.parse(), GPMessage::Accept));
+    }
+    #[test]
+    fn cgt_bad_header() {
+        let mut bytes = tiny_cgt(false);
+        bytes[2*20] = b'2'; // "GOLD Parser Tables/v2.0"
+        assert!(matches!(Builder::from_bytes(&bytes).to_egt(), Err(EgtError::BadHeader(h)) if h.ends_with("v2.0")));
+    }
+
+    /// Writes Tiny.egt in the CGT v1.0 layout: no "Comment" container symbol or group table,
+    /// '{' and '}' as Comment Start/End, or '{' as Comment Line if `line_comment` is set.
+    fn tiny_cgt(line_comment: bool) -> Vec<u8> {
+        let egt = Builder::from_path(crate::test::GP_TINY_EGT).unwrap().to_egt().unwrap();
+        let container = egt.groups[0].container_idx;
+        let sym = |i: usize| RecordEntry::Integer(if i > container { i - 1 } else { i } as u16);
+        let int = |i: usize| RecordEntry::Integer(i as u16);
+        let string = |s: &str| RecordEntry::String(WString::from(s));
+        let record = |kind: RecordType, entries: Vec<RecordEntry>| {
+            let mut rec = LogicalRecord::new(entries.len() as u16 + 1, kind);
+            rec.entries = entries;
+            rec
+        };
+
+        let mut records = vec![
+            record(RecordType::Parameters, vec![
+                string(egt.properties.name().unwrap()), string(egt.properties.version().unwrap()),
+                string(egt.properties.author().unwrap()), string(egt.properties.about().unwrap()),
+                RecordEntry::Bool(0), sym(egt.productions[0].head.index),
+            ]),
+            record(RecordType::LegacyCounts, vec![
+                int(egt.symbols.len() - 1), int(egt.charset.len()), int(egt.productions.len()),
+                int(egt.dfa_states.len()), int(egt.lalr_states.len()),
+            ]),
+            record(RecordType::InitState, vec![int(0), int(0)]),
+        ];
+        for (i, set) in egt.charset.iter().enumerate() {
+            let chars = set.ranges().iter().flat_map(|r| r.clone()).collect::<String>();
+            records.push(record(RecordType::LegacyCharSet, vec![int(i), string(&chars)]));
+        }
+        for s in egt.symbols.iter().filter(|s| s.index != container) {
+            let kind = match s.kind {
+                SymbolType::GroupStart if line_comment => SymbolType::Deprecated,
+                kind => kind,
+            };
+            records.push(record(RecordType::Symbol, vec![sym(s.index), string(&s.name), RecordEntry::Integer(kind as u16)]));
+        }
+        for rule in egt.productions.iter() {
+            let mut entries = vec![int(rule.index), sym(rule.head.index), RecordEntry::Empty];
+            entries.extend(rule.symbols.iter().map(|s| sym(s.index)));
+            records.push(record(RecordType::Production, entries));
+        }
+        for state in egt.dfa_states.iter() {
+            let mut entries = vec![int(state.index), RecordEntry::Bool(state.accept as u8), sym(state.accept_symbol.index), RecordEntry::Empty];
+            for edge in &state.edges {
+                entries.extend([int(edge.charset_idx), int(edge.target_state), RecordEntry::Empty]);
+            }
+            records.push(record(RecordType::DFA, entries));
+        }
+        for state in egt.lalr_states.iter() {
+            let mut entries = vec![int(state.index), RecordEntry::Empty];
+            for action in &state.actions {
+                entries.extend([sym(action.symbol.index), RecordEntry::Integer(action.action as u16), int(action.target_idx), RecordEntry::Empty]);
+            }
+            records.push(record(RecordType::LALR, entries));
+        }
+
+        let mut writer = Writer::new();
+        writer.write_string(EnhancedGrammarTable::CGT_HEADER);
+        for rec in &records {
+            writer.write_logical_record(rec);
+        }
+        writer.into_bytes()
+    }
+
     pub fn gen_builder() -> Builder {
-        let file = PathBuf::from(FILE_NAME);
-        let bldr = Builder::new(file.into_os_string());
-        bldr
+        Builder::from_path(FILE_NAME).unwrap()
     }
 
 }
\ No newline at end of file
@@ -0,0 +1,150 @@
+//! Compiled Grammar Table (CGT) support
+//!
+//! GOLD Builder 3.x/4.x wrote `GOLD Parser Tables/v1.0` files. They use the same logical
+//! records as the EGT format with a few differences:
+//! * a single Parameters record ('P') instead of Property records
+//! * a Table Counts record ('T') without the group count
+//! * Character Set records ('C') holding a string of member characters
+//! * no group table, comments are described by the Comment Start, Comment End and
+//!   Comment Line symbol types instead
+//!
+//! The `Builder` decodes these records into an `EnhancedGrammarTable` and then calls
+//! `synthesize_groups` so the tokenizer sees the comments as ordinary lexical groups.
+//!
+//! http://goldparser.org/doc/cgt/index.htm
+
+use crate::engine::{
+    egt::EnhancedGrammarTable,
+    group::{LexicalGroup, AdvanceMode, EndingMode},
+    states::{DFAEdge, DFAState},
+    tables::Table,
+    CharacterSet, Symbol, SymbolType, SymbolTable,
+};
+
+/// Names of the Parameters record entries, in file order
+pub const PARAMETERS: [&str; 6] = ["Name", "Version", "Author", "About", "Case Sensitive", "Start Symbol"];
+
+/// Adds the `LexicalGroup`s implied by the CGT comment symbols.
+/// * Comment Start/Comment End become the nestable "Comment Block" group
+/// * Comment Line becomes the "Comment Line" group, closed by a `NewLine` symbol
+///
+/// Both groups share a new noise container symbol named "Comment".
+pub fn synthesize_groups(egt: &mut EnhancedGrammarTable) {
+    let start = egt.symbols.get_by_type(SymbolType::GroupStart).map(|s| s.index);
+    let end = egt.symbols.get_by_type(SymbolType::GroupEnd).map(|s| s.index);
+    let line = egt.symbols.get_by_type(SymbolType::Deprecated).map(|s| s.index);
+    if line.is_none() && (start.is_none() || end.is_none()) {
+        return;
+    }
+
+    let container = add_symbol(egt, "Comment", SymbolType::Noise);
+    if let (Some(start), Some(end)) = (start, end) {
+        // v1.0 engines counted Comment Start/End pairs, so block comments nest
+        let index = add_group(egt);
+        egt.groups.add(LexicalGroup::new(index, "Comment Block".to_string(), container, start, end,
+            AdvanceMode::Character, EndingMode::Closed, vec![index]));
+    }
+    if let Some(line) = line {
+        let name = egt.symbols[line].name.clone();
+        egt.symbols.add(Symbol::new(line, name, SymbolType::GroupStart));
+        let newline = newline_symbol(egt);
+        let index = add_group(egt);
+        egt.groups.add(LexicalGroup::new(index, "Comment Line".to_string(), container, line, newline,
+            AdvanceMode::Character, EndingMode::Open, vec![]));
+    }
+    relink_symbols(egt);
+}
+
+/// Returns the index of the grammar's `NewLine` terminal, adding one to the symbol table
+/// and DFA when the grammar does not define it.
+fn newline_symbol(egt: &mut EnhancedGrammarTable) -> usize {
+    if let Some(sym) = egt.symbols.get("NewLine".to_string()) {
+        return sym.index;
+    }
+    let newline = add_symbol(egt, "NewLine", SymbolType::Noise);
+    let accept = egt.symbols[newline].clone();
+
+    // {CR}{LF} | {CR} | {LF}
+    let cr = add_charset(egt, CharacterSet::from("\r"));
+    let lf = add_charset(egt, CharacterSet::from("\n"));
+    let cr_state = add_state(egt);
+    let lf_state = add_state(egt);
+    egt.dfa_states[lf_state] = DFAState::new(lf_state, true, accept.clone(), vec![]);
+    egt.dfa_states[cr_state] = DFAState::new(cr_state, true, accept, vec![
+        DFAEdge { chars: egt.charset[lf].clone(), charset_idx: lf, target_state: lf_state },
+    ]);
+
+    // Route CR and LF away from whatever the initial state used them for (usually whitespace)
+    let initial = egt.initial_states.dfa as usize;
+    let mut edges = Vec::new();
+    for mut edge in std::mem::take(&mut egt.dfa_states[initial].edges) {
+        if edge.chars.contains('\r') || edge.chars.contains('\n') {
+            let mut chars = edge.chars.clone();
+            chars.remove('\r');
+            chars.remove('\n');
+            if chars.ranges().is_empty() {
+                continue;
+            }
+            edge.charset_idx = add_charset(egt, chars.clone());
+            edge.chars = chars;
+        }
+        edges.push(edge);
+    }
+    edges.push(DFAEdge { chars: egt.charset[cr].clone(), charset_idx: cr, target_state: cr_state });
+    edges.push(DFAEdge { chars: egt.charset[lf].clone(), charset_idx: lf, target_state: lf_state });
+    egt.dfa_states[initial].edges = edges;
+
+    newline
+}
+
+fn add_symbol(egt: &mut EnhancedGrammarTable, name: &str, kind: SymbolType) -> usize {
+    let index = egt.symbols.len();
+    egt.counts.symtab += 1;
+    egt.resize();
+    egt.symbols.add(Symbol::new(index, name.to_string(), kind));
+    index
+}
+fn add_charset(egt: &mut EnhancedGrammarTable, chars: CharacterSet) -> usize {
+    let index = egt.charset.len();
+    egt.counts.charset += 1;
+    egt.resize();
+    egt.charset.add(index, chars);
+    index
+}
+fn add_state(egt: &mut EnhancedGrammarTable) -> usize {
+    let index = egt.dfa_states.len();
+    egt.counts.dfatab += 1;
+    egt.resize();
+    index
+}
+fn add_group(egt: &mut EnhancedGrammarTable) -> usize {
+    let index = egt.groups.len();
+    egt.counts.lexgroups += 1;
+    egt.resize();
+    index
+}
+
+/// The DFA, LALR and production tables hold copies of their symbols. Refreshes those
+/// copies after symbol kinds have been changed.
+fn relink_symbols(egt: &mut EnhancedGrammarTable) {
+    for idx in 0..egt.dfa_states.len() {
+        let state = &egt.dfa_states[idx];
+        if state.accept {
+            let sym = egt.symbols[state.accept_symbol.index].clone();
+            egt.dfa_states[idx].accept_symbol = sym;
+        }
+    }
+    for idx in 0..egt.lalr_states.len() {
+        let actions = &mut egt.lalr_states[idx].actions;
+        for action in actions.iter_mut() {
+            action.symbol = egt.symbols[action.symbol.index].clone();
+        }
+    }
+    for idx in 0..egt.productions.len() {
+        let rule = &egt.productions[idx];
+        let head = egt.symbols[rule.head.index].clone();
+        let symbols = rule.symbols.iter().map(|s| egt.symbols[s.index].clone()).collect::<Vec<_>>();
+        egt.productions[idx].head = head;
+        egt.productions[idx].symbols = SymbolTable::from(symbols);
+    }
+}
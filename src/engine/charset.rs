@@ -37,26 +37,103 @@ impl CharacterSet {
         }).collect::<String>())       
     }
     pub fn ranges(&self) -> &CharacterRange { &self.0 }
-    ///TODO Merges ranges and overlapping intervals
-    // pub fn concat(&mut self) -> String {
-    //     let mut catstr = String::new();
-    //     for range in &self.0 {
-    //         let (start, end) = range.into_inner();
-    //         // if start == end {
-    //         //     catstr.push(start);
-    //         //     //return catstr;
-    //         // }
-    //     }
-    //     // self.0.iter().map(|mut r| {
-            
-
-    //     // });
-    //     catstr
-    //     //let ranges = self.0.sort_by(|a,b| a.cmp(&b));
-    //     //let ranges = self.0.concat().
-    // }
+    /// Decodes a range as stored in an EGT character set record. `start` and `end` are the
+    /// low 16 bits of code points on Unicode `plane`. The surrogate block 0xD800-0xDFFF holds no
+    /// `char`s, so a range touching it is trimmed and a range inside it yields `None`.
+    pub fn decode_range(plane: u16, start: u16, end: u16) -> Option<RangeInclusive<char>> {
+        let base = (plane as u32) << 16;
+        let (mut start, mut end) = (base | start as u32, base | end as u32);
+        if (0xD800..=0xDFFF).contains(&start) { start = 0xE000; }
+        if (0xD800..=0xDFFF).contains(&end) { end = 0xD7FF; }
+        let range = char::from_u32(start)?..=char::from_u32(end)?;
+        (!range.is_empty()).then_some(range)
+    }
+    /// Removes `ch` from the set, splitting the range that contains it
+    pub fn remove(&mut self, ch: char) {
+        let mut ranges = CharacterRange::with_capacity(self.0.len() + 1);
+        for range in self.0.drain(..) {
+            if !range.contains(&ch) {
+                ranges.push(range);
+                continue;
+            }
+            let (start, end) = range.into_inner();
+            if let Some(before) = pred(ch).filter(|_| start < ch) {
+                ranges.push(start..=before);
+            }
+            if let Some(after) = succ(ch).filter(|_| ch < end) {
+                ranges.push(after..=end);
+            }
+        }
+        self.0 = ranges;
+    }
+    pub fn is_empty(&self) -> bool { self.0.is_empty() }
+    /// Sorts the ranges and merges overlapping and adjacent ones
+    pub fn normalize(&mut self) {
+        self.0.sort_unstable_by_key(|r| *r.start());
+        let mut ranges = CharacterRange::with_capacity(self.0.len());
+        for range in self.0.drain(..).filter(|r| !r.is_empty()) {
+            match ranges.last_mut() {
+                Some(last) if succ(*last.end()).is_none_or(|next| next >= *range.start()) => {
+                    *last = *last.start()..=cmp::max(*last.end(), *range.end());
+                },
+                _ => ranges.push(range),
+            }
+        }
+        self.0 = ranges;
+    }
+    /// Adds `other` to the set, merging it with the ranges it overlaps
     pub fn merge(&mut self, other: &RangeInclusive<char>) {
-        todo!()
+        self.0.push(other.clone());
+        self.normalize();
+    }
+    /// Characters in either set
+    pub fn union(&self, other: &CharacterSet) -> CharacterSet {
+        let mut set = CharacterSet(self.0.iter().chain(other.0.iter()).cloned().collect());
+        set.normalize();
+        set
+    }
+    /// Characters in this set that are not in `other`
+    pub fn difference(&self, other: &CharacterSet) -> CharacterSet {
+        let (mut set, mut other) = (self.clone(), other.clone());
+        set.normalize();
+        other.normalize();
+        let mut ranges = CharacterRange::new();
+        for range in set.0 {
+            let (first, end) = range.into_inner();
+            let (mut start, mut rest) = (first, true);
+            for hole in other.0.iter().filter(|h| *h.start() <= end && *h.end() >= first) {
+                if *hole.start() > start {
+                    ranges.push(start..=pred(*hole.start()).unwrap());
+                }
+                match succ(*hole.end()) {
+                    Some(next) if next <= end => start = next,
+                    _ => { rest = false; break; },
+                }
+            }
+            if rest {
+                ranges.push(start..=end);
+            }
+        }
+        CharacterSet(ranges)
+    }
+    /// Characters in both sets
+    pub fn intersection(&self, other: &CharacterSet) -> CharacterSet {
+        self.difference(&self.difference(other))
+    }
+}
+
+/// The next `char`, stepping over the surrogate block
+pub(crate) fn succ(ch: char) -> Option<char> {
+    match ch {
+        '\u{D7FF}' => Some('\u{E000}'),
+        _ => char::from_u32(ch as u32 + 1),
+    }
+}
+/// The previous `char`, stepping over the surrogate block
+pub(crate) fn pred(ch: char) -> Option<char> {
+    match ch {
+        '\u{E000}' => Some('\u{D7FF}'),
+        _ => char::from_u32((ch as u32).checked_sub(1)?),
     }
 }
 
@@ -79,6 +156,24 @@ impl std::fmt::Display for CharacterSet {
     }
 }
 
+/// Builds a set from a string of member characters, as stored by CGT v1.0 files.
+/// Runs of consecutive characters are collapsed into a single range.
+impl From<&str> for CharacterSet {
+    fn from(chars: &str) -> Self {
+        let mut members = chars.chars().collect::<Vec<_>>();
+        members.sort_unstable();
+        members.dedup();
+        let mut ranges = CharacterRange::new();
+        for ch in members {
+            match ranges.last_mut() {
+                Some(last) if *last.end() as u32 + 1 == ch as u32 => *last = *last.start()..=ch,
+                _ => ranges.push(ch..=ch),
+            }
+        }
+        CharacterSet(ranges)
+    }
+}
+
 // use std::vec::Vec;
 // impl PartialEq for CharacterSet {
 //     fn eq(&self, other: &Self) -> bool {
@@ -128,6 +223,49 @@ pub mod test {
         assert!(charset.contains(char::from_u32(180_u32).unwrap()) == false);
     }
     #[test]
+    fn decode_range() {
+        assert_eq!(CharacterSet::decode_range(0, 0x41, 0x5A), Some('A'..='Z'));
+        assert_eq!(CharacterSet::decode_range(1, 0xF600, 0xF64F), Some('\u{1F600}'..='\u{1F64F}'));
+        assert_eq!(CharacterSet::decode_range(2, 0x0000, 0xA6DF), Some('\u{20000}'..='\u{2A6DF}'));
+        assert_eq!(CharacterSet::decode_range(0, 0xD000, 0xDBFF), Some('\u{D000}'..='\u{D7FF}'));
+        assert_eq!(CharacterSet::decode_range(0, 0xDC00, 0xFFFF), Some('\u{E000}'..='\u{FFFF}'));
+        assert_eq!(CharacterSet::decode_range(0, 0xD800, 0xDFFF), None);
+        assert_eq!(CharacterSet::decode_range(17, 0, 1), None);
+
+        let set = CharacterSet::new(vec![CharacterSet::decode_range(0, 0xD7FF, 0xE000).unwrap()]);
+        assert!(set.contains('\u{D7FF}') && set.contains('\u{E000}'));
+    }
+    #[test]
+    fn from_str() {
+        let charset = CharacterSet::from("dcba z\u{1F600}b");
+        assert_eq!(charset.ranges(), &vec![' '..=' ', 'a'..='d', 'z'..='z', '\u{1F600}'..='\u{1F600}']);
+    }
+    #[test]
+    fn set_operations() {
+        let letters = CharacterSet::new(vec!['a'..='z', 'A'..='Z']);
+        let vowels = CharacterSet::from("aeiou");
+        let consonants = letters.difference(&vowels);
+        assert!(consonants.contains('b') && consonants.contains('Z') && !consonants.contains('e'));
+        assert_eq!(consonants.ranges().len(), 6);
+        assert_eq!(consonants.union(&vowels), CharacterSet::new(vec!['A'..='Z', 'a'..='z']));
+        assert_eq!(letters.intersection(&CharacterSet::from("0aZ")).ranges(), &vec!['Z'..='Z', 'a'..='a']);
+
+        let mut set = CharacterSet::new(vec!['\u{E000}'..='\u{FFFF}', '\0'..='\u{D7FF}']);
+        set.normalize();
+        assert_eq!(set.ranges(), &vec!['\0'..='\u{FFFF}']);
+        assert!(set.difference(&set).is_empty());
+        set.merge(&('\u{10000}'..='\u{10FFFF}'));
+        assert_eq!(set.ranges(), &vec!['\0'..=char::MAX]);
+    }
+    #[test]
+    fn remove() {
+        let mut charset = gen_charset();
+        charset.remove('\u{0B}');
+        charset.remove(' ');
+        assert_eq!(charset.ranges(), &vec!['\t'..='\t', '\u{0C}'..='\u{0C}', '\u{A0}'..='\u{A0}']);
+        assert!(!charset.contains('\u{0B}'));
+    }
+    #[test]
     fn default() {
         let charset = gen_charset();
         let mut defset = CharacterSet::default();
@@ -1,31 +1,29 @@
-use std::{fmt::Display, collections::HashMap};
+use std::fmt::Display;
 
 use crate::{
     engine::{
-        property::PropertyRecord, 
+        property::GrammarProperties, 
         counts::TableCountsRecord, 
-        charset::CharacterSet, 
-        Symbol, SymbolTable,
-        group::LexicalGroup, 
-        production::ProductionRule, 
-        states::{InitialStatesRecord, DFAState, LALRState},
+        Symbol, SymbolTable, SymbolType,
+        group::{EndingMode, GroupStep, LexicalGroup},
+        states::InitialStatesRecord,
         tables::{Table, CharacterSetTable},
-        builder::Builder,
+        builder::{Builder, EgtError},
     }
 };
 
-use super::tables::{ProductionTable, DFAStateTable, LALRStateTable};
+use super::tables::{ProductionTable, DFAStateTable, LALRStateTable, GroupTable};
 
 
 
 
 pub struct EnhancedGrammarTable {
     pub header: String,
-    pub properties: Vec<PropertyRecord>,
+    pub properties: GrammarProperties,
     pub counts: TableCountsRecord,
     pub charset: CharacterSetTable, //Vec<CharacterSet>,
     pub symbols: SymbolTable,
-    pub groups: Vec<LexicalGroup>,
+    pub groups: GroupTable,
     //pub productions: Vec<ProductionRule>,
     pub productions: ProductionTable,
     pub initial_states: InitialStatesRecord,
@@ -36,18 +34,19 @@ pub struct EnhancedGrammarTable {
 }
 
 impl EnhancedGrammarTable {
-    const EGT_HEADER: &str = "GOLD Parser Tables/v5.0";
+    pub const EGT_HEADER: &str = "GOLD Parser Tables/v5.0";
+    /// Header of the legacy CGT format, which `Builder` converts on load
+    pub const CGT_HEADER: &str = "GOLD Parser Tables/v1.0";
 
     pub fn new(header: String) -> Self {
-        assert_eq!(header,Self::EGT_HEADER);
 
         EnhancedGrammarTable { 
             header,
-            properties: Vec::new(),
+            properties: GrammarProperties::new(),
             counts: TableCountsRecord::default(), //TableCountsRecord {symtab: 0, charset: 0, rules: 0, dfatab: 0, lalrtab: 0, lexgroups: 0 },
             charset: CharacterSetTable::new(),
             symbols: SymbolTable::new(),
-            groups: Vec::new(),
+            groups: GroupTable::new(),
             productions: ProductionTable::new(),
             initial_states: InitialStatesRecord { dfa: 0, lalr: 0 },
             dfa_states: DFAStateTable::new(),
@@ -57,18 +56,34 @@ impl EnhancedGrammarTable {
     
     /// 
     /// Searches (name,value) pairs by name and returns value
-    pub fn property(&self, name: &str) -> &String {
-        for rec in self.properties.as_slice() {
-            if rec.name == name {
-                return &rec.value;
-            }
+    pub fn property(&self, name: &str) -> Option<&str> {
+        self.properties.get(name)
+    }
+
+    /// Returns the `LexicalGroup` whose start symbol is the symbol at `symbol_idx`
+    pub fn group_by_start(&self, symbol_idx: usize) -> Option<&LexicalGroup> {
+        self.groups.iter().find(|g| g.start_idx == symbol_idx)
+    }
+
+    /// How a token of `symbol` is read when `top` is the innermost open group. A group start
+    /// opens a group outside any group, or inside one that lists it as nestable.
+    pub fn group_step(&self, top: Option<&LexicalGroup>, symbol: &Symbol) -> GroupStep<'_> {
+        let start = match symbol.kind {
+            SymbolType::GroupStart => self.group_by_start(symbol.index),
+            _ => None,
+        };
+        match (top, start) {
+            (None, Some(group)) => GroupStep::Start(group),
+            (None, None) => GroupStep::Token,
+            (Some(top), Some(group)) if top.can_nest(group.index) => GroupStep::Start(group),
+            (Some(top), _) if symbol.index == top.end_idx => GroupStep::End { closed: top.ending_mode == EndingMode::Closed },
+            (Some(_), _) if symbol.kind == SymbolType::EndOfFile => GroupStep::Runaway,
+            (Some(top), _) => GroupStep::Advance(top.advance_mode),
         }
-        // we should return an Option<> and None here
-        panic!("Parameter({}): Not Found",name)       
     }
 
     pub fn properties_as_string(&self) -> String {
-        self.properties.iter().map(|p| {format!("{} = {}\n",p.name,p.value)}).collect::<String>()      
+        self.properties.to_string()
     }
 
     #[inline(always)]
@@ -78,6 +93,7 @@ impl EnhancedGrammarTable {
         self.productions.resize(self.counts.rules as usize);
         self.dfa_states.resize(self.counts.dfatab as usize);
         self.lalr_states.resize(self.counts.lalrtab as usize);
+        self.groups.resize(self.counts.lexgroups as usize);
     
     }
     
@@ -90,20 +106,22 @@ impl EnhancedGrammarTable {
     }
 }
 
-/// The `Builder` must have already called `Builder::init()`
-impl From<Builder> for EnhancedGrammarTable {
-    fn from(mut builder: Builder) -> Self {
+/// Decodes the tables held by the `Builder`, calling `Builder::init()` if needed
+impl TryFrom<Builder> for EnhancedGrammarTable {
+    type Error = EgtError;
+
+    fn try_from(mut builder: Builder) -> Result<Self, Self::Error> {
         builder.to_egt()
     }
 }
 
 impl Display for EnhancedGrammarTable {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f,"[Properties]\n{}\n",self.properties.iter().map(|p| {format!("{} = {}\n",p.name,p.value)}).collect::<String>())?;
+        write!(f,"[Properties]\n{}\n",self.properties)?;
         write!(f,"[Total Counts]\n{}\n\n", self.counts)?;
         write!(f,"[Character Sets]\n{}\n\n", self.charset)?;
         write!(f,"[Symbols]\n{}\n", self.symbols.to_string())?;
-        write!(f,"[Groups]\n{}\n", "self.groups")?;
+        write!(f,"[Groups]\n{}\n", self.groups)?;
         write!(f,"[Productions]\n{}\n", self.productions)?;
         write!(f,"[Initial States]\n{}\n", self.initial_states)?;
         write!(f,"[DFA States]\n{}\n", self.dfa_states)?;
@@ -131,11 +149,11 @@ mod test {
     #[test]
     fn from_builder() {
         let mut bldr = gen_builder();
-        bldr.init();
-        let egt = bldr.to_egt();
+        bldr.init().unwrap();
+        let egt = bldr.to_egt().unwrap();
         assert_eq!(egt.header.to_string(),"GOLD Parser Tables/v5.0");
         println!("OK");
-        let egt = EnhancedGrammarTable::from(bldr);
+        let egt = EnhancedGrammarTable::try_from(bldr).unwrap();
         assert_eq!(egt.header.to_string(),"GOLD Parser Tables/v5.0");
         println!("OK");
 
@@ -154,8 +172,8 @@ mod test {
 
     fn gen_egt() -> EnhancedGrammarTable {
         let mut bldr = gen_builder();
-        bldr.init();
-        bldr.to_egt()
+        bldr.init().unwrap();
+        bldr.to_egt().unwrap()
     }
 
 }
\ No newline at end of file
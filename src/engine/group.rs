@@ -2,8 +2,15 @@
 //! 
 //! http://goldparser.org/doc/egt/record-group.htm
 //! 
+//! | Byte | Integer | String | Integer   | Integer | Integer | Integer | Integer | Empty | Integer | 0..n Integer |
+//! |  'g' |  index  |  name  | container |  start  |   end   | advance | ending  | rsvd  | nesting | group_idx    |
 
+use std::fmt::Display;
 
+use enum_primitive::enum_from_primitive;
+
+
+#[derive(Debug,Default,Clone)]
 /// Group records occur after all the Symbol Records. The record is preceded 
 /// by a byte field that contains the value 103, the ASCII code for the letter 'g'
 pub struct LexicalGroup {
@@ -29,21 +36,69 @@ pub struct LexicalGroup {
 }
 impl LexicalGroup {
     const CODE: u8 = 103; //'g';
-    
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(index: usize, name: String, container_idx: usize, start_idx: usize, end_idx: usize,
+        advance_mode: AdvanceMode, ending_mode: EndingMode, nested: Vec<usize>) -> Self {
+        LexicalGroup {
+            index, name, container_idx, start_idx, end_idx, advance_mode, ending_mode,
+            nesting_count: nested.len(),
+            nested,
+        }
+    }
+    /// Can the group with table index `group` be nested inside this one?
+    pub fn can_nest(&self, group: usize) -> bool {
+        self.nested.contains(&group)
+    }
 }
 
-/// `AdvanceMode`
-pub enum AdvanceMode {
-    /// The group will advance a Token at a time
+/// What the tokenizer does with a token it reads, given the innermost open `LexicalGroup`.
+/// The parser and the `Lexer` both read groups this way.
+#[derive(Debug, Clone, Copy)]
+pub enum GroupStep<'g> {
+    /// No group is open. The token is returned as read.
     Token,
-    /// The group will advance by one character at a time
-    Character,
+    /// The token starts a group, nested in the open one if there is one
+    Start(&'g LexicalGroup),
+    /// The token ends the innermost group, and is part of it if `closed`
+    End { closed: bool },
+    /// The source ended with a group still open
+    Runaway,
+    /// The token is inside the innermost group, which takes all of it or only its first
+    /// character
+    Advance(AdvanceMode),
+}
+
+impl Display for LexicalGroup {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f,"[Group {:3}] {} Container: {} Start: {} End: {} Advance: {:?} Ending: {:?} Nesting: {:?}",
+            self.index, self.name, self.container_idx, self.start_idx, self.end_idx,
+            self.advance_mode, self.ending_mode, self.nested)
+    }
+}
+
+enum_from_primitive! {
+    #[derive(Debug,Default,Clone,Copy,PartialEq,Eq)]
+    #[repr(u16)]
+    /// `AdvanceMode`
+    pub enum AdvanceMode {
+        /// The group will advance a Token at a time
+        #[default]
+        Token = 0,
+        /// The group will advance by one character at a time
+        Character = 1,
+    }
 }
 
-/// `EndingMode`
-pub enum EndingMode {
-    /// The ending symbol will be left on the input queue
-    Open,
-    /// The ending symbol will be consumed
-    Closed,
-}
\ No newline at end of file
+enum_from_primitive! {
+    #[derive(Debug,Default,Clone,Copy,PartialEq,Eq)]
+    #[repr(u16)]
+    /// `EndingMode`
+    pub enum EndingMode {
+        /// The ending symbol will be left on the input queue
+        #[default]
+        Open = 0,
+        /// The ending symbol will be consumed
+        Closed = 1,
+    }
+}
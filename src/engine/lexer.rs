@@ -0,0 +1,280 @@
+//! Lexer
+//!
+//! Tokenizes text with the DFA of a grammar table, without the LALR parser. `Lexer`
+//! borrows the `EnhancedGrammarTable` and the source, and is an `Iterator` of `Token`s
+//! carrying their symbol, text, line and column, and byte span. Noise tokens can be
+//! filtered out, and lexical groups can be returned whole or as the tokens they are read as,
+//! for syntax highlighting and other token-level tooling. Groups are read as the parser
+//! reads them, see `EnhancedGrammarTable::group_step`.
+//!
+//! ```no_run
+//! use goldparser::engine::{Builder, Lexer};
+//!
+//! let egt = Builder::from_path("examples/simple.egt").and_then(|mut b| b.to_egt()).unwrap();
+//! for token in Lexer::new(&egt, "display 'Hello'").noise(false) {
+//!     println!("{} {:?} {:?}", token.name(), token.text, token.span);
+//! }
+//! ```
+
+use crate::engine::{
+    group::{AdvanceMode, GroupStep},
+    token::Token,
+    CharacterMapping, Columns, EnhancedGrammarTable, LexicalGroup, Position, Symbol, SymbolType,
+};
+
+/// Runs the DFA from its initial state over `input`, and returns the symbol accepted by the
+/// longest match and its length in characters
+pub(crate) fn longest_match(
+    grammar: &EnhancedGrammarTable,
+    input: impl IntoIterator<Item = char>,
+) -> Option<(&Symbol, usize)> {
+    let mut state = grammar.initial_states.dfa as usize;
+    let mut accepted = None;
+    for (length, ch) in input.into_iter().enumerate() {
+        match grammar.dfa_states[state].find_edge(ch) {
+            Some(target) => {
+                state = target;
+                let target = &grammar.dfa_states[target];
+                if target.accept {
+                    accepted = Some((&target.accept_symbol, length + 1));
+                }
+            },
+            None => break,
+        }
+    }
+    accepted
+}
+
+/// An `Iterator` of the `Token`s of a source text
+///
+/// Text the DFA does not match is returned one character at a time as tokens of the
+/// `Error` symbol, and reading continues after it. A lexical group still open at the end of
+/// the text, which the parser reports as a `GroupError`, is returned whole as a token of the
+/// `Error` symbol. The iterator ends at the end of the text; no `EndOfFile` token is returned.
+pub struct Lexer<'a> {
+    grammar: &'a EnhancedGrammarTable,
+    source: &'a str,
+    mapping: CharacterMapping,
+    /// Whether `Noise` tokens are returned
+    noise: bool,
+    /// Whether lexical groups are returned as one token
+    groups: bool,
+    columns: Columns,
+    /// Byte offset of the next token
+    offset: usize,
+    /// Line and column of the next token
+    pos: Position,
+}
+
+impl<'a> Lexer<'a> {
+    /// Creates a `Lexer` for `source` that returns noise tokens and whole groups
+    pub fn new(grammar: &'a EnhancedGrammarTable, source: &'a str) -> Self {
+        Lexer {
+            grammar,
+            source,
+            mapping: grammar.properties.character_mapping().unwrap_or_default(),
+            noise: true,
+            groups: true,
+            columns: Columns::default(),
+            offset: 0,
+            pos: Position(1, 1),
+        }
+    }
+
+    /// Sets whether `Noise` tokens, such as whitespace and comments, are returned
+    pub fn noise(mut self, noise: bool) -> Self {
+        self.noise = noise;
+        self
+    }
+
+    /// Sets whether a lexical group is returned as one token of its container symbol, as the
+    /// parser reads it. Otherwise the group start, its contents and its end are returned as
+    /// the separate tokens the DFA reads.
+    pub fn groups(mut self, groups: bool) -> Self {
+        self.groups = groups;
+        self
+    }
+
+    /// Sets how token columns are counted, characters by default
+    pub fn columns(mut self, columns: Columns) -> Self {
+        self.columns = columns;
+        self
+    }
+
+    /// Line and column of the next token, both starting at 1
+    pub fn position(&self) -> Position {
+        self.pos
+    }
+
+    /// The symbol of the token at `offset` and the byte offset of its end, or `None` at the
+    /// end of the source
+    fn match_at(&self, offset: usize) -> Option<(&'a Symbol, usize)> {
+        let rest = &self.source[offset..];
+        let first = rest.chars().next()?;
+        let chars = rest.chars().map(|ch| self.mapping.map(ch));
+        match longest_match(self.grammar, chars) {
+            Some((symbol, length)) => Some((symbol, offset + rest.chars().take(length).map(char::len_utf8).sum::<usize>())),
+            None => {
+                let error = self.grammar.symbols.get_by_type(SymbolType::Error)?;
+                Some((error, offset + first.len_utf8()))
+            },
+        }
+    }
+
+    /// Moves past the text up to `end`, counting lines and columns
+    fn advance(&mut self, end: usize) {
+        let mut after_cr = self.source[..self.offset].ends_with('\r');
+        for ch in self.source[self.offset..end].chars() {
+            self.pos.advance(ch, after_cr, self.columns);
+            after_cr = ch == '\r';
+        }
+        self.offset = end;
+    }
+
+    /// Moves past a lexical group that was started by the text up to `end`, and returns
+    /// whether it ends before the end of the source
+    fn skip_group(&mut self, group: &'a LexicalGroup, end: usize) -> bool {
+        let mut open = vec![group];
+        self.advance(end);
+        while let Some(&top) = open.last() {
+            let Some((symbol, end)) = self.match_at(self.offset) else { return false };
+            match self.grammar.group_step(Some(top), symbol) {
+                GroupStep::Start(nested) => {
+                    open.push(nested);
+                    self.advance(end);
+                },
+                GroupStep::End { closed } => {
+                    // an open ending is left to be read after the group
+                    if closed {
+                        self.advance(end);
+                    }
+                    open.pop();
+                },
+                GroupStep::Advance(AdvanceMode::Token) => self.advance(end),
+                GroupStep::Advance(AdvanceMode::Character) => {
+                    let next = self.source[self.offset..].chars().next().map_or(0, char::len_utf8);
+                    self.advance(self.offset + next);
+                },
+                GroupStep::Token | GroupStep::Runaway => return false,
+            }
+        }
+        true
+    }
+
+    /// Reads the next token, noise included
+    fn read(&mut self) -> Option<Token> {
+        let (start, pos) = (self.offset, self.pos);
+        let (mut symbol, end) = self.match_at(start)?;
+        match self.grammar.group_step(None, symbol) {
+            GroupStep::Start(group) if self.groups => {
+                symbol = match self.skip_group(group, end) {
+                    true => &self.grammar.symbols[group.container_idx],
+                    false => self.grammar.symbols.get_by_type(SymbolType::Error).unwrap_or(symbol),
+                };
+            },
+            _ => self.advance(end),
+        }
+        let mut token = Token::new(symbol.clone(), self.source[start..self.offset].to_string());
+        token.pos = pos;
+        token.span = start..self.offset;
+        Some(token)
+    }
+}
+
+impl Iterator for Lexer<'_> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        loop {
+            let token = self.read()?;
+            if self.noise || *token.kind() != SymbolType::Noise {
+                return Some(token);
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::{engine::{Builder, ColumnUnit, Columns, SymbolType}, grammar::compile};
+
+    use super::Lexer;
+
+    #[test]
+    fn simple() {
+        let egt = Builder::from_path(crate::test::GP_SIMPLE_EGT).unwrap().to_egt().unwrap();
+        let source = "display 'Hello'\r\nassign n = 1.5";
+        let tokens = Lexer::new(&egt, source).noise(false).collect::<Vec<_>>();
+        let texts = tokens.iter().map(|t| t.text.as_str()).collect::<Vec<_>>();
+        assert_eq!(texts, vec!["display", "'Hello'", "assign", "n", "=", "1.5"]);
+        assert_eq!(tokens[1].name(), "StringLiteral");
+        assert_eq!(tokens[1].span, 8..15);
+        assert_eq!(&source[tokens[1].span.clone()], "'Hello'");
+        assert_eq!((tokens[1].pos.line(), tokens[1].pos.col()), (1, 9));
+        assert_eq!((tokens[3].pos.line(), tokens[3].pos.col()), (2, 8));
+
+        // whitespace is returned unless filtered
+        let all = Lexer::new(&egt, source).collect::<Vec<_>>();
+        assert_eq!(all.len(), 11);
+        assert_eq!(all[1].kind(), &SymbolType::Noise);
+        assert_eq!(all.iter().map(|t| t.text.as_str()).collect::<String>(), source);
+    }
+
+    #[test]
+    fn columns() {
+        let egt = Builder::from_path(crate::test::GP_SIMPLE_EGT).unwrap().to_egt().unwrap();
+        let source = "display '~'\r\tassign 🦀 = 1";
+        let cols = |columns: Columns| Lexer::new(&egt, source).noise(false).columns(columns)
+            .map(|t| (t.pos.line(), t.pos.col())).collect::<Vec<_>>();
+        assert_eq!(cols(Columns::default()), vec![(1, 1), (1, 9), (2, 2), (2, 9), (2, 11), (2, 13)]);
+        assert_eq!(cols(Columns::new(ColumnUnit::Char, 4)), vec![(1, 1), (1, 9), (2, 5), (2, 12), (2, 14), (2, 16)]);
+        assert_eq!(cols(Columns::new(ColumnUnit::Utf16, 0)), vec![(1, 1), (1, 9), (2, 2), (2, 9), (2, 12), (2, 14)]);
+    }
+
+    #[test]
+    fn errors() {
+        let egt = Builder::from_path(crate::test::GP_SIMPLE_EGT).unwrap().to_egt().unwrap();
+        let tokens = Lexer::new(&egt, "a ~é b").noise(false).collect::<Vec<_>>();
+        let kinds = tokens.iter().map(|t| *t.kind()).collect::<Vec<_>>();
+        assert_eq!(kinds, vec![SymbolType::Terminal, SymbolType::Error, SymbolType::Error, SymbolType::Terminal]);
+        assert_eq!(tokens[2].span, 3..5);
+        assert_eq!(tokens[3].text, "b");
+    }
+
+    #[test]
+    fn groups() {
+        let egt = compile(concat!(
+            "\"Start Symbol\" = <List>\n",
+            "Comment Line = '//'\n",
+            "Comment Start = '/*'\n",
+            "Comment End = '*/'\n",
+            "Id = {Letter}+\n",
+            "<List> ::= <List> Id |\n",
+        )).unwrap();
+        let source = "a /* b */ c // d\ne";
+
+        let tokens = Lexer::new(&egt, source).noise(false).collect::<Vec<_>>();
+        let texts = tokens.iter().map(|t| t.text.as_str()).collect::<Vec<_>>();
+        assert_eq!(texts, vec!["a", "c", "e"]);
+
+        let tokens = Lexer::new(&egt, source).filter(|t| t.name() == "Comment").collect::<Vec<_>>();
+        let texts = tokens.iter().map(|t| t.text.as_str()).collect::<Vec<_>>();
+        assert_eq!(texts, vec!["/* b */", "// d"]);
+        assert_eq!(tokens[1].span, 12..16);
+
+        // without group handling the start and end symbols are returned as read
+        let tokens = Lexer::new(&egt, source).noise(false).groups(false).collect::<Vec<_>>();
+        let texts = tokens.iter().map(|t| t.text.as_str()).collect::<Vec<_>>();
+        assert_eq!(texts, vec!["a", "/*", "b", "*/", "c", "//", "d", "e"]);
+
+        // an unterminated group runs to the end of the source, and is an error
+        let tokens = Lexer::new(&egt, "a /* b").collect::<Vec<_>>();
+        assert_eq!(tokens.last().unwrap().text, "/* b");
+        assert_eq!(tokens.last().unwrap().kind(), &SymbolType::Error);
+        let tokens = Lexer::new(&egt, "a /* b */ c /* d").noise(false).collect::<Vec<_>>();
+        assert_eq!(tokens.iter().map(|t| t.text.as_str()).collect::<Vec<_>>(), vec!["a", "c", "/* d"]);
+        assert_eq!(tokens[2].kind(), &SymbolType::Error);
+        assert_eq!(tokens[2].span, 12..16);
+    }
+}
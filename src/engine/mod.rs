@@ -39,12 +39,16 @@ pub mod states;
 pub mod token;
 pub mod reduction;
 pub mod builder;
+pub mod writer;
+pub mod cgt;
+pub mod validate;
 pub mod egt;
 pub mod source;
 pub mod parser;
+pub mod lexer;
 
 pub use stack::Stack;
-pub use property::PropertyRecord;
+pub use property::{PropertyRecord, GrammarProperties, CharacterMapping};
 pub use counts::TableCountsRecord;
 pub use charset::{CharacterSet};
 pub use symbol::{Symbol, SymbolType};
@@ -54,8 +58,11 @@ pub use states::{InitialStatesRecord, DFAState, DFAEdge, LALRState, LALRAction};
 pub use tables::{SymbolTable};
 pub use source::SourceReader;
 pub use parser::Parser;
+pub use lexer::Lexer;
 pub use egt::EnhancedGrammarTable;
-pub use builder::Builder;
+pub use builder::{Builder, EgtError};
+pub use writer::{Writer, WriteError};
+pub use validate::Finding;
 
 
 use self::token::Token;
@@ -97,6 +104,9 @@ enum_from_primitive! {
         InitState   = 73, // 'I'
         DFA         = 68,       // 'D'
         LALR        = 76,      // 'L'
+        Parameters  = 80, // 'P' CGT v1.0
+        LegacyCounts = 84, // 'T' CGT v1.0
+        LegacyCharSet = 67, // 'C' CGT v1.0
     }
 }
 
@@ -110,50 +120,46 @@ pub enum RecordEntry {
 }
 
 impl RecordEntry {
-    pub fn byte(&self) -> u8 {
+    /// The `EntryType` this entry was decoded from
+    pub fn kind(&self) -> EntryType {
         match self {
-            RecordEntry::Byte(b) => *b,
-            _ => panic!("RecordEntry::byte() {:?}", self)
+            RecordEntry::Empty => EntryType::Empty,
+            RecordEntry::Byte(_) => EntryType::Byte,
+            RecordEntry::Bool(_) => EntryType::Boolean,
+            RecordEntry::Integer(_) => EntryType::Integer,
+            RecordEntry::String(_) => EntryType::String,
         }
     }
-    pub fn bool(&self) -> bool {
+    pub fn byte(&self) -> Option<u8> {
         match self {
-            RecordEntry::Bool(b) => {
-                if *b != 0u8 { true } else { false }
-            },
-            _ => panic!()
+            RecordEntry::Byte(b) => Some(*b),
+            _ => None,
+        }
+    }
+    pub fn bool(&self) -> Option<bool> {
+        match self {
+            RecordEntry::Bool(b) => Some(*b != 0u8),
+            _ => None,
         }
     }
-    pub fn integer(&self) -> u16 {
+    pub fn integer(&self) -> Option<u16> {
         match self {
-            RecordEntry::Integer(i) => { /* println!("integer(): {}", *i); */ *i},
-            _ => panic!()
+            RecordEntry::Integer(i) => Some(*i),
+            _ => None,
         }
     }
     #[inline(always)]
-    pub fn as_usize(&self) -> usize { self.integer() as usize }
-    pub fn string(&self) -> String {
+    pub fn as_usize(&self) -> Option<usize> { self.integer().map(usize::from) }
+    pub fn string(&self) -> Option<String> {
         match self {
-            RecordEntry::String(i) =>  {
-                //let mut wstr:  WString<LE> = WString::from(i);
-                //i.clone_into(&mut &wstr);
-                
-                //println!("string(): {}",i.to_string()); //, wstr.to_string());
-                i.to_string() //wstr
-            },
-            _ => panic!()
+            RecordEntry::String(i) => Some(i.to_string()),
+            _ => None,
         }
     }
-    pub fn wstring(&self) -> WString<LE> {
+    pub fn wstring(&self) -> Option<WString<LE>> {
         match self {
-            RecordEntry::String(i) => {
-                let rets = i.deref().to_string();
-                WString::from(&rets)
-               // let ret = i.clone();
-               // let rret = i.clone_into(&mut ret.clone());
-                //ret.deref().to_string()
-            },
-            _ => panic!("wstring(): error")
+            RecordEntry::String(i) => Some(WString::from(i.deref().to_string().as_str())),
+            _ => None,
         }
     }
 } 
@@ -163,6 +169,10 @@ pub struct LogicalRecord {
     pub num_entries: u16,
     pub kind: RecordType,
     pub entries: Vec<RecordEntry>,
+    /// Byte offset in the EGT file of each entry in `entries`
+    pub offsets: Vec<usize>,
+    /// Byte offset in the EGT file of the end of this record
+    pub end: usize,
 }
 impl LogicalRecord {
     pub fn new(num: u16, kind: RecordType) -> Self {
@@ -170,6 +180,49 @@ impl LogicalRecord {
             num_entries: num,
             kind,
             entries: Vec::new(),
+            offsets: Vec::new(),
+            end: 0,
+        }
+    }
+
+    /// Byte offset of entry `idx`. Entries past the end of the record report the end of the record.
+    pub fn offset(&self, idx: usize) -> usize {
+        self.offsets.get(idx).copied().unwrap_or(self.end)
+    }
+
+    /// Returns entry `idx`, or `EgtError::Truncated` if the record is too short
+    pub fn entry(&self, idx: usize) -> Result<&RecordEntry, EgtError> {
+        self.entries.get(idx).ok_or(EgtError::Truncated { offset: self.offset(idx) })
+    }
+
+    fn mismatch(&self, idx: usize, expected: EntryType) -> EgtError {
+        EgtError::UnexpectedEntry {
+            offset: self.offset(idx),
+            record: self.kind,
+            expected,
+            found: self.entries[idx].kind(),
+        }
+    }
+
+    /// Entry `idx` as a `u16` integer
+    pub fn integer(&self, idx: usize) -> Result<u16, EgtError> {
+        self.entry(idx)?.integer().ok_or_else(|| self.mismatch(idx, EntryType::Integer))
+    }
+    /// Entry `idx` as a `bool`
+    pub fn bool(&self, idx: usize) -> Result<bool, EgtError> {
+        self.entry(idx)?.bool().ok_or_else(|| self.mismatch(idx, EntryType::Boolean))
+    }
+    /// Entry `idx` as a `String`
+    pub fn string(&self, idx: usize) -> Result<String, EgtError> {
+        self.entry(idx)?.string().ok_or_else(|| self.mismatch(idx, EntryType::String))
+    }
+    /// Entry `idx` as an index into a table named `table` holding `len` items
+    pub fn index(&self, idx: usize, table: &'static str, len: usize) -> Result<usize, EgtError> {
+        let index = self.integer(idx)? as usize;
+        if index < len {
+            Ok(index)
+        } else {
+            Err(EgtError::IndexOutOfRange { offset: self.offset(idx), record: self.kind, table, index, len })
         }
     }
 }
@@ -191,6 +244,15 @@ impl Position {
     }
     pub fn inc_col(&mut self) { self.1 += 1; }
     pub fn inc_line(&mut self) { self.0 += 1; self.1 = 1; }
+    /// Moves past `ch`. Lines end at LF, CR LF or a lone CR: `after_cr` tells whether the
+    /// character before was a CR, so the LF of a CR LF does not start another line.
+    pub fn advance(&mut self, ch: char, after_cr: bool, columns: Columns) {
+        match ch {
+            '\n' if after_cr => {},
+            '\n' | '\r' => self.inc_line(),
+            ch => self.1 = columns.next(self.1, ch),
+        }
+    }
 
     pub fn to_string(&self) -> String {
         format!(" [{}, {}]", self.line(), self.col())
@@ -202,6 +264,41 @@ impl Position {
 
 }
 
+/// The unit columns are counted in
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub enum ColumnUnit {
+    /// Unicode scalar values, as Rust `char`s
+    #[default]
+    Char,
+    /// UTF-8 bytes
+    Utf8,
+    /// UTF-16 code units, as the Language Server Protocol expects
+    Utf16,
+}
+
+/// How a `Position` counts columns: the unit, and the tab stops
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub struct Columns {
+    pub unit: ColumnUnit,
+    /// A tab moves to the column after the next multiple of `tab_width`. 0, the default,
+    /// counts a tab as any other character.
+    pub tab_width: usize,
+}
+impl Columns {
+    pub fn new(unit: ColumnUnit, tab_width: usize) -> Self {
+        Columns { unit, tab_width }
+    }
+    /// The column after `ch` when it is read at column `col`
+    pub fn next(&self, col: usize, ch: char) -> usize {
+        match (ch, self.unit) {
+            ('\t', _) if self.tab_width > 0 => (col.saturating_sub(1) / self.tab_width + 1) * self.tab_width + 1,
+            (_, ColumnUnit::Char) => col + 1,
+            (ch, ColumnUnit::Utf8) => col + ch.len_utf8(),
+            (ch, ColumnUnit::Utf16) => col + ch.len_utf16(),
+        }
+    }
+}
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub struct LineColSpan(Position, Position);
 impl LineColSpan {
@@ -220,12 +317,62 @@ impl LineColSpan {
     }
 }
 
-/// Returns the line and column of the given `pos` and `input`
-// pub(crate) fn line_col(input: &str, pos: usize, start: (usize,usize)) -> (usize,usize) {
-//     let slice = &input[..pos];
-//     // TODO (see pest line_col())
-//   //  let prec_ln = memrchr(b'\n', slice.as_bytes());
-// }
+/// Converts byte offsets in a source text to lines and columns, both starting at 1.
+/// Lines end at LF, CR LF or a lone CR, and columns are counted as `Columns` says,
+/// characters by default.
+///
+/// The start of every line is recorded once, so the line of an offset is found in O(log n)
+/// of the number of lines. UTF-8 columns without tab stops are then the byte distance from
+/// the start of the line; other columns are counted over the line up to the offset.
+#[derive(Debug, Clone)]
+pub struct LineIndex<'i> {
+    input: &'i str,
+    /// Byte offsets of the first character of each line
+    starts: Vec<usize>,
+    columns: Columns,
+}
+impl<'i> LineIndex<'i> {
+    pub fn new(input: &'i str) -> Self {
+        let bytes = input.as_bytes();
+        let mut starts = vec![0];
+        for (i, byte) in bytes.iter().enumerate() {
+            match byte {
+                b'\r' if bytes.get(i + 1) == Some(&b'\n') => {},
+                b'\r' | b'\n' => starts.push(i + 1),
+                _ => {},
+            }
+        }
+        LineIndex { input, starts, columns: Columns::default() }
+    }
+    /// Sets how columns are counted
+    pub fn columns(mut self, columns: Columns) -> Self {
+        self.columns = columns;
+        self
+    }
+    /// Number of lines, counting an empty last line
+    pub fn lines(&self) -> usize {
+        self.starts.len()
+    }
+    /// Line and column of the byte at `offset`. Offsets inside a character or past the end
+    /// are clamped to the character before them.
+    pub fn line_col(&self, offset: usize) -> Position {
+        let mut offset = offset.min(self.input.len());
+        while !self.input.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        let line = self.starts.partition_point(|&start| start <= offset);
+        let text = &self.input[self.starts[line - 1]..offset];
+        let col = match self.columns {
+            Columns { unit: ColumnUnit::Utf8, tab_width: 0 } => text.len() + 1,
+            columns => text.chars().fold(1, |col, ch| columns.next(col, ch)),
+        };
+        Position(line, col)
+    }
+    /// Byte offset of the start of `line`, counting from 1
+    pub fn line_start(&self, line: usize) -> Option<usize> {
+        self.starts.get(line.checked_sub(1)?).copied()
+    }
+}
 
 #[derive(Clone,Copy)]
 pub struct Cursor<'i> {
@@ -256,13 +403,12 @@ impl<'i> Cursor<'i> {
             panic!("span created from positions from different inputs")
         }
     }
-    // #[inline]
-    // pub fn line_col(&self) -> (usize, usize) {
-    //     if self.pos > self.input.len() {
-    //         panic!("position out of bounds");
-    //     }
-
-    // }
+    /// Line and column of the cursor. Scans the input, so use a `LineIndex` for many lookups.
+    #[inline]
+    pub fn line_col(&self) -> (usize, usize) {
+        let pos = LineIndex::new(self.input).line_col(self.pos);
+        (pos.line(), pos.col())
+    }
 }
 
 #[derive(Clone,Copy)]
@@ -282,6 +428,14 @@ impl<'i> Span<'i> {
         }
         Span { input, start: usize::MAX, end: usize::MAX }
     }
+    #[inline]
+    pub fn start(&self) -> usize { self.start }
+    #[inline]
+    pub fn end(&self) -> usize { self.end }
+    /// The text of the span, or `""` for a span that was not valid in its input
+    pub fn as_str(&self) -> &'i str {
+        self.input.get(self.start..self.end).unwrap_or_default()
+    }
 }
 
 
@@ -333,3 +487,48 @@ pub trait RuleParser<R: RuleType> {
     /// Parses `&str`
     fn parse(rule: R, input: &str) -> Result<(Token, R), Error>;
 }
+
+
+#[cfg(test)]
+mod test {
+    use super::{ColumnUnit, Columns, Cursor, LineIndex, Span};
+
+    #[test]
+    fn line_index() {
+        let input = "ab\r\ncd\nxé\rz\n";
+        let index = LineIndex::new(input);
+        assert_eq!(index.lines(), 5);
+        let at = |offset| { let pos = index.line_col(offset); (pos.line(), pos.col()) };
+        assert_eq!(at(0), (1, 1));
+        assert_eq!(at(2), (1, 3));
+        assert_eq!(at(4), (2, 1));
+        assert_eq!(at(8), (3, 2));
+        // inside the two bytes of é
+        assert_eq!(at(9), (3, 2));
+        assert_eq!(at(11), (4, 1));
+        assert_eq!(at(100), (5, 1));
+        assert_eq!(index.line_start(3), Some(7));
+        assert_eq!(index.line_start(0), None);
+
+        let cursor = Cursor::new(input, 11).unwrap();
+        assert_eq!(cursor.line_col(), (4, 1));
+        assert_eq!(Cursor::from_start(input).span(&cursor).as_str(), "ab\r\ncd\nxé\r");
+        assert_eq!(Span::new(input, 4, 6).as_str(), "cd");
+    }
+
+    #[test]
+    fn columns() {
+        let tabs = Columns::new(ColumnUnit::Char, 4);
+        assert_eq!(tabs.next(1, '\t'), 5);
+        assert_eq!(tabs.next(3, '\t'), 5);
+        assert_eq!(tabs.next(5, '\t'), 9);
+        assert_eq!(Columns::default().next(3, '\t'), 4);
+
+        let input = "\tx = \"é🦀\" y";
+        let at = |columns: Columns| LineIndex::new(input).columns(columns).line_col(input.find('y').unwrap()).col();
+        assert_eq!(at(Columns::default()), 11);
+        assert_eq!(at(tabs), 14);
+        assert_eq!(at(Columns::new(ColumnUnit::Utf8, 0)), 15);
+        assert_eq!(at(Columns::new(ColumnUnit::Utf16, 0)), 12);
+    }
+}
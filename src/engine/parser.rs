@@ -3,27 +3,26 @@
 //! Defines `GPParser` trait, as well as the messages that are passed during lexing and parsing.
 
 
-use std::collections::HashMap;
 use std::fmt::{Display};
 use std::{fs};
-use std::path::PathBuf;
+use std::io::Read;
 
 use super::egt::EnhancedGrammarTable;
 use super::reduction::Reduction;
 use crate::engine::states::ActionType;
-use crate::engine::{LALRState, Stack, Position, Symbol, SymbolType, DFAState, reduction};
-use crate::engine::tables::{GroupTable, Table};
+use crate::engine::{LALRState, Stack, Position, Symbol, SymbolType, reduction};
+use crate::engine::tables::Table;
+use crate::engine::group::{LexicalGroup, AdvanceMode, GroupStep};
 use crate::engine::token::{Token};
 use super::source::SourceReader;
-use super::{Builder, SymbolTable};
+use super::lexer::longest_match;
+use super::{Builder, EgtError, SymbolTable};
 
 /// Trait for exposing granular parsing methods
 pub trait GPParser {
     /// Load the grammar EGT 5.0
-    fn load_grammar(grammar: String) -> EnhancedGrammarTable {
-        let file = PathBuf::from(grammar);
-        let mut bldr = Builder::new(file.into_os_string());
-        bldr.init();
+    fn load_grammar(grammar: String) -> Result<EnhancedGrammarTable, EgtError> {
+        let mut bldr = Builder::from_path(grammar)?;
         bldr.to_egt()
     }
 
@@ -85,7 +84,8 @@ pub enum GPMessage {
     NotLoadedError,          //No grammar is loaded
     LexicalError,            //Token not recognized
     SyntaxError,             //Token is not expected
-    GroupError,              //Reached the end of the file - mostly due to being stuck in comment mode
+    GroupError,              //Reached the end of the file - mostly due to being stuck in comment mode.
+                             //The current position is set to the start of the runaway group.
     InternalError,           //Something is wrong, very wrong
 }
 
@@ -103,9 +103,15 @@ pub enum GPParseResult {
 #[derive(Debug)]
 pub enum ParserError {
     Format(GPMessage),
+    Grammar(EgtError),
     ParseIntError(::std::num::ParseIntError),
     ParseFloatError(::std::num::ParseFloatError),
 }
+impl From<EgtError> for ParserError {
+    fn from(value: EgtError) -> Self {
+        ParserError::Grammar(value)
+    }
+}
 impl Display for ParserError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "ParserError {:?}", self)  
@@ -126,8 +132,6 @@ pub struct Parser {
     /// `SourceReader` responsible for opening the source file and maintaining
     /// the lookahead buffer.
     pub source: SourceReader,
-
-    pub properties: HashMap<String,String>,
     
     /// Symbols recognized by the system
     //pub symbols: SymbolTable, // from grammar.symbols
@@ -148,9 +152,8 @@ pub struct Parser {
     /// *LALR Parser Stack* | Will be used during reduction
     pub stack: Stack<Token>,
 
-    /// Lexical groups
+    /// Lexical groups currently open, innermost on top
     group: Stack<Token>,
-    groups: GroupTable,
 
     // Reductions
     /// **TODO** For *Reductions*
@@ -174,22 +177,21 @@ impl Parser {
     pub const PARSER_NAME: &str = "GOLD Parser Engine";
     pub const PARSER_VERSION: &str = "5.0.3";
 
-    pub fn new(egt: String) -> Self {
-        let grammar = Self::load_grammar(egt);
-        //let source = Self::load_source(src);
+    /// Creates a `Parser` for the EGT file at path `egt`
+    pub fn new(egt: String) -> Result<Self, ParserError> {
+        let grammar = Self::load_grammar(egt)?;
+        Ok(Self::from_grammar(grammar))
+    }
 
-        let mut properties: HashMap<String,String> = HashMap::new();
-        for rec in &grammar.properties {
-            properties.insert(rec.name.to_string(), rec.value.to_string());
-        }
+    /// Creates a `Parser` for an already loaded grammar, e.g. one decoded with
+    /// `Builder::from_bytes(include_bytes!("lang.egt"))`
+    pub fn from_grammar(grammar: EnhancedGrammarTable) -> Self {
         Parser {
             grammar,
             source: Default::default(),
-            properties,
             curr_state: 0,
             stack: Stack::new(),
             group: Stack::new(),
-            groups: GroupTable::new(),
             expected_symbols: SymbolTable::new(),
             have_reduction: false,
             trim_reductions: false,
@@ -207,12 +209,9 @@ impl Parser {
     pub fn line(&self) -> usize { self.curr_position.line() }
 
     pub fn about(&self) -> String {
-        self.properties.get("About").expect("No About property").to_string()
+        self.grammar.properties.about().unwrap_or_default().to_string()
     }
 
-    fn get_dfa_state(&self, index: usize) -> &DFAState {
-        &self.grammar.dfa_states[index]
-    }
     fn get_lalr_state(&self, state: usize) -> &LALRState {
         &self.grammar.lalr_states[state]
     }
@@ -226,13 +225,27 @@ impl Parser {
         }
     }
 
-    /// Loads the parse tables from the specified `source` as `String`
+    /// Loads the source file at path `source`. The file is read as it is parsed.
     pub fn load_source(&mut self, source: String)  -> Result<(), ParserError> {
-        let src = <Parser as GPParser>::load_source(source)?;
-        self.source.load(src);
-        self.initialized = true;
+        let file = fs::File::open(source).map_err(|_| ParserError::Format(GPMessage::NotLoadedError))?;
+        self.load_reader(file);
         Ok(())
     }
+    /// Loads the source text to be parsed from a stream, which is read in chunks as it is
+    /// parsed. Only the unconsumed part of the last chunk and the lookahead are kept in memory.
+    pub fn load_reader<R: Read + 'static>(&mut self, source: R) {
+        self.source.load_reader(source);
+        self.source.mapping = self.grammar.properties.character_mapping().unwrap_or_default();
+        self.restart();
+        self.initialized = true;
+    }
+    /// Loads the source text to be parsed from memory
+    pub fn load_source_str(&mut self, source: &str) {
+        self.source.load(source.to_string());
+        self.source.mapping = self.grammar.properties.character_mapping().unwrap_or_default();
+        self.restart();
+        self.initialized = true;
+    }
     pub fn clear(&mut self) {
         self.reset();
     }
@@ -260,25 +273,84 @@ impl Parser {
         }
     }
 
-    /// Wraps `<GPParser>::input_token`, manages group blocks, consumes
-    /// the lookahead buffer and //pushes the token onto the `input_tokens` stack.
+    /// Returns the `LexicalGroup` started by the parent symbol of `token`, if any
+    fn token_group(&self, token: &Token) -> Option<&LexicalGroup> {
+        self.grammar.group_by_start(token.symbol.index)
+    }
+
+    /// Wraps `<GPParser>::input_token`, manages group blocks and consumes
+    /// the lookahead buffer.
+    ///
+    /// When a `GroupStart` symbol is read, the token is pushed onto the group stack
+    /// and the text that follows is appended to it, a token or a character at a time
+    /// depending on the group's `AdvanceMode`, until the group's end symbol is read.
+    /// Groups listed in the current group's nesting list start a nested group. The
+    /// outermost group is returned as a single token of the group's container symbol.
+    /// If the end of file is reached inside a group, the `EndOfFile` token is returned
+    /// with the group stack still populated.
     pub fn produce_token(&mut self) -> Token {
         trace!("produce_token");
-        let mut nested_group = false;
-        let mut tok = self.input_token();
-        debug!("Token: \'{}\'",&tok.text);
-
-        
-
-        if nested_group {
-
-        } else {
-            let len = tok.text.len();
-            self.source.consume_buf(len);
+        loop {
+            let read = self.input_token();
+            debug!("Token: \'{}\'",&read.text);
+
+            let top = self.group.peek().and_then(|top| self.grammar.group_by_start(top.symbol.index));
+            match self.grammar.group_step(top, &read.symbol) {
+                GroupStep::Start(_) => {
+                    self.source.consume_buf(read.text.chars().count());
+                    self.group.push(read);
+                },
+                GroupStep::Token => {
+                    // The token is ready to be analyzed
+                    self.source.consume_buf(read.text.chars().count());
+                    return read;
+                },
+                GroupStep::End { closed } => {
+                    // End the current group
+                    let mut pop = self.group.pop().unwrap();
+                    if closed {
+                        pop.text.push_str(&read.text);
+                        self.source.consume_buf(read.text.chars().count());
+                    }
+                    match self.group.peek_mut() {
+                        // Append the group text to the enclosing group
+                        Some(top) => top.text.push_str(&pop.text),
+                        // We are out of the group. The popped token contains all of the group text
+                        None => {
+                            let container = self.token_group(&pop).unwrap().container_idx;
+                            pop.symbol = self.grammar.symbols[container].clone();
+                            pop.span.end = self.source.get_offset();
+                            return pop;
+                        },
+                    }
+                },
+                // Always stop on EOF. `parse_step()` reports the runaway group.
+                GroupStep::Runaway => return read,
+                // We are in a group. Append to the token on top of the stack.
+                GroupStep::Advance(AdvanceMode::Token) => {
+                    self.group.peek_mut().unwrap().text.push_str(&read.text);
+                    self.source.consume_buf(read.text.chars().count());
+                },
+                GroupStep::Advance(AdvanceMode::Character) => {
+                    if let Some(ch) = read.text.chars().next() {
+                        self.group.peek_mut().unwrap().text.push(ch);
+                        self.source.consume_buf(1);
+                    }
+                },
+            }
         }
-        //self.input_tokens.push(tok.to_owned());
-        //debug!("Pushed {} onto input token queue.",tok.text());
-        tok       
+    }
+
+    /// Clears the parse state and pushes the initial LALR state onto the stack
+    fn restart(&mut self) {
+        self.curr_state = self.grammar.initial_states.lalr as usize;
+        self.stack.clear();
+        self.group.clear();
+        self.input_tokens.clear();
+        self.expected_symbols.clear();
+        self.have_reduction = false;
+        self.curr_position.clear();
+        self.stack.push(Token { lalr_state: self.curr_state, ..Default::default() });
     }
 
 }
@@ -330,64 +402,62 @@ impl GPParser for Parser {
         trace!("parse_step()");
 
         while !done {
-            if self.input_tokens.len() == 0 { // get next Token from DFA lexer
+            if self.input_tokens.is_empty() { // get next Token from DFA lexer
                 trace!("Getting token from DFA");
                 let token = self.produce_token();
-                let kind = token.kind();
-                //self.input_tokens.push(token);
-
-                // handle case where an unterminated comment block consumes program
-                if *kind == SymbolType::EndOfFile && self.group.is_empty() {
-                    result = GPMessage::Empty;
-                } else { // a good Token was read
-                    result = GPMessage::TokenRead;
-                }
+                self.input_tokens.push(token);
+                result = GPMessage::TokenRead;
                 done = true;
-            
             } else { // a Token is present and can be parsed
                 trace!("Token is present");
                 let mut token = self.input_tokens.peek().expect("peek with input tokens").clone();
                 let kind = token.kind();
                 self.curr_position = token.pos;
 
-                match kind {
-                    SymbolType::Noise => {  // whitespace and other ignorables
-                        trace!("SymbolType::Noise");
-                        self.input_tokens.pop();
-                    },
-                    SymbolType::EndOfFile => { 
-                        if !self.group.is_empty() { // runaway group
-                            result = GPMessage::GroupError;
+                if let Some(start) = self.group.peek() {
+                    // runaway group. Report where it started.
+                    self.curr_position = start.pos;
+                    result = GPMessage::GroupError;
+                    done = true;
+                } else {
+                    match kind {
+                        SymbolType::Noise => {  // whitespace and other ignorables
+                            trace!("SymbolType::Noise");
+                            self.input_tokens.pop();
+                        },
+                        SymbolType::Error => {
+                            result = GPMessage::LexicalError;
                             done = true;
-                        } else  {
-                            result = GPMessage::Empty;
-                        }
-                    },
-                     SymbolType::Error => {
-                        result = GPMessage::LexicalError;
-                        done = true;
-                    },
-                    _ => {  // LALR parsing of the input Token
-                        trace!("Parsing input token");
-                        let parsemsg = self.parse_token(&mut token);
-                        match parsemsg {
-                            // TODO I think I have to do something here
-                            GPParseResult::Shift => {
-                                self.input_tokens.pop();
-                            },
-                            GPParseResult::SyntaxError => {
-                                panic!("Syntax error on parse_token()");
-                            },
-                            // GPParseResult::Reduce => {
-                            //     println!("LALR parser return Reduce");
-                            // },
-                            _ => { // fallthru includes reduce/eliminated
-                                   // shift, and trim-reduced
-                                   // do nothing
-                                   //println!("{:?}",parsemsg);
-                            },
-                        }
-                    },
+                        },
+                        _ => {  // LALR parsing of the input Token
+                            trace!("Parsing input token");
+                            match self.parse_token(&mut token) {
+                                GPParseResult::Accept => {
+                                    result = GPMessage::Accept;
+                                    done = true;
+                                },
+                                GPParseResult::InternalError => {
+                                    result = GPMessage::InternalError;
+                                    done = true;
+                                },
+                                GPParseResult::Reduce => {
+                                    result = GPMessage::Reduction;
+                                    done = true;
+                                },
+                                GPParseResult::Shift => {
+                                    // The token now lives on the LALR stack
+                                    self.input_tokens.pop();
+                                },
+                                GPParseResult::SyntaxError => {
+                                    result = GPMessage::SyntaxError;
+                                    done = true;
+                                },
+                                _ => { // fallthru includes trim-reduced
+                                       // do nothing
+                                },
+                            }
+                        },
+                    }
                 }
             }
         }
@@ -397,12 +467,26 @@ impl GPParser for Parser {
 
     fn parse_token(&mut self, input_token: &mut Token) -> GPParseResult {
         trace!("parse_token({})",&input_token.text);
-        let mut result = GPParseResult::Undefined;
+        let mut result;
         self.have_reduction = false;
         let parent_symbol = &input_token.symbol;
-        let parse_action = self.get_lalr_state(self.curr_state)
-                                            .find_action(parent_symbol)
-                                            .expect("Problems fetching LALRAction from LALRState");
+        let parse_action = match self.get_lalr_state(self.curr_state).find_action(parent_symbol) {
+            Some(action) => action.clone(),
+            None => {
+                // Syntax error. Generate a list of expected symbols to report
+                self.expected_symbols.clear();
+                for action in &self.grammar.lalr_states[self.curr_state].actions {
+                    match action.symbol.kind {
+                        SymbolType::Terminal | SymbolType::EndOfFile |
+                        SymbolType::GroupStart | SymbolType::GroupEnd => {
+                            self.expected_symbols.push(action.symbol.clone());
+                        },
+                        _ => {},
+                    }
+                }
+                return GPParseResult::SyntaxError;
+            },
+        };
         match parse_action.action {
             // Creates a new reduction. Pops all the terminals and non-terminals for
             // this rule and push the most left non-terminal.
@@ -413,7 +497,7 @@ impl GPParser for Parser {
                 // a non-terminal Token
                 let rule = &self.grammar.productions[parse_action.target_idx];
                 // Create a new non-terminal to represent the reduction
-                let mut head = Token::default();
+                let mut head;
                 
                 // If the rule has only a non-terminal then we don't create a reduction
                 // node for this rule in the tree since its not useful. If the user enabled 
@@ -426,15 +510,30 @@ impl GPParser for Parser {
                     result = GPParseResult::ReduceTrimmed;
                 } else { // create a new reduction for the current rule
                     self.have_reduction = true;
-                    let n = rule.symbols.len(); // n - 1;
-                    let mut reduce_tokens: Vec<Token> = vec![];
+                    let n = rule.symbols.len();
+                    let mut reduce_tokens: Vec<Token> = Vec::with_capacity(n);
                     // pop the tokens off the stack for the reduced rule
-                    for i in n..0 {
-                        reduce_tokens[i] = self.stack.pop().expect("empty stack");
+                    for _ in 0..n {
+                        reduce_tokens.push(self.stack.pop().expect("empty stack"));
                     }
+                    reduce_tokens.reverse();
+                    // flatten EBNF lists and options back into the rule that uses them
+                    let reduce_tokens = reduce_tokens.into_iter().flat_map(|token| match token.reduction {
+                        Some(reduction) if token.symbol.is_generated() => reduction.tokens,
+                        _ => vec![token],
+                    }).collect();
+                    let mut reduction = reduction::reduce(rule, reduce_tokens);
                     head = Token::new(rule.head.to_owned(), String::from(""));
-                    //head.reduction = Some(Reduction::new(rule, reduce_tokens));
-                    head.reduction = Some(reduction::reduce(rule, reduce_tokens));
+                    match reduction.tokens.first() {
+                        Some(first) => head.pos = first.pos,
+                        None => {
+                            // an empty rule is reduced where the next token starts
+                            reduction.span = input_token.span.start..input_token.span.start;
+                            head.pos = input_token.pos;
+                        },
+                    }
+                    head.span = reduction.span.clone();
+                    head.reduction = Some(reduction);
                     result = GPParseResult::Reduce;
                 }
                 // execute GOTO action for the rule that was just reduced
@@ -442,7 +541,7 @@ impl GPParser for Parser {
                 // and find the action corresponding to the rule's head symbol
                 let state_index = self.stack.peek().expect("Invalid peek").state();
                 match self.get_lalr_state(state_index)
-                                      .find_action(&rule.head())
+                                      .find_action(rule.head())
                                       .cloned()
                 {
                     Some(action) => {
@@ -463,26 +562,17 @@ impl GPParser for Parser {
             ActionType::Shift  => {
                 trace!("ActionType::Shift");
                 // Shift to target state and push the current Token.
-                self.curr_state = parse_action.target_idx;  //self.get_lalr_state(parse_action.target_idx);
+                self.curr_state = parse_action.target_idx;
                 input_token.lalr_state = self.curr_state;
-                self.input_tokens.push(input_token.clone());
+                self.stack.push(input_token.clone());
                 result = GPParseResult::Shift;
-                debug!("Pushed {} onto input stack and Parser shifted to state {}",input_token.text,input_token.lalr_state)
+                debug!("Pushed {} onto the stack and Parser shifted to state {}",input_token.text,input_token.lalr_state)
             },
             ActionType::Undefined |
             ActionType::Goto  => {
                 trace!("ActionType::Undefined|Goto");
-                // Syntax error. Generate a list of expected symbols to report
-                self.expected_symbols.clear();
-                let lrstate = self.get_lalr_state(self.curr_state).actions.clone();
-
-                for action in lrstate {
-                    if action.action == ActionType::Shift {
-                        
-                        self.expected_symbols.add(action.symbol.clone());
-                    }
-                }
-                result = GPParseResult::SyntaxError;
+                // A Goto is never taken on an input token
+                result = GPParseResult::InternalError;
             },
         }
         result
@@ -491,62 +581,30 @@ impl GPParser for Parser {
     fn input_token(&mut self) -> Token {
         trace!("input_token()");
         let mut token = Token::default();
-        let mut curr_state = self.grammar.initial_states.dfa as usize;
-        let mut length = 1;
-        let mut last_accept_state: i32 = -1;
-        let mut last_accept_pos: i32 = -1;
-        //let mut target = 0;
-        let mut done = false;
-
-        while !done {
-            let mut ch = '';
-            // Search all the branches of the current DFA state for the next
-            // character in the input stream. If found, the target state is returned.
-            //let ch = self.lookahead(length);
-            if let Some(c) = self.lookahead(length) {
-                ch = c;
-            } else {
-                //done = true;
-                token.symbol.kind = SymbolType::EndOfFile;
-                break;
-            }
-            // Checks whether an edge was found from the `curr_state`. If so, the state and
-            // `curr_pos` advances. Else, quit main loop and report Token found. If the
-            // last_accept_state is -1, then no match found and the Error Token is created.
-            match self.get_dfa_state(curr_state).find_edge(ch) {
-                // Checks whether the target state accepts a Token. If so, it sets the
-                // appropriate variables so when the algorithm is done, it can return the
-                // proper Token and number of characters
-                Some(index) => { 
-                    let target = index as i32;
-                    if self.get_dfa_state(index).accept {
-                        last_accept_state = target;
-                        last_accept_pos = length as i32;
-                        debug!("target state {index} accepts a token");
-                    }
-                    curr_state = target as usize;
-                    length += 1;
-                    debug!("curr_state = {target}");
+        if self.lookahead(1).is_none() {
+            // Nothing left to read
+            token.symbol = self.symbol_by_type(SymbolType::EndOfFile).cloned().unwrap_or_default();
+        } else {
+            // The DFA runs until no edge matches the next character or the input ends, and
+            // the token is the text up to the last accepting state. If no state accepted,
+            // the first character is returned as an Error token.
+            let source = &mut self.source;
+            let input = (1..).map_while(|n| source.lookahead_char(n));
+            match longest_match(&self.grammar, input) {
+                Some((symbol, length)) => {
+                    token.symbol = symbol.to_owned();
+                    token.text = <Parser as GPParser>::lookahead(self, length).to_string();
+                },
+                None => {
+                    token.symbol = self.symbol_by_type(SymbolType::Error).unwrap().to_owned();
+                    token.text = <Parser as GPParser>::lookahead(self, 1).to_string();
                 },
-                None => { // no edge found. no target state found.
-                    if last_accept_state == -1 { // Lexer doesn't recognize the symbol
-                        token.symbol = self.symbol_by_type(SymbolType::Error).unwrap().to_owned();
-                        token.text = <Parser as GPParser>::lookahead(self,1).to_string();
-                    } else { // create Token and read text for Token.
-                        // self.text contains the total number of accept characters
-                        token.symbol = self.get_dfa_state(last_accept_state as usize).accept_symbol.to_owned();
-                        token.text = <Parser as GPParser>::lookahead(&self, last_accept_pos as usize).to_string();
-                    }
-                    done = true;
-                    debug!("done.");
-                }
             }
-            println!("Current DFA State: {curr_state} Edge: \'{ch}\'\nLast accept DFA State: {last_accept_state} Position: {last_accept_pos}");
-
-            
         }
         token.pos = self.source.pos;
-        println!("Span: {}:{}  {length} {:?}",self.source.pos.line(),self.source.pos.col(),token);
+        let start = self.source.get_offset();
+        token.span = start..start + token.text.len();
+        debug!("Span: {}:{} {:?}", self.source.pos.line(), self.source.pos.col(), token);
         token
     }
 
@@ -570,7 +628,6 @@ impl GPParser for Parser {
         self.curr_state = self.grammar.initial_states.lalr as usize;
         self.stack.clear();
         self.group.clear();
-        // TODO self.groups.clear()
         self.expected_symbols.clear();
         self.have_reduction = false;
         self.initialized = false;
@@ -580,7 +637,8 @@ impl GPParser for Parser {
     }
     fn version(&self) -> String {
         let ver = format!("{} - Version {}",Self::PARSER_NAME, Self::PARSER_VERSION);
-        format!("{}\n{} {}",ver, self.properties.get("Name").unwrap(), self.properties.get("Version").unwrap() )
+        let props = &self.grammar.properties;
+        format!("{}\n{} {}",ver, props.name().unwrap_or_default(), props.version().unwrap_or_default())
     }
 
 }
@@ -714,15 +772,19 @@ pub mod test {
         assert_eq!(&tok.text, "assign");
     }
     #[test]
+    fn new_missing_grammar() {
+        assert!(matches!(Parser::new("no/such/grammar.egt".to_string()), Err(super::ParserError::Grammar(_))));
+    }
+    #[test]
     fn new() {
-        let parser = Parser::new(crate::test::GP_TEST_EGT.to_string());
+        let parser = Parser::new(crate::test::GP_SIMPLE_EGT.to_string()).unwrap();
         println!("About:\n{}",parser.about());
-        assert_eq!(parser.grammar.property("Name"),"BADASS");
+        assert_eq!(parser.grammar.property("Name"),Some("Simple"));
     }
     #[test]
     fn load_source() {
-        let mut parser = Parser::new(crate::test::GP_TEST_EGT.to_string());
-        if let Ok(_) = parser.load_source(crate::test::GP_TEST_SRC.to_string()) {
+        let mut parser = Parser::new(crate::test::GP_SIMPLE_EGT.to_string()).unwrap();
+        if let Ok(_) = parser.load_source(crate::test::GP_SIMPLE_SRC.to_string()) {
             println!("{}",parser.version());
             println!("Source length: {}", parser.source.len());
             println!("Source:\n{}",parser.source.to_string());
@@ -747,10 +809,170 @@ pub mod test {
         tok
     }
 
+    #[test]
+    fn parse_accept() {
+        let mut parser = gen_loaded_parser();
+        assert!(matches!(parser.parse(), GPMessage::Accept));
+        let root = parser.get_current_reduction().expect("root reduction");
+        assert_eq!(root.rule.head.name, "Statements");
+    }
+    #[test]
+    fn spans() {
+        let source = "assign a = 34\r\nwhile a > 3 do\n\tassign a = a - 4\nend  ";
+        let mut parser = Parser::new(crate::test::GP_SIMPLE_EGT.to_string()).unwrap();
+        parser.load_source_str(source);
+        assert!(matches!(parser.parse(), GPMessage::Accept));
+        let root = parser.get_current_reduction().expect("root reduction");
+        assert_eq!(root.span_in(source).as_str(), source.trim_end());
+
+        // <Statements> ::= <Statement> <Statements>
+        let assign = root.tokens[0].reduction();
+        assert_eq!(assign.span_in(source).as_str(), "assign a = 34");
+        assert_eq!(assign.tokens[3].span, 11..13);
+        let index = crate::engine::LineIndex::new(source);
+        let body = root.tokens[1].reduction().tokens[0].reduction().tokens[3].span.start;
+        let pos = index.line_col(body);
+        assert_eq!((pos.line(), pos.col()), (3, 2));
+    }
+    #[test]
+    fn long_token() {
+        // lookahead is constant time, so a long literal is read in linear time
+        let literal = format!("'{}'", "x".repeat(200_000));
+        let mut parser = Parser::new(crate::test::GP_SIMPLE_EGT.to_string()).unwrap();
+        parser.load_source_str(&format!("display {literal}"));
+        assert_eq!(parser.produce_token().text, "display");
+        parser.produce_token();
+        assert_eq!(parser.produce_token().text, literal);
+    }
+    #[test]
+    fn parse_stream() {
+        use crate::engine::source::test::Trickle;
+        let mut parser = Parser::new(crate::test::GP_TINY_EGT.to_string()).unwrap();
+        parser.load_reader(Trickle(b"{ \xC3\xA9t\xC3\xA9 }\r\nread x;\r\nwrite x", 2));
+        assert!(matches!(parser.parse(), GPMessage::Accept));
+        let root = parser.get_current_reduction().expect("root reduction");
+        assert_eq!(root.span, 11..27);
+    }
+    #[test]
+    fn parse_empty() {
+        let egt = crate::grammar::compile("\"Start Symbol\" = <P>\n<P> ::= <S>*\n<S> ::= s\n").unwrap();
+        let mut parser = Parser::from_grammar(egt);
+        parser.load_reader(std::io::empty());
+        assert!(matches!(parser.parse(), GPMessage::Accept));
+        parser.load_source_str("");
+        assert!(matches!(parser.parse(), GPMessage::Accept));
+    }
+    #[test]
+    fn group_comment_block() {
+        let mut parser = gen_tiny_parser("{ a comment }read x");
+        let tok = parser.produce_token();
+        assert_eq!(tok.name(), "Comment");
+        assert_eq!(tok.text, "{ a comment }");
+        assert_eq!(parser.produce_token().text, "read");
+    }
+    #[test]
+    fn group_nested() {
+        let mut parser = gen_tiny_parser("{ outer { inner } outer }read");
+        // Tiny comments do not nest. The first '}' ends the comment.
+        assert_eq!(parser.produce_token().text, "{ outer { inner }");
+
+        let mut parser = gen_tiny_parser("{ outer { inner } outer }read");
+        parser.grammar.groups[0].nested.push(0);
+        let tok = parser.produce_token();
+        assert_eq!(tok.name(), "Comment");
+        assert_eq!(tok.text, "{ outer { inner } outer }");
+        assert_eq!(parser.produce_token().text, "read");
+    }
+    #[test]
+    fn group_runaway() {
+        let mut parser = gen_tiny_parser("read x;\n  { never closed");
+        assert!(matches!(parser.parse(), GPMessage::GroupError));
+        // reported at the start of the group rather than at the end of file
+        assert_eq!(parser.curr_position, parser.group.peek().unwrap().pos);
+        let eof = parser.get_current_token().unwrap();
+        assert_eq!(*eof.kind(), SymbolType::EndOfFile);
+        assert_ne!(parser.curr_position, eof.pos);
+        assert_eq!(parser.group.peek().unwrap().text, "{ never closed");
+    }
+    #[test]
+    fn parse_tiny_with_comments() {
+        crate::test::init_logger();
+        let mut parser = Parser::new(crate::test::GP_TINY_EGT.to_string()).unwrap();
+        parser.load_source(crate::test::GP_TINY_SRC.to_string()).unwrap();
+        assert!(matches!(parser.parse(), GPMessage::Accept));
+    }
+
+    #[test]
+    fn supplementary_identifiers() {
+        use crate::engine::tables::Table;
+        const SIMPLE: &[u8] = include_bytes!("../../examples/simple.egt");
+        let mut grammar = crate::engine::Builder::from_bytes(SIMPLE).to_egt().unwrap();
+        // let identifiers hold emoji wherever they can hold a 'z'
+        let emoji = crate::engine::CharacterSet::new(vec!['\u{1F600}'..='\u{1F64F}']);
+        let charset_idx = grammar.charset.len();
+        grammar.counts.charset += 1;
+        grammar.resize();
+        grammar.charset.add(charset_idx, emoji.clone());
+        for state in 0..grammar.dfa_states.len() {
+            let edges = &mut grammar.dfa_states[state].edges;
+            if let Some(target_state) = edges.iter().find(|e| e.chars.contains('z')).map(|e| e.target_state) {
+                edges.push(crate::engine::DFAEdge { chars: emoji.clone(), charset_idx, target_state });
+            }
+        }
+        let bytes = crate::engine::writer::write(&grammar).unwrap();
+        let grammar = crate::engine::Builder::from_bytes(&bytes).to_egt().unwrap();
+        let mut parser = Parser::from_grammar(grammar);
+        parser.load_source_str("assign \u{1F600}\u{1F642} = 34");
+        assert!(matches!(parser.parse(), GPMessage::Accept));
+    }
+
+    #[test]
+    fn character_mapping() {
+        use crate::engine::tables::Table;
+        let mut grammar = crate::engine::Builder::from_path(crate::test::GP_SIMPLE_EGT).unwrap().to_egt().unwrap();
+        assert_eq!(grammar.properties.character_mapping(), Some(crate::engine::CharacterMapping::Windows1252));
+        // allow the euro sign in string literals
+        let string_ch = (0..grammar.charset.len()).find(|&i| grammar.charset[i].contains('~')).unwrap();
+        grammar.charset[string_ch].add('€'..='€');
+        for state in 0..grammar.dfa_states.len() {
+            for edge in grammar.dfa_states[state].edges.iter_mut().filter(|e| e.charset_idx == string_ch) {
+                edge.chars.add('€'..='€');
+            }
+        }
+
+        let mut parser = Parser::from_grammar(grammar);
+        parser.load_source_str("display '\u{80}5'");
+        assert!(matches!(parser.parse(), GPMessage::Accept));
+        // the token text is the source text
+        parser.load_source_str("display '\u{80}5'");
+        let tokens = (0..3).map(|_| parser.produce_token().text).collect::<Vec<_>>();
+        assert_eq!(tokens[2], "'\u{80}5'");
+
+        parser.grammar.properties.set("Character Mapping", "None");
+        parser.load_source_str("display '\u{80}5'");
+        assert!(matches!(parser.parse(), GPMessage::LexicalError));
+    }
+
+    #[test]
+    fn from_grammar() {
+        const TINY: &[u8] = include_bytes!("../../examples/Tiny.egt");
+        let grammar = crate::engine::Builder::from_bytes(TINY).to_egt().unwrap();
+        let mut parser = Parser::from_grammar(grammar);
+        parser.load_source_str("read x; write x");
+        assert!(matches!(parser.parse(), GPMessage::Accept));
+    }
+
+    fn gen_tiny_parser(src: &str) -> Parser {
+        crate::test::init_logger();
+        let mut parser = Parser::new(crate::test::GP_TINY_EGT.to_string()).unwrap();
+        parser.load_source_str(src);
+        parser
+    }
+
     fn gen_loaded_parser<'test>() -> Parser {
         crate::test::init_logger();
 
-        let mut parser = Parser::new(crate::test::GP_SIMPLE_EGT.to_string());
+        let mut parser = Parser::new(crate::test::GP_SIMPLE_EGT.to_string()).unwrap();
         if let Ok(_) = parser.load_source(crate::test::GP_SIMPLE_SRC.to_string()) {
             // println!("{}",parser.version());
             // println!("Source length: {}", parser.source.len());
@@ -1,8 +1,8 @@
 //! Property Record
 //! 
-//! 
+//! http://goldparser.org/doc/egt/record-property.htm
 
-use super::Utf16;
+use std::{ops::{Index, IndexMut}, time::{Duration, SystemTime, UNIX_EPOCH}};
 
 
 
@@ -12,7 +12,7 @@ use super::Utf16;
 /// for the letter 'p'. The record contains an index, the property name, and its 
 /// associated value. The idea is to allow additional information to be added in the future. 
 /// This may include more information about the grammar and/or user-defined meta-data.
-#[derive(Debug)]
+#[derive(Debug,Clone)]
 pub struct PropertyRecord {
     pub index: usize,
     pub name: String,
@@ -32,4 +32,192 @@ impl std::fmt::Display for PropertyRecord {
         let disp = format!("@{:04X} {} = {}",self.index, self.name,self.value);
         write!(f,"{}", disp)
     }
-}
\ No newline at end of file
+}
+
+#[derive(Debug,Default,Clone,Copy,PartialEq,Eq)]
+/// Value of the `Character Mapping` property
+pub enum CharacterMapping {
+    /// Characters are passed to the DFA unchanged
+    #[default]
+    None,
+    /// Characters 0x80-0x9F are mapped from Windows-1252 to Unicode
+    Windows1252,
+}
+
+impl CharacterMapping {
+    /// Windows-1252 characters 0x80-0x9F. Unassigned code points map to themselves.
+    const WINDOWS_1252: [char; 32] = [
+        '\u{20AC}', '\u{81}',   '\u{201A}', '\u{192}',  '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+        '\u{2C6}',  '\u{2030}', '\u{160}',  '\u{2039}', '\u{152}',  '\u{8D}',   '\u{17D}',  '\u{8F}',
+        '\u{90}',   '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+        '\u{2DC}',  '\u{2122}', '\u{161}',  '\u{203A}', '\u{153}',  '\u{9D}',   '\u{17E}',  '\u{178}',
+    ];
+
+    /// Maps an input character to the character the DFA was built for
+    pub fn map(self, ch: char) -> char {
+        match (self, ch) {
+            (CharacterMapping::Windows1252, '\u{80}'..='\u{9F}') => Self::WINDOWS_1252[ch as usize - 0x80],
+            _ => ch,
+        }
+    }
+}
+
+#[derive(Debug,Default,Clone)]
+/// The grammar's `PropertyRecord`s in file order, with typed accessors for the
+/// properties GOLD defines. Accessors return `None` if the property is missing
+/// or its value cannot be parsed.
+pub struct GrammarProperties(Vec<PropertyRecord>);
+impl GrammarProperties {
+    pub fn new() -> Self { GrammarProperties(Vec::new()) }
+    pub fn push(&mut self, rec: PropertyRecord) {
+        self.0.push(rec);
+    }
+    pub fn iter(&self) -> std::slice::Iter<'_, PropertyRecord> {
+        self.0.iter()
+    }
+    pub fn len(&self) -> usize { self.0.len() }
+    pub fn is_empty(&self) -> bool { self.0.is_empty() }
+
+    /// Searches (name,value) pairs by name and returns value
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0.iter().find(|p| p.name == name).map(|p| p.value.as_str())
+    }
+    /// Sets the property `name`, adding it if it is not already defined
+    pub fn set(&mut self, name: &str, value: &str) {
+        match self.0.iter_mut().find(|p| p.name == name) {
+            Some(rec) => rec.value = value.to_string(),
+            None => {
+                let index = self.0.len();
+                self.0.push(PropertyRecord::new(index, name.to_string(), value.to_string()));
+            },
+        }
+    }
+
+    pub fn name(&self) -> Option<&str> { self.get("Name") }
+    pub fn version(&self) -> Option<&str> { self.get("Version") }
+    pub fn author(&self) -> Option<&str> { self.get("Author") }
+    pub fn about(&self) -> Option<&str> { self.get("About") }
+    pub fn character_set(&self) -> Option<&str> { self.get("Character Set") }
+    pub fn generated_by(&self) -> Option<&str> { self.get("Generated By") }
+    /// `True` or `False`
+    pub fn case_sensitive(&self) -> Option<bool> {
+        match self.get("Case Sensitive")?.trim() {
+            v if v.eq_ignore_ascii_case("true") => Some(true),
+            v if v.eq_ignore_ascii_case("false") => Some(false),
+            _ => None,
+        }
+    }
+    /// Name of the start symbol, without the angle brackets used in grammar source
+    pub fn start_symbol(&self) -> Option<&str> {
+        let value = self.get("Start Symbol")?.trim();
+        Some(value.strip_prefix('<').and_then(|v| v.strip_suffix('>')).unwrap_or(value))
+    }
+    /// `Windows-1252` or `None`
+    pub fn character_mapping(&self) -> Option<CharacterMapping> {
+        match self.get("Character Mapping")?.trim() {
+            "Windows-1252" => Some(CharacterMapping::Windows1252),
+            "None" => Some(CharacterMapping::None),
+            _ => None,
+        }
+    }
+    /// `Generated Date` is written by GOLD Builder as `YYYY-MM-DD HH:MM`, in local time
+    /// with no offset. The time is taken as UTC.
+    pub fn generated_date(&self) -> Option<SystemTime> {
+        parse_date(self.get("Generated Date")?)
+    }
+}
+
+/// Parses `YYYY-MM-DD HH:MM[:SS]`
+fn parse_date(value: &str) -> Option<SystemTime> {
+    let (date, time) = value.trim().split_once(' ')?;
+    let mut ymd = date.splitn(3, '-').map(|v| v.parse::<i64>().ok());
+    let (y, m, d) = (ymd.next()??, ymd.next()??, ymd.next()??);
+    let mut hms = time.trim().splitn(3, ':').map(|v| v.parse::<i64>().ok());
+    let (hh, mm, ss) = (hms.next()??, hms.next()??, hms.next().unwrap_or(Some(0))?);
+    if !(1..=12).contains(&m) || !(1..=31).contains(&d) || hh > 23 || mm > 59 || ss > 60 {
+        return None;
+    }
+    // days since 1970-01-01 in the proleptic Gregorian calendar
+    let (y, m) = if m <= 2 { (y - 1, m + 9) } else { (y, m - 3) };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * m + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146097 + doe - 719468;
+    let secs = u64::try_from(days * 86400 + hh * 3600 + mm * 60 + ss).ok()?;
+    Some(UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+impl Index<usize> for GrammarProperties {
+    type Output = PropertyRecord;
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.0[index]
+    }
+}
+impl IndexMut<usize> for GrammarProperties {
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        &mut self.0[index]
+    }
+}
+
+impl std::fmt::Display for GrammarProperties {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for p in &self.0 {
+            writeln!(f,"{} = {}",p.name,p.value)?;
+        }
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use crate::engine::Builder;
+
+    use super::{GrammarProperties, CharacterMapping};
+
+    #[test]
+    fn typed() {
+        let egt = Builder::from_path(crate::test::GP_TINY_EGT).unwrap().to_egt().unwrap();
+        let props = &egt.properties;
+        assert_eq!(props.name(), Some("Tiny"));
+        assert_eq!(props.author(), Some("Kenneth C. Louden"));
+        assert_eq!(props.character_set(), Some("Unicode"));
+        assert_eq!(props.character_mapping(), Some(CharacterMapping::Windows1252));
+        assert_eq!(props.generated_by(), Some("GOLD Parser Builder 5.2.0."));
+        // 2023-01-23 11:42
+        assert_eq!(props.generated_date(), Some(UNIX_EPOCH + Duration::from_secs(1674474120)));
+        assert_eq!(props.case_sensitive(), None);
+        assert_eq!(props.start_symbol(), None);
+    }
+
+    #[test]
+    fn windows_1252() {
+        let map = |ch| CharacterMapping::Windows1252.map(ch);
+        assert_eq!(map('\u{80}'), '€');
+        assert_eq!(map('\u{93}'), '“');
+        assert_eq!(map('\u{9F}'), 'Ÿ');
+        assert_eq!(map('\u{81}'), '\u{81}');
+        assert_eq!(map('a'), 'a');
+        assert_eq!(map('\u{A0}'), '\u{A0}');
+        assert_eq!(CharacterMapping::None.map('\u{80}'), '\u{80}');
+    }
+    #[test]
+    fn set() {
+        let mut props = GrammarProperties::new();
+        props.set("Case Sensitive", "True");
+        props.set("Start Symbol", "<Program>");
+        props.set("Generated Date", "2000-02-29 00:00");
+        assert_eq!(props.case_sensitive(), Some(true));
+        assert_eq!(props.start_symbol(), Some("Program"));
+        assert_eq!(props.generated_date(), Some(UNIX_EPOCH + Duration::from_secs(951782400)));
+        props.set("Case Sensitive", "False");
+        assert_eq!(props.case_sensitive(), Some(false));
+        assert_eq!(props.len(), 3);
+        props.set("Generated Date", "yesterday");
+        assert_eq!(props.generated_date(), None);
+        assert_eq!(props.get("Author"), None);
+    }
+}
@@ -30,7 +30,9 @@
 //! Ralph Iden (http://www.creativewidgetworks.com), port to Java
 //! Gian James (https://www.convolutedsystems.com), port to Rust
 
-use super::{token::Token, ProductionRule};
+use std::ops::Range;
+
+use super::{token::Token, ProductionRule, Span};
 use crate::parser::RuleHandler;
 
 pub trait Reducible {
@@ -42,7 +44,16 @@ pub trait Reducible {
 pub fn reduce(rule: &ProductionRule, tokens: Vec<Token>) -> Reduction {
 //pub fn reduce<R: RuleHandler>(rule: &'static ProductionRule, tokens: Vec<Token>) -> Reduction {
 
-    Reduction { tokens, rule: rule.to_owned(), tag: 0 }
+    let span = cover(&tokens);
+    Reduction { tokens, rule: rule.to_owned(), span, tag: 0 }
+}
+
+/// The span from the first to the last of `tokens`, empty at 0 if there are none
+fn cover(tokens: &[Token]) -> Range<usize> {
+    match (tokens.first(), tokens.last()) {
+        (Some(first), Some(last)) => first.span.start..last.span.end,
+        _ => 0..0,
+    }
 }
 
 #[derive(Debug,Clone)]
@@ -54,6 +65,9 @@ pub fn reduce(rule: &ProductionRule, tokens: Vec<Token>) -> Reduction {
 pub struct Reduction {
     pub tokens: Vec<Token>, // 
     pub rule: ProductionRule,
+    /// Byte offsets in the source from the start of the first token to the end of the last.
+    /// The parser places an empty reduction at the token that follows it.
+    pub span: Range<usize>,
     tag: u16,
 }
 impl Reduction {
@@ -67,7 +81,8 @@ impl Reduction {
     pub fn with_capacity(size: usize, rule: &ProductionRule, tokens: Vec<Token>) -> Self {
         let mut tok = Vec::with_capacity(size);
         tok.clone_from(&tokens);
-        Reduction { tokens: tok, rule: rule.to_owned(), tag: 0 }
+        let span = cover(&tok);
+        Reduction { tokens: tok, rule: rule.to_owned(), span, tag: 0 }
     }
     pub fn reduce(&mut self) -> Reduction {
         todo!()
@@ -77,6 +92,10 @@ impl Reduction {
     pub fn token_count(&self) -> usize {
         self.tokens.len()
     }
+    /// The `Span` of this reduction in `input`, the source it was parsed from
+    pub fn span_in<'i>(&self, input: &'i str) -> Span<'i> {
+        Span::new(input, self.span.start, self.span.end)
+    }
 }
 
 // impl From<Vec<Token>> for Reduction<'_> {
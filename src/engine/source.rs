@@ -1,116 +1,248 @@
 //! Source Reader
 //! 
+//! The source is either loaded whole from a `String`, or pulled in chunks from any
+//! `io::Read` so that large files and streams are parsed without reading them into memory.
 
-use crate::engine::Position;
+use std::io::{self, Read};
 
+use crate::engine::{Position, CharacterMapping, Columns};
+
+/// Bytes pulled from a stream at a time
+const CHUNK: usize = 64 * 1024;
 
 /// Responsible for storage and access of the source buffer, both as unicode `char`
 /// and string represting same. It is naive and has no knowlege about the source.
+///
+/// The text is kept in one `String`. The characters read ahead are tracked by the byte
+/// offsets of their ends, so looking ahead and consuming take constant time per character,
+/// and the text read ahead can be sliced out without copying.
+///
+/// A streamed source keeps only the text from the first character not consumed to the end
+/// of the last chunk. Invalid UTF-8 is read as U+FFFD, and byte offsets count the UTF-8
+/// length of the characters read.
 #[derive(Default)]
 pub struct SourceReader {
-    pub src: Vec<char>,
-    buf: String,
+    /// The whole source when loaded from a `String`. When streamed, the consumed text is
+    /// dropped each time a chunk is read.
+    text: String,
+    /// Byte offset in `text` of the first character not consumed
+    start: usize,
+    /// Byte offsets in `text` of the ends of the characters read ahead, from index `first`
+    ahead: Vec<usize>,
+    first: usize,
     pub pos: Position,  // line,col position
     bufpos: usize,      // absolute position
+    offset: usize,      // bytes consumed
+    chars: usize,       // chars decoded
+    cr: bool,           // the last char consumed was a CR
+    input: Option<Box<dyn Read>>,
+    pending: Vec<u8>,   // start of a UTF-8 sequence split between chunks
+    error: Option<io::Error>,
+    /// A source was loaded, which may be empty
+    loaded: bool,
+    /// Applied to characters returned by `lookahead`, from the grammar's `Character Mapping`
+    pub mapping: CharacterMapping,
+    /// How `pos` counts columns
+    pub columns: Columns,
 }
 
 
 impl SourceReader {
     pub fn new(source: String) -> Self {
-        let src = source.chars().collect();
-        SourceReader {src, buf: String::new(), pos: Position::default(), bufpos: 0 }
+        let mut reader = SourceReader::default();
+        reader.load(source);
+        reader
+    }
+    /// Creates a `SourceReader` streaming from `input`
+    pub fn from_reader<R: Read + 'static>(input: R) -> Self {
+        let mut reader = SourceReader::default();
+        reader.load_reader(input);
+        reader
     }
-    /// Operates on the lookahead buffer `buf`. Non-consuming.
-    /// Will autoload buffer from source `src` if needed
+    /// Operates on the lookahead buffer. Non-consuming.
+    /// Will read ahead in the source if needed
+    /// The returned character is translated by `mapping`, the buffer keeps the source text.
     pub fn lookahead(&mut self, count: usize) -> char {
-        if self.src.len() == 0 { panic!("Attemped lookahead on unloaded source. Load source file before calling lookahead()"); }
-        // Autoload from src into buffer
-        if count > self.buf.len() {
-            debug!("Pre-emptively reading {} chars",count-self.buf.len());
-            for _ in 0..count-self.buf.len() {
-                match self.read() {
-                    Some((_,c)) => self.buf.push(c),
-                    None => return '', //panic!("Reading past src vector"),
-                }
-            }
-            debug!("buf: \'{}\'",self.buf);
+        if !self.loaded { panic!("Attemped lookahead on unloaded source. Load source file before calling lookahead()"); }
+        self.lookahead_char(count).unwrap_or('\u{E003}')
+    }
+    /// Like `lookahead`, but `None` past the end of the source
+    pub fn lookahead_char(&mut self, count: usize) -> Option<char> {
+        while self.get_buf_len() < count {
+            self.read()?;
         }
-        self.buf.chars().nth(count-1).expect(format!("Problem indexing lookahead buf: {}",count).as_str())
+        let at = match count {
+            1 => self.start,
+            _ => self.ahead[self.first+count-2],
+        };
+        self.text[at..].chars().next().map(|ch| self.mapping.map(ch))
     }
     pub fn get_abs_pos(&self) -> usize { self.bufpos }
-    pub fn get_buf_len(&self) -> usize { self.buf.len() }
+    /// Byte offset in the source of the first character of the lookahead buffer
+    pub fn get_offset(&self) -> usize { self.offset }
+    /// Number of `char`s in the lookahead buffer
+    pub fn get_buf_len(&self) -> usize { self.ahead.len() - self.first }
+    /// The first `end` `char`s of the lookahead buffer
     pub fn get_buf_slice_to(&self, end: usize) -> &str {
-        &self.buf[0..end]
+        match end.min(self.get_buf_len()) {
+            0 => "",
+            end => &self.text[self.start..self.ahead[self.first+end-1]],
+        }
+    }
+    /// Byte offset in `text` of the end of the lookahead buffer
+    fn end(&self) -> usize {
+        self.ahead.last().copied().filter(|_| self.get_buf_len() > 0).unwrap_or(self.start)
     }
-    /// Looks into `src` data. Does not change bufpos
+    /// Looks into the source after the lookahead buffer. Does not change bufpos
     pub fn peek(&mut self, count: usize) -> char {
-
-        if self.bufpos+count <= self.src.len() {
-            return self.src[self.bufpos+count-1];        
-        } else {
-            return char::MAX;
+        loop {
+            if let Some(ch) = self.text[self.end()..].chars().nth(count-1) { return ch; }
+            if !self.pull() { return char::MAX; }
         }
     }
-    /// Works on lookahead buffer `buf`. Consumes characters from lookahead `buf`
+    /// Works on the lookahead buffer. Consumes characters from the lookahead buffer
     /// Adjusts `Position` pos to reflect this.
     pub fn consume_buf(&mut self, count: usize) {
         trace!("consume_buf({count})");
-        if count > 0 && count <= self.buf.len() {
-            // adjust position
-            self.buf.chars().for_each(|c| {
-                if c == '\n' {  //0x0A {
-                    if self.pos.col() > 1 {
-                        self.pos.inc_line();
-                    }
-                } else if c == '\r' { //0x0D {
-                    self.pos.inc_line();
-                } else {
-                    self.pos.inc_col();
-                }
-            });
-            debug!("Pre-crop: \'{}\'",self.buf);
-            // remove the characters
-            let cropped = match self.buf.char_indices().skip(count).next() {
-                Some((pos,_)) => self.buf.split_off(pos), // = &self.buf[pos..],
-                None => String::new(),
-            };
-            self.buf = cropped;
-            debug!("Post-crop: \'{}\'",self.buf);
+        if count > 0 && count <= self.get_buf_len() {
+            let end = self.ahead[self.first+count-1];
+            // adjust position over the consumed characters only
+            for c in self.text[self.start..end].chars() {
+                self.pos.advance(c, self.cr, self.columns);
+                self.cr = c == '\r';
+            }
+            self.offset += end-self.start;
+            self.start = end;
+            self.first += count;
+            // drop the consumed offsets once they are half of them, which is constant time
+            // per character overall
+            if self.first*2 >= self.ahead.len() {
+                self.ahead.drain(..self.first);
+                self.first = 0;
+            }
         } else {
-            error!("Buf len is {} but count is {count}",self.buf.len());
+            error!("Buf len is {} but count is {count}",self.get_buf_len());
         }
     }
     /// Mutable read takes next and returns a `char` and the index it was found
     pub fn read(&mut self) -> Option<(usize,char)> {
-        trace!("read()");
-        if self.bufpos >= self.src.len() { return None; }
+        if self.end() == self.text.len() && !self.pull() { return None; }
 
+        let end = self.end();
+        let ch = self.text[end..].chars().next()?;
+        self.ahead.push(end+ch.len_utf8());
         let oldpos = self.bufpos;
         self.inc_bufpos();
-        debug!("=> \'{}\'", self.src[oldpos]);
-        Some((oldpos, self.src[oldpos]))
+        Some((oldpos, ch))
+    }
+    /// Reads the next chunk of a streamed source, dropping the text already consumed.
+    /// Returns whether text was added.
+    fn pull(&mut self) -> bool {
+        let Some(mut input) = self.input.take() else { return false };
+        self.text.drain(..self.start);
+        self.ahead.drain(..self.first);
+        self.ahead.iter_mut().for_each(|end| *end -= self.start);
+        self.first = 0;
+        self.start = 0;
+
+        let before = self.text.len();
+        let mut chunk = vec![0; CHUNK];
+        while self.text.len() == before {
+            match input.read(&mut chunk) {
+                Ok(0) => {
+                    // a sequence cut short by the end of the stream
+                    if !self.pending.is_empty() {
+                        self.pending.clear();
+                        self.text.push(char::REPLACEMENT_CHARACTER);
+                        self.chars += 1;
+                    }
+                    return self.text.len() > before;
+                },
+                Ok(n) => {
+                    self.pending.extend_from_slice(&chunk[..n]);
+                    self.decode();
+                },
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {},
+                Err(e) => {
+                    error!("Error reading source: {e}");
+                    self.error = Some(e);
+                    return false;
+                },
+            }
+        }
+        self.input = Some(input);
+        true
+    }
+    /// Moves the complete UTF-8 sequences of `pending` to `text`
+    fn decode(&mut self) {
+        loop {
+            let (valid, invalid) = match std::str::from_utf8(&self.pending) {
+                Ok(_) => (self.pending.len(), None),
+                Err(e) => (e.valid_up_to(), Some(e.error_len())),
+            };
+            let decoded = std::str::from_utf8(&self.pending[..valid]).unwrap();
+            self.text.push_str(decoded);
+            self.chars += decoded.chars().count();
+            match invalid {
+                Some(Some(invalid)) => {
+                    self.text.push(char::REPLACEMENT_CHARACTER);
+                    self.chars += 1;
+                    self.pending.drain(..valid+invalid);
+                },
+                // the rest of the sequence is in the next chunk
+                Some(None) => {
+                    self.pending.drain(..valid);
+                    return;
+                },
+                None => {
+                    self.pending.clear();
+                    return;
+                },
+            }
+        }
     }
     pub fn clear(&mut self) {
-        self.src.clear();
-        self.buf.clear();
-        self.pos.clear();
+        self.text.clear();
+        self.start = 0;
+        self.ahead.clear();
+        self.first = 0;
+        self.pos = Position(1,1);
         self.bufpos = 0;
+        self.offset = 0;
+        self.chars = 0;
+        self.cr = false;
+        self.input = None;
+        self.pending.clear();
+        self.error = None;
+        self.loaded = false;
     }
 
     pub fn load(&mut self, source: String) {
         self.clear();
-        self.src = source.chars().collect();
-        self.buf = String::new();
-        self.pos = Position::default();
-        self.bufpos = 0;
+        self.chars = source.chars().count();
+        self.text = source;
+        self.loaded = true;
+    }
+    /// Streams the source from `input`. The first chunk is read right away.
+    pub fn load_reader<R: Read + 'static>(&mut self, input: R) {
+        self.clear();
+        self.input = Some(Box::new(input));
+        self.loaded = true;
+        self.pull();
+    }
+    /// The error that ended reading a streamed source early, if any
+    pub fn take_error(&mut self) -> Option<io::Error> {
+        self.error.take()
     }
 
+    /// Number of `char`s of the source read so far. This is the whole source unless it is
+    /// streamed.
     pub fn len(&self) -> usize {
-        self.src.len()
+        self.chars
     }
+    /// The source text in memory
     pub fn to_string(&self) -> String {
-        let ret = self.src.iter().collect::<String>();
-        ret
+        self.text.clone()
     }
 
     #[inline(always)]
@@ -138,17 +270,41 @@ impl From<String> for SourceReader {
 
 
 #[cfg(test)]
-mod test {
-    use super::SourceReader;
+pub(crate) mod test {
+    use std::io::{self, Read};
+
+    use crate::engine::{ColumnUnit, Columns};
+
+    use super::{SourceReader, CHUNK};
+
+    /// Hands out a few bytes per read, splitting UTF-8 sequences
+    pub(crate) struct Trickle(pub &'static [u8], pub usize);
+    impl Read for Trickle {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.1.min(buf.len()).min(self.0.len());
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    fn consume_all(src: &mut SourceReader) -> String {
+        let mut text = String::new();
+        while let Some(ch) = src.lookahead_char(1) {
+            text.push(ch);
+            src.consume_buf(1);
+        }
+        text
+    }
 
     const SRC_TEST: &str = "LDI R0,23\nLDI R1,10\nMOV R0, R1";
     #[test]
     fn look() {
         let mut src = SourceReader::new(String::from(SRC_TEST));
         println!("{SRC_TEST}");
-        println!("{:?}",src.src);
+        println!("{:?}",src.to_string());
         println!("src[5] = \'{}\'", src.peek(5));
-        println!("src[4..=5] = \'{:?}\'",src.src[4..=5].to_vec());
+        println!("src[4..=5] = \'{:?}\'",&src.to_string()[4..=5]);
         println!("src[3] = \'{}\'", src.peek(3));
         println!("lookahead: 3 = {}", src.lookahead(3));
         println!("bufpos: {}",src.bufpos);
@@ -164,4 +320,75 @@ mod test {
             print!("\n");
         }
     }
+    #[test]
+    fn lookahead_slices() {
+        let mut src = SourceReader::new(String::from("né\r\nz"));
+        assert_eq!(src.lookahead(3), '\r');
+        assert_eq!(src.get_buf_slice_to(2), "né");
+        assert_eq!(src.get_buf_slice_to(9), "né\r");
+        src.consume_buf(2);
+        assert_eq!(src.get_offset(), 3);
+        assert_eq!(src.lookahead(1), '\r');
+        assert_eq!(src.lookahead(3), 'z');
+        assert_eq!(src.lookahead_char(4), None);
+        src.consume_buf(2);
+        assert_eq!((src.pos.line(), src.pos.col()), (2, 1));
+        assert_eq!(src.get_buf_slice_to(1), "z");
+        assert_eq!(src.get_abs_pos(), 5);
+    }
+    #[test]
+    fn positions() {
+        let line_col = |text: &str, columns: Columns| {
+            let mut src = SourceReader::new(text.to_string());
+            src.columns = columns;
+            let count = text.chars().count();
+            src.lookahead(count);
+            src.consume_buf(count);
+            (src.pos.line(), src.pos.col())
+        };
+        assert_eq!(line_col("ab\ncd", Columns::default()), (2, 3));
+        assert_eq!(line_col("ab\r\ncd", Columns::default()), (2, 3));
+        assert_eq!(line_col("ab\rcd", Columns::default()), (2, 3));
+        assert_eq!(line_col("\n\n\r\n\r\rx", Columns::default()), (6, 2));
+        assert_eq!(line_col("a\tb", Columns::new(ColumnUnit::Char, 8)), (1, 10));
+        assert_eq!(line_col("é🦀", Columns::new(ColumnUnit::Utf16, 0)), (1, 4));
+        assert_eq!(line_col("é🦀", Columns::new(ColumnUnit::Utf8, 0)), (1, 7));
+
+        // a CR LF split between two tokens is still one line break
+        let mut src = SourceReader::new(String::from("a\r\nb"));
+        src.lookahead(2);
+        src.consume_buf(2);
+        src.lookahead(1);
+        src.consume_buf(1);
+        assert_eq!((src.pos.line(), src.pos.col()), (2, 1));
+    }
+    #[test]
+    fn stream() {
+        let text = "où\r\nest 🦀\nici";
+        let mut src = SourceReader::from_reader(Trickle(text.as_bytes(), 3));
+        assert_eq!(src.peek(2), 'ù');
+        assert_eq!(consume_all(&mut src), text);
+        assert_eq!((src.pos.line(), src.pos.col()), (3, 4));
+        assert_eq!(src.get_offset(), text.len());
+        assert!(src.take_error().is_none());
+
+        // invalid and truncated sequences are replaced
+        let mut src = SourceReader::from_reader(Trickle(b"a\xFFb\xF0\x9F", 1));
+        assert_eq!(consume_all(&mut src), "a\u{FFFD}b\u{FFFD}");
+    }
+    #[test]
+    fn stream_window() {
+        let mut src = SourceReader::from_reader(io::repeat(b'a').take(3 * CHUNK as u64));
+        let mut read = 0;
+        loop {
+            src.lookahead_char(1000);
+            let buffered = src.get_buf_len();
+            if buffered == 0 { break; }
+            assert!(src.text.len() <= 2 * CHUNK);
+            src.consume_buf(buffered);
+            read += buffered;
+        }
+        assert_eq!(read, 3 * CHUNK);
+        assert_eq!(src.len(), 3 * CHUNK);
+    }
 }
\ No newline at end of file
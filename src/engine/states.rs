@@ -96,6 +96,8 @@ pub struct DFAEdge {
     /// The actual set of valid characters is not stored in this field, but, rather, an index in the 
     /// `CharacterSetTable`
     pub chars: CharacterSet,
+    /// Index of `chars` in the `CharacterSetTable`
+    pub charset_idx: usize,
     /// Each edge is linked to state in the DFA Table. This field contains the index of that state
     pub target_state: usize, //DFAState,
 }
@@ -15,16 +15,16 @@ enum_from_primitive! {
     #[derive(Debug,Default,Clone,Copy,PartialEq,Eq)]
     #[repr(u16)]
     pub enum SymbolType {
+        NonTerminal = 0,    // normal nonterminal
+        Terminal = 1,       // normal terminal (content passed to the parser)
+        Noise = 2,          // Noise terminal. These are ignored by the parser. Comments and whitespace are considered 'noise'.
+        EndOfFile = 3,      // End Character - End of File. This symbol is used to represent the end of the file or the end of the source input.
+        GroupStart = 4,     // Lexical group start
+        GroupEnd = 5,       // lexical group end
+        Deprecated = 6,     // Used as COMMENT_LINE in previous CGT format. Not used in EGT.
+        Error = 7,          // error terminal. if the parser encounters an error reading a Token, this kind of symbol can be used to differentiate it from other terminal types
         #[default]
-        Undefined,
-        NonTerminal,    // normal nonterminal
-        Terminal,       // normal terminal (content passed to the parser)
-        Noise,          // Noise terminal. These are ignored by the parser. Comments and whitespace are considered 'noise'.
-        EndOfFile,      // End Character - End of File. This symbol is used to represent the end of the file or the end of the source input.
-        GroupStart,     // Lexical group start
-        GroupEnd,       // lexical group end
-        Deprecated,     // Used as COMMENT_LINE in previous CGT format. Not used in EGT.
-        Error           // error terminal. if the parser encounters an error reading a Token, this kind of symbol can be used to differentiate it from other terminal types
+        Undefined = 8,      // not stored in the EGT; used for symbols that have not been loaded
     }
 }
 impl SymbolType {
@@ -110,6 +110,64 @@ impl Symbol {
         }
     }
 
+    /// Is this a nonterminal the grammar compiler generated for an EBNF group, like
+    /// `<Statement>*` or `(',' <Expression>)?` The parser splices the tokens of their
+    /// reductions into the reduction using them. Such names are the group as written, which
+    /// the compiler does not accept as the name of a rule.
+    pub fn is_generated(&self) -> bool {
+        self.kind == SymbolType::NonTerminal && Self::is_group_name(&self.name)
+    }
+
+    /// Is `name` an EBNF group as the grammar compiler writes it: a parenthesized group, or a
+    /// symbol followed by `?`, `*` or `+`
+    pub fn is_group_name(name: &str) -> bool {
+        match Self::group_item(name) {
+            Some((len, group)) => match &name[len..] {
+                "" => group,
+                "?" | "*" | "+" => true,
+                _ => false,
+            },
+            None => false,
+        }
+    }
+
+    /// Byte length of the `<Nonterminal>`, terminal or parenthesized group at the start of
+    /// `text`, and whether it is a group
+    fn group_item(text: &str) -> Option<(usize, bool)> {
+        let len = match text.chars().next()? {
+            '<' => text.find('>').filter(|&end| end > 1)? + 1,
+            '\'' => {
+                // '' is a quote in a quoted terminal
+                let mut at = 1;
+                loop {
+                    let end = at + text[at..].find('\'')?;
+                    if text[end + 1..].starts_with('\'') {
+                        at = end + 2;
+                    } else {
+                        break end + 1;
+                    }
+                }
+            },
+            '(' => {
+                let mut at = 1;
+                loop {
+                    match text[at..].chars().next()? {
+                        ')' => return Some((at + 1, true)),
+                        ' ' | '|' => at += 1,
+                        _ => {
+                            at += Self::group_item(&text[at..])?.0;
+                            if text[at..].starts_with(['?', '*', '+']) {
+                                at += 1;
+                            }
+                        },
+                    }
+                }
+            },
+            _ => text.find([' ', '|', '(', ')', '<', '>', '\'', '?', '*', '+']).unwrap_or(text.len()),
+        };
+        Some((len, false)).filter(|_| len > 0)
+    }
+
     /// Returns the text representation of the symbol as follows:
     /// * \<NonTerminal\>
     /// * 'Terminal'
@@ -151,7 +209,7 @@ impl PartialEq for Symbol {
 
 #[cfg(test)]
 pub mod test {
-    use super::SymbolType;
+    use super::{Symbol, SymbolType};
 
 
 
@@ -161,4 +219,14 @@ pub mod test {
         println!("{} {:?}",kind.format(),kind);
     }
 
+    #[test]
+    fn group_name() {
+        for name in ["<Statement>*", "Id+", "(',' <Exp>)*", "(a | b c)?", "(<B>+ | x)", "''''?", "('(' x)"] {
+            assert!(Symbol::is_group_name(name), "{name}");
+        }
+        for name in ["Statement", "Rest (opt)", "(a) (b)", "Id+ x", "<Id>", "(a", "*", "'a"] {
+            assert!(!Symbol::is_group_name(name), "{name}");
+        }
+    }
+
 }
\ No newline at end of file
@@ -87,6 +87,9 @@ impl SymbolTable {
     pub fn with_capacity(size: usize) -> Self {
         SymbolTable(Vec::with_capacity(size))
     }
+    pub fn iter(&self) -> std::slice::Iter<'_, Symbol> {
+        self.0.iter()
+    }
     pub fn push(&mut self, item: <SymbolTable as Table>::Item) {
         self.0.push(item);
     }
@@ -150,6 +153,9 @@ impl Index<usize> for SymbolTable {
 pub struct LALRStateTable(Vec<LALRState>);
 impl LALRStateTable {
     pub fn new() -> Self { LALRStateTable(Vec::new()) }
+    pub fn iter(&self) -> std::slice::Iter<'_, LALRState> {
+        self.0.iter()
+    }
 
 }
 impl Table for LALRStateTable {
@@ -201,6 +207,9 @@ impl IndexMut<usize> for LALRStateTable {
 pub struct DFAStateTable(Vec<DFAState>);
 impl DFAStateTable {
     pub fn new() -> Self { DFAStateTable(Vec::new()) }
+    pub fn iter(&self) -> std::slice::Iter<'_, DFAState> {
+        self.0.iter()
+    }
     pub fn add(&mut self, state: DFAState) {
         let i = state.index;
         self.0.insert(i, state);
@@ -270,6 +279,9 @@ impl CharacterSetTable {
     pub fn contains(&self, charset: CharacterSet) -> bool {
         self.0.contains(&charset)
     }
+    pub fn iter(&self) -> std::slice::Iter<'_, CharacterSet> {
+        self.0.iter()
+    }
 
 }
 
@@ -297,11 +309,17 @@ pub struct ProductionTable(Vec<ProductionRule>);
 impl ProductionTable {
     pub fn new() -> Self { ProductionTable(Vec::new()) }
     pub fn with_capacity(capacity: usize) -> Self {ProductionTable(Vec::with_capacity(capacity))}
+    pub fn iter(&self) -> std::slice::Iter<'_, ProductionRule> {
+        self.0.iter()
+    }
 
     pub fn add(&mut self, rule: ProductionRule) {
         let index = rule.index;
         self.0[index] = rule;
     }
+    pub fn push(&mut self, rule: ProductionRule) {
+        self.0.push(rule);
+    }
 }
 
 impl Display for ProductionTable {
@@ -367,14 +385,55 @@ impl GroupTable {
         let i = group.index;
         self.0[i] = group;
     }
+    pub fn iter(&self) -> std::slice::Iter<'_, LexicalGroup> {
+        self.0.iter()
+    }
+
+}
+impl Table for GroupTable {
+    type Item = LexicalGroup;
+
+    fn add(&mut self, item: Self::Item) {
+        let i = item.index;
+        self.0[i] = item;
+    }
+    fn insert(&mut self, index: usize, value: Self::Item) {
+        self.0[index] = value;
+    }
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+    fn get(&self, item: Self::Item) -> Option<&Self::Item> {
+        self.0.get(item.index)
+    }
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+    fn resize(&mut self, sz: usize) {
+        self.0.resize_with(sz, LexicalGroup::default)
+    }
+    fn clear(&mut self) {
+        self.0.clear();
+    }
+}
 
+impl Display for GroupTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f,"{}", self.0.iter().map(|g| {format!("{}\n",g)}).collect::<String>())
+    }
 }
+
 impl Index<usize> for GroupTable {
     type Output = LexicalGroup;
     fn index(&self, index: usize) -> &LexicalGroup {
         &self.0[index]
     }
 }
+impl IndexMut<usize> for GroupTable {
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        &mut self.0[index]
+    }
+}
 
 
 
@@ -7,9 +7,11 @@
 
 //use utf16string::{WString, LE};
 
+use std::ops::Range;
+
 use crate::engine::SymbolType;
 
-use super::{Position, Symbol, reduction::Reduction};
+use super::{Position, Span, Symbol, reduction::Reduction};
 
 
 #[derive(Debug,Clone)]
@@ -40,6 +42,9 @@ pub struct Token {
     /// `Position` can represent either the line/col of this token or the
     /// start and stop of a span referencing the absolute bufpos.
     pub pos: Position,
+    /// Byte offsets of the token in the source. For a `Token` created by reduction this is
+    /// the text its reduction covers, empty at the next input token for an empty rule.
+    pub span: Range<usize>,
 }
 
 impl Token {
@@ -53,6 +58,7 @@ impl Token {
             reduction: None,
             lalr_state: 0,
             pos: Position::default(),
+            span: 0..0,
         }
     }
 
@@ -84,6 +90,10 @@ impl Token {
     pub fn state(&self) -> usize {
         self.lalr_state
     }
+    /// The `Span` of this token in `input`, the source it was read from
+    pub fn span_in<'i>(&self, input: &'i str) -> Span<'i> {
+        Span::new(input, self.span.start, self.span.end)
+    }
     #[inline(always)]
     /// You should always call `has_reduction` before this or risk panic
     pub fn reduction(&self) -> &Reduction {
@@ -107,6 +117,7 @@ impl Default for Token {
             reduction: None, 
             lalr_state: 0, 
             pos: Position(0,0), 
+            span: 0..0,
         }
     }
 }
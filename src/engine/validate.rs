@@ -0,0 +1,289 @@
+//! Structural validation of an `EnhancedGrammarTable`
+//!
+//! The `Builder` only checks what it needs to decode each record. `validate()` checks the
+//! loaded tables as a whole: table sizes against the `TableCountsRecord`, every index held
+//! by a DFA state, LALR action, production, group and the initial states, and that each
+//! symbol is of a type that makes sense where it is used.
+
+use thiserror::Error;
+
+use crate::engine::{
+    egt::EnhancedGrammarTable,
+    states::ActionType,
+    tables::Table,
+    SymbolType,
+};
+
+/// A single problem found by `EnhancedGrammarTable::validate()`.
+/// `table` and `index` locate the offending entry.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum Finding {
+    /// A table does not hold the number of entries declared by the `TableCountsRecord`
+    #[error("{table} has {found} entries, table counts declare {expected}")]
+    CountMismatch { table: &'static str, expected: usize, found: usize },
+    /// A table entry was never defined by a record
+    #[error("{table}[{index}] is not defined")]
+    Undefined { table: &'static str, index: usize },
+    /// An entry refers to an index outside of another table
+    #[error("{table}[{index}] {field} refers to {target}[{value}], which has {len} entries")]
+    IndexOutOfRange { table: &'static str, index: usize, field: &'static str, target: &'static str, value: usize, len: usize },
+    /// An entry uses a symbol of the wrong type
+    #[error("{table}[{index}] {field} is {kind:?} symbol {symbol}, expected {expected}")]
+    SymbolType { table: &'static str, index: usize, field: &'static str, symbol: usize, kind: SymbolType, expected: &'static str },
+    /// The grammar has no symbol of a required type
+    #[error("no {0:?} symbol")]
+    MissingSymbol(SymbolType),
+}
+
+/// Symbol types produced by the DFA and seen by the LALR parser as lookahead
+fn is_terminal(kind: SymbolType) -> bool {
+    !matches!(kind, SymbolType::NonTerminal | SymbolType::Undefined)
+}
+
+impl EnhancedGrammarTable {
+    /// Checks the tables for consistency. An empty list means the table is safe to parse with.
+    pub fn validate(&self) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        self.validate_counts(&mut findings);
+        self.validate_symbols(&mut findings);
+        self.validate_groups(&mut findings);
+        self.validate_productions(&mut findings);
+        self.validate_dfa(&mut findings);
+        self.validate_lalr(&mut findings);
+        findings
+    }
+
+    fn validate_counts(&self, findings: &mut Vec<Finding>) {
+        let c = &self.counts;
+        for (table, expected, found) in [
+            ("symbol table", c.symtab, self.symbols.len()),
+            ("character set table", c.charset, self.charset.len()),
+            ("production table", c.rules, self.productions.len()),
+            ("DFA state table", c.dfatab, self.dfa_states.len()),
+            ("LALR state table", c.lalrtab, self.lalr_states.len()),
+            ("group table", c.lexgroups, self.groups.len()),
+        ] {
+            if expected as usize != found {
+                findings.push(Finding::CountMismatch { table, expected: expected as usize, found });
+            }
+        }
+        let check = |findings: &mut Vec<Finding>, field, target, value: u16, len| {
+            if value as usize >= len {
+                findings.push(Finding::IndexOutOfRange { table: "initial states", index: 0, field, target, value: value as usize, len });
+            }
+        };
+        check(findings, "DFA", "DFA state table", self.initial_states.dfa, self.dfa_states.len());
+        check(findings, "LALR", "LALR state table", self.initial_states.lalr, self.lalr_states.len());
+    }
+
+    fn validate_symbols(&self, findings: &mut Vec<Finding>) {
+        for (index, sym) in self.symbols.iter().enumerate() {
+            if sym.kind == SymbolType::Undefined || sym.index != index {
+                findings.push(Finding::Undefined { table: "symbol table", index });
+            }
+        }
+        for kind in [SymbolType::EndOfFile, SymbolType::Error] {
+            if self.symbols.get_by_type(kind).is_none() {
+                findings.push(Finding::MissingSymbol(kind));
+            }
+        }
+    }
+
+    fn validate_groups(&self, findings: &mut Vec<Finding>) {
+        const TABLE: &str = "group table";
+        for (index, group) in self.groups.iter().enumerate() {
+            if group.index != index {
+                findings.push(Finding::Undefined { table: TABLE, index });
+                continue;
+            }
+            self.check_symbol(findings, TABLE, index, "container", group.container_idx, is_terminal, "a terminal");
+            self.check_symbol(findings, TABLE, index, "start", group.start_idx,
+                |k| k == SymbolType::GroupStart, "a group start");
+            self.check_symbol(findings, TABLE, index, "end", group.end_idx, is_terminal, "a terminal");
+            for nested in &group.nested {
+                if *nested >= self.groups.len() {
+                    findings.push(Finding::IndexOutOfRange {
+                        table: TABLE, index, field: "nested group", target: TABLE, value: *nested, len: self.groups.len(),
+                    });
+                }
+            }
+        }
+    }
+
+    fn validate_productions(&self, findings: &mut Vec<Finding>) {
+        const TABLE: &str = "production table";
+        for (index, rule) in self.productions.iter().enumerate() {
+            if rule.index != index || rule.head.kind == SymbolType::Undefined {
+                findings.push(Finding::Undefined { table: TABLE, index });
+                continue;
+            }
+            self.check_symbol(findings, TABLE, index, "head", rule.head.index,
+                |k| k == SymbolType::NonTerminal, "a nonterminal");
+            for sym in rule.symbols.iter() {
+                self.check_symbol(findings, TABLE, index, "body", sym.index,
+                    |k| matches!(k, SymbolType::NonTerminal | SymbolType::Terminal), "a terminal or nonterminal");
+            }
+        }
+    }
+
+    fn validate_dfa(&self, findings: &mut Vec<Finding>) {
+        const TABLE: &str = "DFA state table";
+        for (index, state) in self.dfa_states.iter().enumerate() {
+            if state.index != index {
+                findings.push(Finding::Undefined { table: TABLE, index });
+                continue;
+            }
+            if state.accept {
+                self.check_symbol(findings, TABLE, index, "accept symbol", state.accept_symbol.index,
+                    |k| is_terminal(k) && k != SymbolType::EndOfFile, "a terminal");
+            }
+            for edge in &state.edges {
+                if edge.charset_idx >= self.charset.len() {
+                    findings.push(Finding::IndexOutOfRange {
+                        table: TABLE, index, field: "edge character set", target: "character set table",
+                        value: edge.charset_idx, len: self.charset.len(),
+                    });
+                }
+                if edge.target_state >= self.dfa_states.len() {
+                    findings.push(Finding::IndexOutOfRange {
+                        table: TABLE, index, field: "edge target", target: TABLE,
+                        value: edge.target_state, len: self.dfa_states.len(),
+                    });
+                }
+            }
+        }
+    }
+
+    fn validate_lalr(&self, findings: &mut Vec<Finding>) {
+        const TABLE: &str = "LALR state table";
+        for (index, state) in self.lalr_states.iter().enumerate() {
+            if state.index != index {
+                findings.push(Finding::Undefined { table: TABLE, index });
+                continue;
+            }
+            for action in &state.actions {
+                let symbol = action.symbol.index;
+                let (target, len) = match action.action {
+                    ActionType::Shift => {
+                        self.check_symbol(findings, TABLE, index, "shift symbol", symbol, is_terminal, "a terminal");
+                        (Some(TABLE), self.lalr_states.len())
+                    },
+                    ActionType::Reduce => {
+                        self.check_symbol(findings, TABLE, index, "reduce lookahead", symbol, is_terminal, "a terminal");
+                        (Some("production table"), self.productions.len())
+                    },
+                    ActionType::Goto => {
+                        self.check_symbol(findings, TABLE, index, "goto symbol", symbol,
+                            |k| k == SymbolType::NonTerminal, "a nonterminal");
+                        (Some(TABLE), self.lalr_states.len())
+                    },
+                    ActionType::Accept => {
+                        self.check_symbol(findings, TABLE, index, "accept symbol", symbol,
+                            |k| k == SymbolType::EndOfFile, "the end of file");
+                        (None, 0)
+                    },
+                    ActionType::Undefined => {
+                        findings.push(Finding::Undefined { table: TABLE, index });
+                        (None, 0)
+                    },
+                };
+                if let Some(target) = target {
+                    if action.target_idx >= len {
+                        findings.push(Finding::IndexOutOfRange {
+                            table: TABLE, index, field: "action target", target, value: action.target_idx, len,
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    /// Checks that `symbol` is in the symbol table and that `valid` accepts its type
+    #[allow(clippy::too_many_arguments)]
+    fn check_symbol(&self, findings: &mut Vec<Finding>, table: &'static str, index: usize, field: &'static str,
+        symbol: usize, valid: fn(SymbolType) -> bool, expected: &'static str) {
+        match (symbol < self.symbols.len()).then(|| self.symbols[symbol].kind) {
+            None => findings.push(Finding::IndexOutOfRange {
+                table, index, field, target: "symbol table", value: symbol, len: self.symbols.len(),
+            }),
+            Some(kind) if !valid(kind) => findings.push(Finding::SymbolType { table, index, field, symbol, kind, expected }),
+            Some(_) => {},
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::engine::{Builder, EgtError, EnhancedGrammarTable, RecordType, SymbolType, Writer, states::ActionType, tables::Table};
+
+    use super::Finding;
+
+    #[test]
+    fn valid() {
+        for file in [crate::test::GP_SIMPLE_EGT, crate::test::GP_TINY_EGT] {
+            assert_eq!(gen_egt(file).validate(), vec![], "{file}");
+        }
+    }
+
+    #[test]
+    fn counts() {
+        let mut egt = gen_egt(crate::test::GP_SIMPLE_EGT);
+        egt.counts.rules += 1;
+        egt.initial_states.lalr = 500;
+        let findings = egt.validate();
+        assert!(findings.contains(&Finding::CountMismatch { table: "production table", expected: 29, found: 28 }));
+        assert!(findings.iter().any(|f| matches!(f, Finding::IndexOutOfRange { table: "initial states", value: 500, .. })));
+    }
+
+    #[test]
+    fn targets() {
+        let mut egt = gen_egt(crate::test::GP_SIMPLE_EGT);
+        egt.dfa_states[0].edges[0].target_state = 99;
+        let state = egt.lalr_states.iter()
+            .position(|s| s.actions.iter().any(|a| a.action == ActionType::Reduce)).unwrap();
+        for action in egt.lalr_states[state].actions.iter_mut().filter(|a| a.action == ActionType::Reduce) {
+            action.target_idx = 28;
+        }
+
+        let findings = egt.validate();
+        assert_eq!(findings[0].to_string(), "DFA state table[0] edge target refers to DFA state table[99], which has 59 entries");
+        assert!(findings[1..].iter().all(|f| matches!(f, 
+            Finding::IndexOutOfRange { table: "LALR state table", target: "production table", value: 28, index, .. } if *index == state)));
+    }
+
+    #[test]
+    fn symbol_types() {
+        let mut egt = gen_egt(crate::test::GP_SIMPLE_EGT);
+        let id = egt.symbols.get("Id".to_string()).unwrap().clone();
+        egt.productions[0].head = id.clone();
+        let findings = egt.validate();
+        assert_eq!(findings, vec![Finding::SymbolType {
+            table: "production table", index: 0, field: "head", symbol: id.index, kind: SymbolType::Terminal, expected: "a nonterminal",
+        }]);
+    }
+
+    #[test]
+    fn load_fails() {
+        // drop the record defining the last symbol
+        let egt = gen_egt(crate::test::GP_SIMPLE_EGT);
+        let mut records = Writer::records(&egt).unwrap();
+        let last = records.iter().rposition(|r| r.kind == RecordType::Symbol).unwrap();
+        records.remove(last);
+        let mut writer = Writer::new();
+        writer.write_string(EnhancedGrammarTable::EGT_HEADER);
+        for rec in &records {
+            writer.write_logical_record(rec);
+        }
+        match Builder::from_bytes(&writer.into_bytes()).to_egt() {
+            Err(EgtError::Invalid(findings)) => {
+                assert!(findings.contains(&Finding::Undefined { table: "symbol table", index: 35 }), "{findings:?}");
+            },
+            other => panic!("expected Invalid, got {:?}", other.err()),
+        }
+    }
+
+    fn gen_egt(file: &str) -> EnhancedGrammarTable {
+        Builder::from_path(file).unwrap().to_egt().unwrap()
+    }
+}
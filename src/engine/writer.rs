@@ -0,0 +1,315 @@
+//! Enhanced Grammar Table Writer
+//!
+//! The inverse of the `Builder`. Serializes an `EnhancedGrammarTable` back into the
+//! binary `GOLD Parser Tables/v5.0` format. Records are written in the same order
+//! GOLD Builder uses, so a table read from a GOLD generated file is written back
+//! byte-for-byte.
+//!
+//! A character set record holds a single Unicode plane. A set spanning several planes, as the
+//! grammar compiler may build, is written as one record per plane: the first at the index of
+//! the set, the others added after the last set, and every DFA edge on the set gets an edge
+//! to the same state for each of them.
+//!
+//! http://goldparser.org/doc/egt/structure-record.htm
+
+use std::io::{self, Write};
+
+use thiserror::Error;
+use utf16string::{WString, LE};
+
+use crate::engine::{
+    LogicalRecord, RecordType, EntryType, RecordEntry,
+    egt::EnhancedGrammarTable,
+    CharacterSet,
+};
+
+#[derive(Debug, Error)]
+pub enum WriteError {
+    #[error("unable to write grammar table: {0}")]
+    Io(#[from] io::Error),
+    /// An index, count or character set range does not fit the 16 bit integers of the format
+    #[error("{value} does not fit in an EGT integer")]
+    TooLarge { value: usize },
+}
+
+/// Serializes `egt` into the bytes of an EGT file
+pub fn write(egt: &EnhancedGrammarTable) -> Result<Vec<u8>, WriteError> {
+    let mut writer = Writer::new();
+    writer.write_egt(egt)?;
+    Ok(writer.into_bytes())
+}
+
+/// Serializes `egt` into the writer `w`
+pub fn write_to<W: Write>(egt: &EnhancedGrammarTable, mut w: W) -> Result<(), WriteError> {
+    Ok(w.write_all(&write(egt)?)?)
+}
+
+#[derive(Debug,Default)]
+/// The `Writer`
+pub struct Writer {
+    /// The raw bytes of the EGT file written so far
+    bytes: Vec<u8>,
+}
+
+impl Writer {
+    pub fn new() -> Self {
+        Writer { bytes: Vec::new() }
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    /// Writes the header followed by every record of `egt`
+    pub fn write_egt(&mut self, egt: &EnhancedGrammarTable) -> Result<(), WriteError> {
+        self.write_string(&egt.header);
+        for record in Self::records(egt)? {
+            self.write_logical_record(&record);
+        }
+        Ok(())
+    }
+
+    /// Converts the tables of `egt` into `LogicalRecord`s in file order
+    pub fn records(egt: &EnhancedGrammarTable) -> Result<Vec<LogicalRecord>, WriteError> {
+        let mut records = Vec::new();
+
+        // the planes of each set, and the indices of the records written for them
+        let planes = egt.charset.iter().map(planes).collect::<Vec<_>>();
+        let mut added = egt.charset.len();
+        let charset_idx = planes.iter().enumerate().map(|(index, planes)| {
+            let extra = added..added + planes.len() - 1;
+            added = extra.end;
+            std::iter::once(index).chain(extra).collect::<Vec<_>>()
+        }).collect::<Vec<_>>();
+
+        for prop in egt.properties.iter() {
+            records.push(record(RecordType::Property, vec![
+                int(prop.index)?, string(&prop.name), string(&prop.value),
+            ])?);
+        }
+        let c = &egt.counts;
+        records.push(record(RecordType::Counts, vec![
+            RecordEntry::Integer(c.symtab), int(c.charset as usize + added - egt.charset.len())?,
+            RecordEntry::Integer(c.rules), RecordEntry::Integer(c.dfatab),
+            RecordEntry::Integer(c.lalrtab), RecordEntry::Integer(c.lexgroups),
+        ])?);
+        records.push(record(RecordType::InitState, vec![
+            RecordEntry::Integer(egt.initial_states.dfa), RecordEntry::Integer(egt.initial_states.lalr),
+        ])?);
+        // the first plane of every set, then the others, in the order their indices were given
+        for (planes, indices) in planes.iter().zip(&charset_idx) {
+            records.push(charset_record(indices[0], planes[0].0, &planes[0].1)?);
+        }
+        for (planes, indices) in planes.iter().zip(&charset_idx) {
+            for ((plane, ranges), &index) in planes.iter().zip(indices).skip(1) {
+                records.push(charset_record(index, *plane, ranges)?);
+            }
+        }
+        for sym in egt.symbols.iter() {
+            records.push(record(RecordType::Symbol, vec![
+                int(sym.index)?, string(&sym.name), RecordEntry::Integer(sym.kind as u16),
+            ])?);
+        }
+        for group in egt.groups.iter() {
+            let mut entries = vec![
+                int(group.index)?, string(&group.name),
+                int(group.container_idx)?, int(group.start_idx)?, int(group.end_idx)?,
+                RecordEntry::Integer(group.advance_mode as u16), RecordEntry::Integer(group.ending_mode as u16),
+                RecordEntry::Empty, int(group.nested.len())?,
+            ];
+            for g in &group.nested {
+                entries.push(int(*g)?);
+            }
+            records.push(record(RecordType::Group, entries)?);
+        }
+        for rule in egt.productions.iter() {
+            let mut entries = vec![int(rule.index)?, int(rule.head.index)?, RecordEntry::Empty];
+            for s in rule.symbols.iter() {
+                entries.push(int(s.index)?);
+            }
+            records.push(record(RecordType::Production, entries)?);
+        }
+        for state in egt.dfa_states.iter() {
+            let accept = if state.accept { state.accept_symbol.index } else { 0 };
+            let mut entries = vec![
+                int(state.index)?, RecordEntry::Bool(state.accept as u8), int(accept)?, RecordEntry::Empty,
+            ];
+            for edge in &state.edges {
+                let single = [edge.charset_idx];
+                let indices = charset_idx.get(edge.charset_idx).map_or(&single[..], Vec::as_slice);
+                for &index in indices {
+                    entries.extend([int(index)?, int(edge.target_state)?, RecordEntry::Empty]);
+                }
+            }
+            records.push(record(RecordType::DFA, entries)?);
+        }
+        for state in egt.lalr_states.iter() {
+            let mut entries = vec![int(state.index)?, RecordEntry::Empty];
+            for action in &state.actions {
+                entries.extend([
+                    int(action.symbol.index)?, RecordEntry::Integer(action.action as u16),
+                    int(action.target_idx)?, RecordEntry::Empty,
+                ]);
+            }
+            records.push(record(RecordType::LALR, entries)?);
+        }
+        Ok(records)
+    }
+
+    /// Writes the 'M' marker, entry count, record type and entries of `record`
+    pub fn write_logical_record(&mut self, record: &LogicalRecord) {
+        self.write_byte(RecordType::Multi as u8);
+        self.write_u16(record.entries.len() as u16 + 1);
+        self.write_byte(EntryType::Byte as u8);
+        self.write_byte(record.kind as u8);
+        for entry in &record.entries {
+            self.write_entry(entry);
+        }
+    }
+
+    fn write_entry(&mut self, entry: &RecordEntry) {
+        self.write_byte(entry.kind() as u8);
+        match entry {
+            RecordEntry::Empty => {},
+            RecordEntry::Byte(b) |
+            RecordEntry::Bool(b) => self.write_byte(*b),
+            RecordEntry::Integer(i) => self.write_u16(*i),
+            RecordEntry::String(s) => self.write_wstring(s),
+        }
+    }
+
+    pub fn write_string(&mut self, s: &str) {
+        self.write_wstring(&WString::from(s));
+    }
+    fn write_wstring(&mut self, s: &WString<LE>) {
+        self.bytes.extend_from_slice(s.as_bytes());
+        self.write_u16(0);
+    }
+    pub fn write_byte(&mut self, byte: u8) {
+        self.bytes.push(byte);
+    }
+    pub fn write_u16(&mut self, i: u16) {
+        self.bytes.extend_from_slice(&i.to_le_bytes());
+    }
+}
+
+/// The Unicode planes `set` has characters on, in order, each with the low 16 bits of the
+/// ranges on it. An empty set is on plane 0.
+fn planes(set: &CharacterSet) -> Vec<(u32, Vec<(u32, u32)>)> {
+    let mut planes = Vec::<(u32, Vec<(u32, u32)>)>::new();
+    for range in set.ranges() {
+        let (mut start, end) = (*range.start() as u32, *range.end() as u32);
+        while start <= end {
+            let plane = start >> 16;
+            let last = end.min(plane << 16 | 0xFFFF);
+            match planes.last_mut() {
+                Some((p, ranges)) if *p == plane => ranges.push((start & 0xFFFF, last & 0xFFFF)),
+                _ => planes.push((plane, vec![(start & 0xFFFF, last & 0xFFFF)])),
+            }
+            start = last + 1;
+        }
+    }
+    if planes.is_empty() {
+        planes.push((0, Vec::new()));
+    }
+    planes
+}
+
+fn charset_record(index: usize, plane: u32, ranges: &[(u32, u32)]) -> Result<LogicalRecord, WriteError> {
+    let mut entries = vec![int(index)?, int(plane as usize)?, int(ranges.len())?, RecordEntry::Empty];
+    for &(start, end) in ranges {
+        entries.extend([int(start as usize)?, int(end as usize)?]);
+    }
+    record(RecordType::CharSet, entries)
+}
+
+fn record(kind: RecordType, entries: Vec<RecordEntry>) -> Result<LogicalRecord, WriteError> {
+    let count = u16::try_from(entries.len() + 1).map_err(|_| WriteError::TooLarge { value: entries.len() + 1 })?;
+    let mut rec = LogicalRecord::new(count, kind);
+    rec.entries = entries;
+    Ok(rec)
+}
+#[inline(always)]
+fn int(i: usize) -> Result<RecordEntry, WriteError> {
+    u16::try_from(i).map(RecordEntry::Integer).map_err(|_| WriteError::TooLarge { value: i })
+}
+#[inline(always)]
+fn string(s: &str) -> RecordEntry {
+    RecordEntry::String(WString::from(s))
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::{engine::{Builder, CharacterSet, Lexer, tables::Table}, grammar::compile};
+
+    use super::{write, WriteError};
+
+    #[test]
+    fn round_trip() {
+        for file in [crate::test::GP_SIMPLE_EGT, crate::test::GP_TINY_EGT] {
+            let bytes = std::fs::read(file).unwrap();
+            let egt = Builder::from_bytes(&bytes).to_egt().unwrap();
+            assert_eq!(write(&egt).unwrap(), bytes, "{file}");
+        }
+    }
+
+    #[test]
+    fn supplementary_planes() {
+        let bytes = std::fs::read(crate::test::GP_SIMPLE_EGT).unwrap();
+        let mut egt = Builder::from_bytes(&bytes).to_egt().unwrap();
+        egt.charset[0] = CharacterSet::new(vec!['\u{1F600}'..='\u{1F64F}', '\u{1F680}'..='\u{1F6FF}']);
+        egt.charset[1] = CharacterSet::new(vec!['\u{D000}'..='\u{E0FF}']);
+
+        let patched = Builder::from_bytes(&write(&egt).unwrap()).to_egt().unwrap();
+        assert_eq!(patched.charset[0], egt.charset[0]);
+        assert!(patched.charset[0].contains('😀'));
+        assert!(!patched.charset[0].contains('\u{F600}'));
+        assert_eq!(patched.charset[1], egt.charset[1]);
+        assert!(patched.charset[1].contains('\u{D7FF}') && patched.charset[1].contains('\u{E000}'));
+    }
+
+    #[test]
+    fn multi_plane_sets() {
+        let egt = compile(concat!(
+            "\"Start Symbol\" = <List>\n",
+            "{W} = {Letter} + {&1F600 .. &1F64F}\n",
+            "Word = {W}+\n",
+            "<List> ::= <List> Word | Word\n",
+        )).unwrap();
+        let written = Builder::from_bytes(&write(&egt).unwrap()).to_egt().unwrap();
+        assert!(written.charset.len() > egt.charset.len());
+        assert_eq!(written.counts.charset as usize, written.charset.len());
+        for egt in [&egt, &written] {
+            let texts = Lexer::new(egt, "a😀b 🙏").noise(false).map(|t| t.text).collect::<Vec<_>>();
+            assert_eq!(texts, vec!["a😀b", "🙏"]);
+        }
+        // and the written table is written back as it was read
+        assert_eq!(write(&written).unwrap(), write(&egt).unwrap());
+    }
+
+    #[test]
+    fn too_large() {
+        let bytes = std::fs::read(crate::test::GP_SIMPLE_EGT).unwrap();
+        let mut egt = Builder::from_bytes(&bytes).to_egt().unwrap();
+        egt.dfa_states[0].edges[0].target_state = 70000;
+        assert!(matches!(write(&egt), Err(WriteError::TooLarge { value: 70000 })));
+    }
+
+    #[test]
+    fn patched() {
+        let bytes = std::fs::read(crate::test::GP_SIMPLE_EGT).unwrap();
+        let mut egt = Builder::from_bytes(&bytes).to_egt().unwrap();
+        egt.properties[0].value = "Simpler".to_string();
+        let idx = egt.symbols.get("Id".to_string()).unwrap().index;
+        let mut sym = egt.symbols[idx].clone();
+        sym.name = "Identifier".to_string();
+        egt.symbols.add(sym);
+
+        let patched = Builder::from_bytes(&write(&egt).unwrap()).to_egt().unwrap();
+        assert_eq!(patched.property("Name"), Some("Simpler"));
+        assert_eq!(patched.symbols[idx].name, "Identifier");
+        assert_eq!(patched.symbols.len(), egt.symbols.len());
+        assert_eq!(patched.dfa_states.len(), egt.dfa_states.len());
+    }
+}
@@ -0,0 +1,337 @@
+//! Grammar analysis
+//!
+//! Computes the properties of a grammar worth checking when reviewing it: which nonterminals
+//! derive the empty string, FIRST and FOLLOW sets, symbols no sentence can contain,
+//! nonterminals that never derive a string of terminals, and left recursion.
+//!
+//! ```no_run
+//! use goldparser::{engine::Builder, grammar::analysis::Analysis};
+//!
+//! let egt = Builder::from_path("examples/simple.egt").and_then(|mut b| b.to_egt()).unwrap();
+//! println!("{}", Analysis::new(&egt));
+//! ```
+
+use std::collections::{BTreeSet, VecDeque};
+use std::fmt::Display;
+
+use crate::engine::{states::ActionType, tables::Table, EnhancedGrammarTable, Symbol, SymbolType};
+
+/// The start symbol of a loaded grammar: the one whose goto from the initial LALR state leads
+/// to the state accepting on `EOF`
+pub fn start_symbol(egt: &EnhancedGrammarTable) -> Option<&Symbol> {
+    let states = &egt.lalr_states;
+    let initial = egt.initial_states.lalr as usize;
+    if initial >= states.len() {
+        return None;
+    }
+    let accepts = |state: usize| state < states.len()
+        && states[state].actions.iter().any(|a| a.action == ActionType::Accept);
+    states[initial].actions.iter()
+        .find(|a| a.action == ActionType::Goto && accepts(a.target_idx))
+        .map(|a| &a.symbol)
+}
+
+/// Which symbols derive the empty string, and the FIRST set of each, for rules given as the
+/// symbol indices of their head and body
+pub(crate) struct FirstSets {
+    pub nullable: Vec<bool>,
+    pub first: Vec<BTreeSet<usize>>,
+}
+
+impl FirstSets {
+    /// Computes the sets of `count` symbols to a fixpoint. The FIRST set of a symbol for
+    /// which `terminal` is true is the symbol itself.
+    pub fn new<'r>(count: usize, terminal: impl Fn(usize) -> bool, rules: impl Iterator<Item = (usize, &'r [usize])> + Clone) -> Self {
+        let mut sets = FirstSets {
+            nullable: vec![false; count],
+            first: (0..count).map(|s| if terminal(s) { BTreeSet::from([s]) } else { BTreeSet::new() }).collect(),
+        };
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (head, body) in rules.clone() {
+                if !sets.nullable[head] && body.iter().all(|s| sets.nullable[*s]) {
+                    sets.nullable[head] = true;
+                    changed = true;
+                }
+                let (first, _) = sets.first_of(body);
+                if !first.is_subset(&sets.first[head]) {
+                    sets.first[head].extend(first);
+                    changed = true;
+                }
+            }
+        }
+        sets
+    }
+
+    /// FIRST of a sequence of symbols, and whether the whole sequence is nullable
+    pub fn first_of(&self, symbols: &[usize]) -> (BTreeSet<usize>, bool) {
+        let mut first = BTreeSet::new();
+        for symbol in symbols {
+            first.extend(self.first[*symbol].iter().copied());
+            if !self.nullable[*symbol] {
+                return (first, false);
+            }
+        }
+        (first, true)
+    }
+}
+
+/// Nullable, FIRST, FOLLOW, reachable and productive symbols of a grammar table
+pub struct Analysis<'a> {
+    egt: &'a EnhancedGrammarTable,
+    start: Option<usize>,
+    sets: FirstSets,
+    follow: Vec<BTreeSet<usize>>,
+    reachable: Vec<bool>,
+    productive: Vec<bool>,
+}
+
+impl<'a> Analysis<'a> {
+    /// Analyses the grammar starting from the symbol found by `start_symbol`
+    pub fn new(egt: &'a EnhancedGrammarTable) -> Self {
+        Analysis::with_start(egt, start_symbol(egt).map(|s| s.index))
+    }
+
+    /// Analyses the grammar from `start`. Without a start symbol nothing is reachable
+    /// and FOLLOW sets do not contain `EOF`.
+    pub fn with_start(egt: &'a EnhancedGrammarTable, start: Option<usize>) -> Self {
+        let count = egt.symbols.iter().count();
+        let terminal = |index: usize| egt.symbols[index].kind != SymbolType::NonTerminal;
+        let rules = egt.productions.iter()
+            .map(|rule| (rule.head.index, rule.symbols.iter().map(|s| s.index).collect::<Vec<_>>()))
+            .collect::<Vec<_>>();
+        let mut analysis = Analysis {
+            egt, start,
+            sets: FirstSets::new(count, terminal, rules.iter().map(|(head, body)| (*head, body.as_slice()))),
+            follow: vec![BTreeSet::new(); count],
+            reachable: vec![false; count],
+            productive: (0..count).map(terminal).collect(),
+        };
+
+        // productive to a fixpoint
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (head, body) in &rules {
+                if !analysis.productive[*head] && body.iter().all(|s| analysis.productive[*s]) {
+                    analysis.productive[*head] = true;
+                    changed = true;
+                }
+            }
+        }
+
+        // FOLLOW
+        let eof = egt.symbols.get_by_type(SymbolType::EndOfFile).map(|s| s.index);
+        if let (Some(start), Some(eof)) = (start, eof) {
+            analysis.follow[start].insert(eof);
+        }
+        changed = true;
+        while changed {
+            changed = false;
+            for (head, body) in &rules {
+                for (position, symbol) in body.iter().enumerate() {
+                    let (mut follow, nullable) = analysis.sets.first_of(&body[position + 1..]);
+                    if nullable {
+                        follow.extend(analysis.follow[*head].iter().copied());
+                    }
+                    if !follow.is_subset(&analysis.follow[*symbol]) {
+                        analysis.follow[*symbol].extend(follow);
+                        changed = true;
+                    }
+                }
+            }
+        }
+
+        // reachable from the start symbol
+        let mut work = start.into_iter().collect::<Vec<_>>();
+        while let Some(symbol) = work.pop() {
+            if std::mem::replace(&mut analysis.reachable[symbol], true) {
+                continue;
+            }
+            for rule in egt.productions.iter().filter(|r| r.head.index == symbol) {
+                work.extend(rule.symbols.iter().map(|s| s.index));
+            }
+        }
+        analysis
+    }
+
+    fn symbols(&self, indices: impl IntoIterator<Item = usize>) -> Vec<&'a Symbol> {
+        indices.into_iter().map(|s| &self.egt.symbols[s]).collect()
+    }
+
+    fn nonterminals(&self) -> impl Iterator<Item = &'a Symbol> {
+        self.egt.symbols.iter().filter(|s| s.kind == SymbolType::NonTerminal)
+    }
+
+    pub fn start(&self) -> Option<&'a Symbol> {
+        self.start.map(|s| &self.egt.symbols[s])
+    }
+
+    /// Nonterminals deriving the empty string
+    pub fn nullable(&self) -> Vec<&'a Symbol> {
+        self.nonterminals().filter(|s| self.sets.nullable[s.index]).collect()
+    }
+
+    /// Terminals which can begin a string derived from `symbol`
+    pub fn first(&self, symbol: &Symbol) -> Vec<&'a Symbol> {
+        self.symbols(self.sets.first[symbol.index].iter().copied())
+    }
+
+    /// Terminals which can follow `symbol` in a sentence
+    pub fn follow(&self, symbol: &Symbol) -> Vec<&'a Symbol> {
+        self.symbols(self.follow[symbol.index].iter().copied())
+    }
+
+    /// Terminals and nonterminals no sentence derived from the start symbol contains
+    pub fn unreachable(&self) -> Vec<&'a Symbol> {
+        self.egt.symbols.iter()
+            .filter(|s| matches!(s.kind, SymbolType::Terminal | SymbolType::NonTerminal) && !self.reachable[s.index])
+            .collect()
+    }
+
+    /// Nonterminals which never derive a string of terminals
+    pub fn unproductive(&self) -> Vec<&'a Symbol> {
+        self.nonterminals().filter(|s| !self.productive[s.index]).collect()
+    }
+
+    /// Cycles of left recursive nonterminals: each list starts a rule with the next one,
+    /// after symbols deriving the empty string, and the last one with the first.
+    /// Direct left recursion is a cycle of one.
+    pub fn left_recursion(&self) -> Vec<Vec<&'a Symbol>> {
+        let count = self.sets.nullable.len();
+        let mut edges = vec![BTreeSet::new(); count];
+        for rule in self.egt.productions.iter() {
+            for symbol in rule.symbols.iter() {
+                if symbol.kind == SymbolType::NonTerminal {
+                    edges[rule.head.index].insert(symbol.index);
+                }
+                if !self.sets.nullable[symbol.index] {
+                    break;
+                }
+            }
+        }
+
+        // the shortest cycle through each nonterminal not already on a cycle found
+        let mut cycles = Vec::new();
+        let mut covered = vec![false; count];
+        for from in self.nonterminals().map(|s| s.index) {
+            if covered[from] {
+                continue;
+            }
+            let mut parent = vec![None; count];
+            let mut queue = VecDeque::from([from]);
+            let mut found = None;
+            while let Some(symbol) = queue.pop_front() {
+                if edges[symbol].contains(&from) {
+                    found = Some(symbol);
+                    break;
+                }
+                for next in &edges[symbol] {
+                    if parent[*next].is_none() && *next != from {
+                        parent[*next] = Some(symbol);
+                        queue.push_back(*next);
+                    }
+                }
+            }
+            let Some(mut symbol) = found else { continue; };
+            let mut cycle = vec![symbol];
+            while symbol != from {
+                symbol = parent[symbol].unwrap();
+                cycle.push(symbol);
+            }
+            cycle.reverse();
+            for symbol in &cycle {
+                covered[*symbol] = true;
+            }
+            cycles.push(self.symbols(cycle));
+        }
+        cycles
+    }
+}
+
+impl Display for Analysis<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let list = |symbols: Vec<&Symbol>| match symbols.is_empty() {
+            true => "None".to_string(),
+            false => symbols.iter().map(|s| s.as_handle()).collect::<Vec<_>>().join(" "),
+        };
+        writeln!(f, "Start symbol: {}", self.start().map_or("None".to_string(), |s| s.as_handle()))?;
+        writeln!(f, "Nullable:     {}", list(self.nullable()))?;
+        writeln!(f, "Unreachable:  {}", list(self.unreachable()))?;
+        writeln!(f, "Unproductive: {}", list(self.unproductive()))?;
+        let cycles = self.left_recursion().into_iter()
+            .map(|cycle| cycle.iter().chain(cycle.first()).map(|s| s.as_handle()).collect::<Vec<_>>().join(" -> "))
+            .collect::<Vec<_>>();
+        writeln!(f, "Left recursion: {}", if cycles.is_empty() { "None".to_string() } else { cycles.join(", ") })?;
+        for symbol in self.nonterminals() {
+            writeln!(f, "\n{}", symbol.as_handle())?;
+            writeln!(f, "  FIRST:  {}", list(self.first(symbol)))?;
+            writeln!(f, "  FOLLOW: {}", list(self.follow(symbol)))?;
+        }
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::{engine::{Builder, SymbolType}, grammar::compile};
+
+    use super::{start_symbol, Analysis};
+
+    fn names(symbols: Vec<&crate::engine::Symbol>) -> Vec<&str> {
+        symbols.into_iter().map(|s| s.name.as_str()).collect()
+    }
+
+    #[test]
+    fn simple() {
+        let egt = Builder::from_path(crate::test::GP_SIMPLE_EGT).unwrap().to_egt().unwrap();
+        let analysis = Analysis::new(&egt);
+        assert_eq!(analysis.start().unwrap().name, "Statements");
+        assert!(analysis.nullable().is_empty());
+        assert!(analysis.unreachable().is_empty());
+        assert!(analysis.unproductive().is_empty());
+
+        let statements = egt.symbols.get("Statements".to_string()).unwrap();
+        let mut first = names(analysis.first(statements));
+        first.sort_unstable();
+        assert_eq!(first, vec!["assign", "display", "if", "while"]);
+        assert!(names(analysis.follow(statements)).contains(&"EOF"));
+        let expression = egt.symbols.get("Expression".to_string()).unwrap();
+        assert!(names(analysis.follow(expression)).contains(&"then"));
+
+        let cycles = analysis.left_recursion().into_iter().map(names).collect::<Vec<_>>();
+        assert!(cycles.contains(&vec!["Add Exp"]));
+        assert!(cycles.iter().all(|c| c.len() == 1));
+    }
+
+    #[test]
+    fn problems() {
+        let egt = compile(concat!(
+            "\"Start Symbol\" = <S>\n",
+            "<S> ::= <A> x | <Loop> | '(' <Opt> ')'\n",
+            "<A> ::= <B> | a\n",
+            "<B> ::= <A> b\n",
+            "<Loop> ::= <Loop> w\n",
+            "<Opt> ::= o |\n",
+            "<Lost> ::= l\n",
+        )).unwrap();
+        assert_eq!(start_symbol(&egt).unwrap().name, "S");
+        let analysis = Analysis::new(&egt);
+        assert_eq!(names(analysis.nullable()), vec!["Opt"]);
+        assert_eq!(names(analysis.unreachable()), vec!["l", "Lost"]);
+        assert_eq!(names(analysis.unproductive()), vec!["Loop"]);
+        let cycles = analysis.left_recursion().into_iter().map(names).collect::<Vec<_>>();
+        assert_eq!(cycles, vec![vec!["A", "B"], vec!["Loop"]]);
+
+        let opt = egt.symbols.get("Opt".to_string()).unwrap();
+        assert_eq!(names(analysis.first(opt)), vec!["o"]);
+        assert_eq!(names(analysis.follow(opt)), vec![")"]);
+        let s = egt.symbols.get("S".to_string()).unwrap();
+        assert!(analysis.first(s).iter().all(|s| s.kind == SymbolType::Terminal));
+        assert_eq!(names(analysis.follow(s)), vec!["EOF"]);
+        let report = analysis.to_string();
+        assert!(report.contains("Left recursion: <A> -> <B> -> <A>, <Loop> -> <Loop>\n"), "{report}");
+    }
+}
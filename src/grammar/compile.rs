@@ -0,0 +1,546 @@
+//! Compiles a `Grammar` into an `EnhancedGrammarTable`
+//!
+//! Symbols are numbered the way GOLD Builder numbers them: `EOF` and `Error` first, then
+//! noise terminals, group start and end terminals, the remaining terminals and finally the
+//! nonterminals, each sorted by name. Productions keep the order of the source.
+//!
+//! Like GOLD Builder, the compiler
+//! * defines `Whitespace = {Whitespace}+` as noise unless the grammar defines it, leaving
+//!   out `{CR}` and `{LF}` when a `NewLine` terminal is defined
+//! * reads a terminal used in a rule but never defined as its literal text
+//! * turns `X Start`/`X End` into the group `X Block` and `X Line` into the group `X Line`,
+//!   ended by a `NewLine` terminal which is defined as `{CR}{LF} | {CR} | {LF}` if needed
+//! * treats symbol and set names as case insensitive
+//!
+//! EBNF groups in rule bodies are first replaced by generated nonterminals, see `ebnf`.
+
+use crate::engine::{
+    group::{AdvanceMode, EndingMode},
+    states::InitialStatesRecord,
+    tables::{ProductionTable, Table},
+    EnhancedGrammarTable, LexicalGroup, ProductionRule, Symbol, SymbolTable, SymbolType, TableCountsRecord,
+};
+
+use super::{
+    dfa::{self, LexerTerminal},
+    ebnf,
+    grm::{Grammar, RuleSymbol},
+    lalr::LALRBuilder,
+    regex::RegExp,
+    sets::{CharacterSets, SetItem, SetOp},
+    GrammarError,
+};
+
+/// A terminal before symbols are numbered
+struct Terminal {
+    name: String,
+    kind: SymbolType,
+    /// `None` for group containers, which the DFA never accepts directly
+    regex: Option<RegExp>,
+    line: usize,
+}
+
+/// The parts of a group declared with `X Start`, `X End` and `X Line`
+#[derive(Default)]
+struct GroupDecl {
+    container: String,
+    start: Option<(RegExp, usize)>,
+    end: Option<(RegExp, usize)>,
+    line: Option<(RegExp, usize)>,
+}
+
+/// A group with its symbols resolved to terminal list indices
+struct Group {
+    name: String,
+    container: usize,
+    start: usize,
+    end: usize,
+    advance: AdvanceMode,
+    ending: EndingMode,
+    nesting: Nesting,
+}
+
+enum Nesting {
+    None,
+    Itself,
+    All,
+}
+
+fn find(terminals: &[Terminal], name: &str) -> Option<usize> {
+    terminals.iter().position(|t| t.name.eq_ignore_ascii_case(name))
+}
+
+/// Splits `Comment Start` into (`Comment`, `Start`)
+fn group_part(name: &str) -> Option<(&str, &str)> {
+    let (container, part) = name.rsplit_once(' ')?;
+    ["Start", "End", "Line"].into_iter()
+        .find(|p| p.eq_ignore_ascii_case(part))
+        .map(|p| (container.trim_end(), p))
+}
+
+impl Grammar {
+    /// Compiles the grammar into tables `Parser` can use
+    pub fn compile(&self) -> Result<EnhancedGrammarTable, GrammarError> {
+        let case_sensitive = self.properties.case_sensitive().unwrap_or(false);
+        let mut sets = CharacterSets::new();
+        for decl in &self.sets {
+            let set = sets.evaluate(&decl.expr).map_err(|name| GrammarError::UndefinedSet { name, line: decl.line })?;
+            sets.define(&decl.name, set);
+        }
+
+        let mut terminals = Vec::<Terminal>::new();
+        let mut group_decls = Vec::<GroupDecl>::new();
+        for decl in &self.terminals {
+            let part = (decl.regex.clone(), decl.line);
+            match group_part(&decl.name) {
+                Some((container, kind)) => {
+                    let index = match group_decls.iter().position(|g| g.container.eq_ignore_ascii_case(container)) {
+                        Some(index) => index,
+                        None => {
+                            group_decls.push(GroupDecl { container: container.to_string(), ..Default::default() });
+                            group_decls.len() - 1
+                        },
+                    };
+                    let group = &mut group_decls[index];
+                    let slot = match kind {
+                        "Start" => &mut group.start,
+                        "End" => &mut group.end,
+                        _ => &mut group.line,
+                    };
+                    if slot.replace(part).is_some() {
+                        return Err(GrammarError::Redefined { name: decl.name.clone(), line: decl.line });
+                    }
+                },
+                None if find(&terminals, &decl.name).is_some() => {
+                    return Err(GrammarError::Redefined { name: decl.name.clone(), line: decl.line });
+                },
+                None => {
+                    let kind = if decl.name.eq_ignore_ascii_case("Whitespace") { SymbolType::Noise } else { SymbolType::Terminal };
+                    terminals.push(Terminal { name: decl.name.clone(), kind, regex: Some(decl.regex.clone()), line: decl.line });
+                },
+            }
+        }
+        let groups = self.groups(&group_decls, &mut terminals)?;
+        if find(&terminals, "Whitespace").is_none() {
+            // line endings must stay distinct from whitespace when they end line groups
+            let mut set = "Whitespace".to_string();
+            if find(&terminals, "NewLine").is_some() {
+                let name = |name: &str| SetItem::Name(name.to_string());
+                let expr = vec![(SetOp::Add, name("Whitespace")), (SetOp::Sub, name("CR")), (SetOp::Sub, name("LF"))];
+                set = "Whitespace Excluding NewLine".to_string();
+                sets.define(&set, sets.evaluate(&expr).unwrap_or_default());
+            }
+            let whitespace = RegExp::Plus(Box::new(RegExp::Set(SetItem::Name(set))));
+            terminals.push(Terminal { name: "Whitespace".to_string(), kind: SymbolType::Noise, regex: Some(whitespace), line: 0 });
+        }
+        for attr in &self.attributes {
+            let Some(index) = find(&terminals, &attr.name) else { continue; };
+            for (key, value) in &attr.attributes {
+                if !key.eq_ignore_ascii_case("Type") {
+                    continue;
+                }
+                terminals[index].kind = match value.to_ascii_lowercase().as_str() {
+                    "noise" => SymbolType::Noise,
+                    "content" => SymbolType::Terminal,
+                    _ => return Err(GrammarError::BadAttribute {
+                        name: attr.name.clone(), attribute: key.clone(), value: value.clone(), line: attr.line,
+                    }),
+                };
+            }
+        }
+
+        // terminals used by rules, and nonterminals in order of definition
+        if let Some(rule) = self.rules.iter().find(|rule| Symbol::is_group_name(&rule.head)) {
+            return Err(GrammarError::GroupName { name: rule.head.clone(), line: rule.line });
+        }
+        let decls = ebnf::desugar(&self.rules);
+        let mut nonterminals = Vec::<&str>::new();
+        for rule in &decls {
+            if !nonterminals.iter().any(|n| n.eq_ignore_ascii_case(&rule.head)) {
+                nonterminals.push(&rule.head);
+            }
+        }
+        for rule in &decls {
+            for symbol in rule.handles.iter().flatten() {
+                match symbol {
+                    RuleSymbol::Terminal(text) => match find(&terminals, text) {
+                        Some(index) if terminals[index].kind == SymbolType::Noise => terminals[index].kind = SymbolType::Terminal,
+                        Some(_) => {},
+                        None => terminals.push(Terminal {
+                            name: text.clone(), kind: SymbolType::Terminal, regex: Some(RegExp::Literal(text.clone())), line: rule.line,
+                        }),
+                    },
+                    RuleSymbol::Nonterminal(name) if !nonterminals.iter().any(|n| n.eq_ignore_ascii_case(name)) => {
+                        return Err(GrammarError::UndefinedNonterminal { name: name.clone(), line: rule.line });
+                    },
+                    RuleSymbol::Nonterminal(_) | RuleSymbol::Group(..) => {},
+                }
+            }
+        }
+
+        // number the symbols
+        let rank = |kind| match kind {
+            SymbolType::Noise => 0,
+            SymbolType::GroupStart | SymbolType::GroupEnd => 1,
+            SymbolType::Terminal => 2,
+            _ => 3,
+        };
+        let mut order = (0..terminals.len()).collect::<Vec<_>>();
+        order.sort_by_key(|t| (rank(terminals[*t].kind), terminals[*t].name.to_lowercase()));
+        let mut sorted_nonterminals = nonterminals.clone();
+        sorted_nonterminals.sort_by_key(|n| n.to_lowercase());
+
+        let mut symbols = SymbolTable::with_capacity(2 + terminals.len() + nonterminals.len());
+        symbols.push(Symbol::new(0, "EOF".to_string(), SymbolType::EndOfFile));
+        symbols.push(Symbol::new(1, "Error".to_string(), SymbolType::Error));
+        let mut terminal_index = vec![0; terminals.len()];
+        for t in order {
+            terminal_index[t] = symbols.len();
+            symbols.push(Symbol::new(symbols.len(), terminals[t].name.clone(), terminals[t].kind));
+        }
+        let first_nonterminal = symbols.len();
+        for name in &sorted_nonterminals {
+            symbols.push(Symbol::new(symbols.len(), name.to_string(), SymbolType::NonTerminal));
+        }
+        let nonterminal = |name: &str| sorted_nonterminals.iter()
+            .position(|n| n.eq_ignore_ascii_case(name))
+            .map(|i| first_nonterminal + i);
+        let terminal = |name: &str| find(&terminals, name).map(|t| terminal_index[t]);
+
+        // productions
+        let mut rules = Vec::new();
+        for rule in &decls {
+            let head = &symbols[nonterminal(&rule.head).unwrap()];
+            for handle in &rule.handles {
+                let body = handle.iter().map(|s| match s {
+                    RuleSymbol::Terminal(text) => symbols[terminal(text).unwrap()].clone(),
+                    RuleSymbol::Nonterminal(name) => symbols[nonterminal(name).unwrap()].clone(),
+                    RuleSymbol::Group(..) => unreachable!("groups are desugared"),
+                }).collect::<Vec<_>>();
+                rules.push(ProductionRule::new(rules.len(), head.clone(), SymbolTable::from(body)));
+            }
+        }
+        let mut productions = ProductionTable::with_capacity(rules.len());
+        productions.resize(rules.len());
+        for rule in rules {
+            productions.add(rule);
+        }
+
+        let start = self.properties.start_symbol().ok_or(GrammarError::NoStartSymbol)?;
+        let start = nonterminal(start).ok_or_else(|| GrammarError::UndefinedStartSymbol(start.to_string()))?;
+        let mut lalr = LALRBuilder::with_symbols(&symbols, &symbols[start], &productions);
+        for decl in &self.precedence {
+            let operators = decl.terminals.iter()
+                .map(|name| terminal(name).map(|t| symbols[t].clone())
+                    .ok_or_else(|| GrammarError::UndefinedTerminal { name: name.clone(), line: decl.line }))
+                .collect::<Result<Vec<_>, _>>()?;
+            lalr.precedence(decl.associativity, &operators);
+        }
+        let lalr_states = lalr.build().map_err(GrammarError::Conflicts)?;
+
+        let mut lexer_terminals = terminals.iter().enumerate()
+            .filter_map(|(t, terminal)| Some(LexerTerminal {
+                symbol: symbols[terminal_index[t]].clone(),
+                regex: terminal.regex.clone()?,
+                line: terminal.line,
+            }))
+            .collect::<Vec<_>>();
+        lexer_terminals.sort_by_key(|t| t.symbol.index);
+        let dfa = dfa::build(&lexer_terminals, &sets, case_sensitive)?;
+
+        let count = |table, count: usize| u16::try_from(count).map_err(|_| GrammarError::TooLarge { table, count });
+        let mut egt = EnhancedGrammarTable::new(EnhancedGrammarTable::EGT_HEADER.to_string());
+        egt.counts = TableCountsRecord::new(
+            count("symbol table", symbols.len())?,
+            count("character set table", dfa.charsets.len())?,
+            count("production table", productions.len())?,
+            count("DFA state table", dfa.states.len())?,
+            count("LALR state table", lalr_states.len())?,
+            count("group table", groups.len())?,
+        );
+        egt.resize();
+        egt.properties = self.properties.clone();
+        if egt.properties.character_set().is_none() {
+            egt.properties.set("Character Set", "Unicode");
+        }
+        if egt.properties.get("Character Mapping").is_none() {
+            egt.properties.set("Character Mapping", "Windows-1252");
+        }
+        egt.properties.set("Generated By", concat!("goldparser-rs ", env!("CARGO_PKG_VERSION")));
+        for symbol in symbols.iter() {
+            egt.symbols.add(symbol.clone());
+        }
+        for (index, chars) in dfa.charsets.into_iter().enumerate() {
+            egt.charset.add(index, chars);
+        }
+        egt.productions = productions;
+        for state in dfa.states {
+            let index = state.index;
+            egt.dfa_states[index] = state;
+        }
+        egt.lalr_states = lalr_states;
+        for (index, group) in groups.iter().enumerate() {
+            let nested = match group.nesting {
+                Nesting::None => vec![],
+                Nesting::Itself => vec![index],
+                Nesting::All => (0..groups.len()).collect(),
+            };
+            egt.groups.add(LexicalGroup::new(index, group.name.clone(), terminal_index[group.container],
+                terminal_index[group.start], terminal_index[group.end], group.advance, group.ending, nested));
+        }
+        egt.initial_states = InitialStatesRecord::new(0, 0);
+
+        let findings = egt.validate();
+        if !findings.is_empty() {
+            return Err(GrammarError::Invalid(findings));
+        }
+        Ok(egt)
+    }
+
+    /// Adds the container, start and end terminals of each group and applies the
+    /// `X Block @=` and `X Line @=` attributes
+    fn groups(&self, decls: &[GroupDecl], terminals: &mut Vec<Terminal>) -> Result<Vec<Group>, GrammarError> {
+        // a delimiter defined by a literal is named by its text, like '{' in Tiny.grm
+        fn delimiter(terminals: &mut Vec<Terminal>, (regex, line): &(RegExp, usize), name: String, kind: SymbolType) -> usize {
+            let name = regex.as_literal().unwrap_or(name);
+            match find(terminals, &name) {
+                Some(index) => {
+                    terminals[index].kind = kind;
+                    index
+                },
+                None => {
+                    terminals.push(Terminal { name, kind, regex: Some(regex.clone()), line: *line });
+                    terminals.len() - 1
+                },
+            }
+        }
+        let mut groups = Vec::new();
+        for decl in decls {
+            let container = match find(terminals, &decl.container) {
+                Some(index) => index,
+                None => {
+                    let kind = if decl.container.eq_ignore_ascii_case("Comment") { SymbolType::Noise } else { SymbolType::Terminal };
+                    terminals.push(Terminal { name: decl.container.clone(), kind, regex: None, line: 0 });
+                    terminals.len() - 1
+                },
+            };
+            match (&decl.start, &decl.end) {
+                (Some(start), Some(end)) => {
+                    let start = delimiter(terminals, start, format!("{} Start", decl.container), SymbolType::GroupStart);
+                    let end = delimiter(terminals, end, format!("{} End", decl.container), SymbolType::GroupEnd);
+                    groups.push(Group {
+                        name: format!("{} Block", decl.container), container, start, end,
+                        advance: AdvanceMode::Character, ending: EndingMode::Closed, nesting: Nesting::None,
+                    });
+                },
+                (Some((_, line)), None) | (None, Some((_, line))) => {
+                    return Err(GrammarError::IncompleteGroup { name: format!("{} Block", decl.container), line: *line });
+                },
+                (None, None) => {},
+            }
+            if let Some(line) = &decl.line {
+                let start = delimiter(terminals, line, format!("{} Line", decl.container), SymbolType::GroupStart);
+                let end = match find(terminals, "NewLine") {
+                    Some(index) => index,
+                    None => {
+                        let set = |name: &str| RegExp::Set(SetItem::Name(name.to_string()));
+                        let newline = RegExp::Alternation(vec![
+                            RegExp::Sequence(vec![set("CR"), set("LF")]), set("CR"), set("LF"),
+                        ]);
+                        terminals.push(Terminal { name: "NewLine".to_string(), kind: SymbolType::Noise, regex: Some(newline), line: 0 });
+                        terminals.len() - 1
+                    },
+                };
+                groups.push(Group {
+                    name: format!("{} Line", decl.container), container, start, end,
+                    advance: AdvanceMode::Character, ending: EndingMode::Open, nesting: Nesting::None,
+                });
+            }
+        }
+
+        for attr in &self.attributes {
+            let Some(group) = groups.iter_mut().find(|g| g.name.eq_ignore_ascii_case(&attr.name)) else { continue; };
+            for (key, value) in &attr.attributes {
+                let bad = || GrammarError::BadAttribute {
+                    name: attr.name.clone(), attribute: key.clone(), value: value.clone(), line: attr.line,
+                };
+                match (key.to_ascii_lowercase().as_str(), value.to_ascii_lowercase().as_str()) {
+                    ("nesting", "none") => group.nesting = Nesting::None,
+                    ("nesting", "self") => group.nesting = Nesting::Itself,
+                    ("nesting", "all") => group.nesting = Nesting::All,
+                    ("advance", "token") => group.advance = AdvanceMode::Token,
+                    ("advance", "character") => group.advance = AdvanceMode::Character,
+                    ("ending", "open") => group.ending = EndingMode::Open,
+                    ("ending", "closed") => group.ending = EndingMode::Closed,
+                    _ => return Err(bad()),
+                }
+            }
+        }
+        Ok(groups)
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        engine::{parser::{GPParser, GPMessage}, group::EndingMode, tables::Table, Builder, Parser, SymbolType},
+        grammar::{compile, compile_file, GrammarError},
+    };
+
+    fn parse(egt: crate::engine::EnhancedGrammarTable, source: &str) -> GPMessage {
+        let mut parser = Parser::from_grammar(egt);
+        parser.load_source_str(source);
+        parser.parse()
+    }
+
+    #[test]
+    fn simple() {
+        let egt = compile_file(crate::test::GP_SIMPLE_GRM).unwrap();
+        let gold = Builder::from_path(crate::test::GP_SIMPLE_EGT).unwrap().to_egt().unwrap();
+        let names = |egt: &crate::engine::EnhancedGrammarTable| {
+            let mut names = egt.symbols.iter().map(|s| (s.name.clone(), s.kind)).collect::<Vec<_>>();
+            names.sort_by_key(|(n, _)| n.to_lowercase());
+            names
+        };
+        assert_eq!(names(&egt), names(&gold));
+        assert_eq!(egt.productions.to_string(), gold.productions.to_string());
+        assert_eq!(egt.properties.name(), Some("Simple"));
+
+        let source = std::fs::read_to_string(crate::test::GP_SIMPLE_SRC).unwrap();
+        assert!(matches!(parse(egt, &source), GPMessage::Accept));
+    }
+
+    #[test]
+    fn tiny() {
+        let egt = compile_file(crate::test::GP_TINY_GRM).unwrap();
+        let gold = Builder::from_path(crate::test::GP_TINY_EGT).unwrap().to_egt().unwrap();
+        assert_eq!(egt.symbols.len(), gold.symbols.len());
+        assert_eq!(egt.productions.to_string(), gold.productions.to_string());
+        let group = &egt.groups[0];
+        assert_eq!((group.name.as_str(), group.ending_mode), ("Comment Block", EndingMode::Closed));
+        assert_eq!(egt.symbols[group.container_idx].name, "Comment");
+        assert_eq!(egt.symbols[group.start_idx].name, "{");
+        for (a, b) in egt.symbols.iter().zip(gold.symbols.iter()).take(6) {
+            assert_eq!((&a.name, a.kind), (&b.name, b.kind));
+        }
+
+        let source = std::fs::read_to_string(crate::test::GP_TINY_SRC).unwrap();
+        assert!(matches!(parse(egt, &source), GPMessage::Accept));
+    }
+
+    #[test]
+    fn precedence() {
+        let egt = compile(concat!(
+            "\"Start Symbol\" = <Exp>\n",
+            "Number = {Digit}+\n",
+            "%left '+'\n",
+            "%left '*'\n",
+            "<Exp> ::= <Exp> '+' <Exp> | <Exp> '*' <Exp> | Number\n",
+        )).unwrap();
+        let mut parser = Parser::from_grammar(egt);
+        parser.load_source_str("1*2+3+4");
+        let mut reduced = Vec::new();
+        loop {
+            match parser.parse_step() {
+                GPMessage::Reduction => reduced.push(parser.get_current_reduction().unwrap().rule.index),
+                GPMessage::TokenRead => {},
+                message => {
+                    assert!(matches!(message, GPMessage::Accept));
+                    break;
+                },
+            }
+        }
+        assert_eq!(reduced, vec![2, 2, 1, 2, 0, 2, 0]);
+
+        assert!(matches!(compile("\"Start Symbol\" = <A>\n%right '^'\n<A> ::= x\n"),
+            Err(GrammarError::UndefinedTerminal { name, line: 2 }) if name == "^"));
+    }
+
+    #[test]
+    fn ebnf() {
+        let egt = compile(concat!(
+            "\"Start Symbol\" = <Program>\n",
+            "Id = {Letter}+\n",
+            "Number = {Digit}+\n",
+            "<Program> ::= <Statement>*\n",
+            "<Statement> ::= Id ('=' Number)? ';'\n",
+        )).unwrap();
+        let generated = egt.symbols.iter().filter(|s| s.is_generated()).map(|s| s.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(generated, vec!["('=' Number)?", "<Statement>*"]);
+
+        let mut parser = Parser::from_grammar(egt);
+        parser.load_source_str("a; b = 1; c;");
+        let mut reductions = Vec::new();
+        loop {
+            match parser.parse_step() {
+                GPMessage::Reduction => reductions.push(parser.get_current_reduction().unwrap().clone()),
+                GPMessage::TokenRead => {},
+                message => {
+                    assert!(matches!(message, GPMessage::Accept));
+                    break;
+                },
+            }
+        }
+        let texts = |name: &str| reductions.iter()
+            .filter(|r| r.rule.head.name == name)
+            .map(|r| r.tokens.iter().map(|t| t.symbol.name.as_str()).collect::<Vec<_>>().join(" "))
+            .collect::<Vec<_>>();
+        assert_eq!(texts("Statement"), vec!["Id ;", "Id = Number ;", "Id ;"]);
+        assert_eq!(texts("Program"), vec!["Statement Statement Statement"]);
+
+        // rules named with parentheses are kept in the tree
+        let egt = compile("\"Start Symbol\" = <S>\n<S> ::= a <Rest (opt)>\n<Rest (opt)> ::= b c\n").unwrap();
+        assert!(!egt.symbols.iter().any(|s| s.is_generated()));
+        let mut parser = Parser::from_grammar(egt);
+        parser.load_source_str("a b c");
+        let mut heads = Vec::new();
+        loop {
+            match parser.parse_step() {
+                GPMessage::Reduction => {
+                    let reduction = parser.get_current_reduction().unwrap();
+                    let names = reduction.tokens.iter().map(|t| t.symbol.name.as_str()).collect::<Vec<_>>().join(" ");
+                    heads.push(format!("{} ::= {names}", reduction.rule.head.name));
+                },
+                GPMessage::TokenRead => {},
+                message => {
+                    assert!(matches!(message, GPMessage::Accept));
+                    break;
+                },
+            }
+        }
+        assert_eq!(heads, vec!["Rest (opt) ::= b c", "S ::= a Rest (opt)"]);
+    }
+
+    #[test]
+    fn comment_line() {
+        let egt = compile(concat!(
+            "\"Start Symbol\" = <List>\n",
+            "Comment Line = '//'\n",
+            "Comment Start = '/*'\n",
+            "Comment End = '*/'\n",
+            "Comment Block @= { Nesting = All }\n",
+            "<List> ::= <List> Item | Item\n",
+        )).unwrap();
+        assert_eq!(egt.groups.len(), 2);
+        assert_eq!(egt.symbols[egt.groups[1].end_idx].name, "NewLine");
+        assert_eq!(egt.symbols.get("NewLine".to_string()).unwrap().kind, SymbolType::Noise);
+        assert_eq!(egt.groups[0].nested, vec![0, 1]);
+        assert!(matches!(parse(egt, "item // one\r\nITEM /* two /* three */ */ item"), GPMessage::Accept));
+    }
+
+    #[test]
+    fn errors() {
+        assert!(matches!(compile("<A> ::= x\n"), Err(GrammarError::NoStartSymbol)));
+        assert!(matches!(compile("\"Start Symbol\" = <A>\n<A> ::= <B>\n"),
+            Err(GrammarError::UndefinedNonterminal { name, line: 2 }) if name == "B"));
+        assert!(matches!(compile("\"Start Symbol\" = <A>\n<A> ::= <B+>\n<B+> ::= b\n"),
+            Err(GrammarError::GroupName { name, line: 3 }) if name == "B+"));
+        assert!(matches!(compile("\"Start Symbol\" = <A>\nX = {Nope}\n<A> ::= X\n"),
+            Err(GrammarError::UndefinedSet { line: 2, .. })));
+        assert!(matches!(compile("\"Start Symbol\" = <A>\nComment Start = '/*'\n<A> ::= x\n"),
+            Err(GrammarError::IncompleteGroup { .. })));
+        let result = compile("\"Start Symbol\" = <E>\n<E> ::= <E> '+' <E> | n\n");
+        assert!(matches!(&result, Err(GrammarError::Conflicts(c)) if c.len() == 1));
+    }
+}
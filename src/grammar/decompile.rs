@@ -0,0 +1,764 @@
+//! EGT decompiler
+//!
+//! Reconstructs GOLD Meta-Language source from grammar tables whose `.grm` is lost:
+//! * the properties, with "Case Sensitive" inferred from the DFA and "Start Symbol" from the
+//!   LALR states
+//! * a definition for each terminal the DFA accepts, recovered by state elimination. Terminals
+//!   whose text is their name are left for the rules to define, as grammars usually do.
+//! * character sets too large to write inline, declared as predefined sets plus or minus
+//!   some characters
+//! * lexical groups and their attributes
+//! * the rules, grouped by head nonterminal
+//!
+//! The DFA accepts each string as a single terminal, so a terminal like `Id` loses the keywords
+//! it overlaps. States accepting a literal are taken to accept the terminals they lead to, which
+//! recovers `{Letter}{AlphaNumeric}*` rather than a list of everything but the keywords.
+//! Precedence declarations cannot be recovered, their effect is only in the LALR states.
+//!
+//! ```no_run
+//! use goldparser::{engine::Builder, grammar};
+//!
+//! let egt = Builder::from_path("examples/simple.egt").and_then(|mut b| b.to_egt()).unwrap();
+//! std::fs::write("simple.grm", grammar::decompile(&egt)).unwrap();
+//! ```
+
+use std::fmt::Write;
+
+use crate::engine::{
+    group::{AdvanceMode, EndingMode},
+    CharacterSet, DFAState, EnhancedGrammarTable, Symbol, SymbolType,
+};
+
+use super::{
+    analysis::start_symbol,
+    regex::RegExp,
+    sets::{self, SetExpr, SetItem, SetOp},
+};
+
+/// A regular expression over character sets, simplified as it is built
+#[derive(Debug, Clone, PartialEq)]
+enum Re {
+    /// The empty string
+    Empty,
+    Set(CharacterSet),
+    Seq(Vec<Re>),
+    Alt(Vec<Re>),
+    Star(Box<Re>),
+}
+
+impl Re {
+    fn seq(first: Re, second: Re) -> Re {
+        let mut items = [first, second].into_iter().flat_map(|re| match re {
+            Re::Empty => Vec::new(),
+            Re::Seq(items) => items,
+            re => vec![re],
+        }).collect::<Vec<_>>();
+        match items.len() {
+            0 => Re::Empty,
+            1 => items.pop().unwrap(),
+            _ => Re::Seq(items),
+        }
+    }
+
+    /// Alternatives that are single characters sets are merged into one set
+    fn alt(first: Re, second: Re) -> Re {
+        let mut items = Vec::<Re>::new();
+        for re in [first, second].into_iter().flat_map(|re| match re {
+            Re::Alt(items) => items,
+            re => vec![re],
+        }) {
+            let set = items.iter().position(|i| matches!(i, Re::Set(_)));
+            match (set, re) {
+                (Some(index), Re::Set(other)) => if let Re::Set(chars) = &mut items[index] {
+                    *chars = chars.union(&other);
+                },
+                (_, re) if !items.contains(&re) => items.push(re),
+                _ => {},
+            }
+        }
+        match items.len() {
+            1 => items.pop().unwrap(),
+            _ => Re::Alt(items),
+        }
+    }
+
+    fn star(self) -> Re {
+        match self {
+            Re::Empty | Re::Star(_) => self,
+            Re::Alt(items) if items.contains(&Re::Empty) => {
+                items.into_iter().filter(|i| *i != Re::Empty).reduce(Re::alt).map_or(Re::Empty, Re::star)
+            },
+            re => Re::Star(Box::new(re)),
+        }
+    }
+}
+
+/// Adds an alternative to a cell of the state elimination table
+fn add(cell: &mut Option<Re>, re: Re) {
+    *cell = Some(match cell.take() {
+        Some(old) => Re::alt(old, re),
+        None => re,
+    });
+}
+
+/// A DFA with its own accepting states, built from the DFA state table
+struct Automaton {
+    start: usize,
+    accept: Vec<bool>,
+    edges: Vec<Vec<(CharacterSet, usize)>>,
+}
+
+impl Automaton {
+    fn new(egt: &EnhancedGrammarTable, accepts: impl Fn(&DFAState) -> bool) -> Self {
+        Automaton {
+            start: egt.initial_states.dfa as usize,
+            accept: egt.dfa_states.iter().map(&accepts).collect(),
+            edges: egt.dfa_states.iter().map(|state| state.edges.iter().map(|edge| {
+                let mut chars = edge.chars.clone();
+                chars.normalize();
+                (chars, edge.target_state)
+            }).collect()).collect(),
+        }
+    }
+
+    /// The states that can reach an accepting state through at least one edge
+    fn leading(&self) -> Vec<bool> {
+        let mut leads = vec![false; self.accept.len()];
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for state in 0..self.accept.len() {
+                if !leads[state] && self.edges[state].iter().any(|(_, t)| self.accept[*t] || leads[*t]) {
+                    leads[state] = true;
+                    changed = true;
+                }
+            }
+        }
+        leads
+    }
+
+    /// Removes the states that are unreachable or cannot reach an accepting state, then merges
+    /// equivalent states by partition refinement. Edges between two states are merged.
+    fn minimize(&self) -> Automaton {
+        let count = self.accept.len();
+        let mut reachable = vec![false; count];
+        let mut queue = vec![self.start];
+        while let Some(state) = queue.pop() {
+            if !std::mem::replace(&mut reachable[state], true) {
+                queue.extend(self.edges[state].iter().map(|(_, t)| *t));
+            }
+        }
+        let leads = self.leading();
+        let live = (0..count).map(|s| reachable[s] && (self.accept[s] || leads[s])).collect::<Vec<_>>();
+        if !live[self.start] {
+            return Automaton { start: 0, accept: Vec::new(), edges: Vec::new() };
+        }
+
+        let mut class = (0..count).map(|s| usize::from(self.accept[s])).collect::<Vec<_>>();
+        let mut signatures = Vec::<(usize, Vec<(usize, CharacterSet)>)>::new();
+        loop {
+            let mut next = Vec::new();
+            let mut refined = vec![usize::MAX; count];
+            for state in (0..count).filter(|s| live[*s]) {
+                let mut moves = Vec::<(usize, CharacterSet)>::new();
+                for (chars, target) in self.edges[state].iter().filter(|(_, t)| live[*t]) {
+                    match moves.iter_mut().find(|(c, _)| *c == class[*target]) {
+                        Some((_, set)) => *set = set.union(chars),
+                        None => moves.push((class[*target], chars.clone())),
+                    }
+                }
+                moves.sort_by_key(|(c, _)| *c);
+                let signature = (class[state], moves);
+                refined[state] = match next.iter().position(|s| *s == signature) {
+                    Some(index) => index,
+                    None => {
+                        next.push(signature);
+                        next.len() - 1
+                    },
+                };
+            }
+            let stable = next.len() == signatures.len();
+            class = refined;
+            signatures = next;
+            if stable {
+                break;
+            }
+        }
+
+        // the signatures of the last round hold the moves between the final classes
+        let mut minimal = Automaton { start: class[self.start], accept: vec![false; signatures.len()], edges: Vec::new() };
+        for state in (0..count).filter(|s| live[*s]) {
+            minimal.accept[class[state]] = self.accept[state];
+        }
+        minimal.edges = vec![Vec::new(); signatures.len()];
+        for state in (0..count).filter(|s| live[*s]) {
+            if minimal.edges[class[state]].is_empty() {
+                minimal.edges[class[state]] = self.edges[state].iter().filter(|(_, t)| live[*t])
+                    .fold(Vec::<(CharacterSet, usize)>::new(), |mut edges, (chars, target)| {
+                        match edges.iter_mut().find(|(_, t)| *t == class[*target]) {
+                            Some((set, _)) => *set = set.union(chars),
+                            None => edges.push((chars.clone(), class[*target])),
+                        }
+                        edges
+                    });
+            }
+        }
+        minimal
+    }
+
+    /// Converts the automaton into an expression by state elimination
+    fn regex(&self) -> Option<Re> {
+        let count = self.accept.len();
+        if count == 0 {
+            return None;
+        }
+        // two extra states: a single start and a single accepting state
+        let (start, end) = (count, count + 1);
+        let mut table = vec![vec![None::<Re>; count + 2]; count + 2];
+        table[start][self.start] = Some(Re::Empty);
+        for (state, row) in table.iter_mut().enumerate().take(count) {
+            if self.accept[state] {
+                row[end] = Some(Re::Empty);
+            }
+            for (chars, target) in &self.edges[state] {
+                add(&mut row[*target], Re::Set(chars.clone()));
+            }
+        }
+
+        let mut remaining = (0..count).collect::<Vec<_>>();
+        while !remaining.is_empty() {
+            // eliminating the state with the fewest paths through it keeps expressions small
+            let paths = |k: usize| {
+                let into = (0..count + 2).filter(|i| *i != k && table[*i][k].is_some()).count();
+                let out = (0..count + 2).filter(|j| *j != k && table[k][*j].is_some()).count();
+                into * out
+            };
+            let position = (0..remaining.len()).min_by_key(|p| paths(remaining[*p])).unwrap();
+            let k = remaining.remove(position);
+            let repeat = table[k][k].take().map_or(Re::Empty, Re::star);
+            for i in (0..count + 2).filter(|i| *i != k) {
+                let Some(into) = table[i][k].take() else { continue; };
+                for j in (0..count + 2).filter(|j| *j != k) {
+                    if let Some(out) = table[k][j].clone() {
+                        let path = Re::seq(Re::seq(into.clone(), repeat.clone()), out);
+                        add(&mut table[i][j], path);
+                    }
+                }
+            }
+            table[k].fill(None);
+        }
+        table[start][end].take()
+    }
+}
+
+/// A terminal definition recovered from the DFA, its character sets not yet named
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Pattern {
+    /// Text, in lowercase when the grammar is case insensitive
+    Literal(String),
+    Chars(CharacterSet),
+    Sequence(Vec<Pattern>),
+    Alternation(Vec<Pattern>),
+    Star(Box<Pattern>),
+    Plus(Box<Pattern>),
+    Optional(Box<Pattern>),
+}
+
+/// The terminal definitions recovered from the DFA of a grammar table
+pub(crate) struct Lexicon {
+    /// Case insensitive grammars only have edges on both cases of a letter
+    pub case_sensitive: bool,
+    /// The terminals the DFA accepts, by symbol index, in symbol table order
+    pub terminals: Vec<(usize, Pattern)>,
+    /// The terminals accepting the text of literal terminals too, like `Id` and `display`
+    pub words: Vec<usize>,
+}
+
+impl Lexicon {
+    pub(crate) fn new(egt: &EnhancedGrammarTable) -> Self {
+        let case_sensitive = egt.dfa_states.iter().flat_map(|s| s.edges.iter()).any(|edge| {
+            let mut chars = edge.chars.clone();
+            chars.normalize();
+            sets::fold_case(&chars) != chars
+        });
+        let mut lexicon = Lexicon { case_sensitive, terminals: Vec::new(), words: Vec::new() };
+
+        let accepted = |symbol: &Symbol| egt.dfa_states.iter().any(|s| s.accept && s.accept_symbol.index == symbol.index);
+        let exact = |symbol: &Symbol| Automaton::new(egt, |s| s.accept && s.accept_symbol.index == symbol.index);
+        let mut literals = vec![false; egt.symbols.iter().count()];
+        for symbol in egt.symbols.iter().filter(|s| accepted(s)) {
+            literals[symbol.index] = exact(symbol).minimize().regex().is_some_and(|re| lexicon.literal(&re).is_some());
+        }
+        for symbol in egt.symbols.iter().filter(|s| accepted(s)) {
+            let exact = exact(symbol);
+            let mut re = exact.minimize().regex();
+            if !literals[symbol.index] {
+                // a keyword state leading on to the terminal is in its language, as `display` is in `Id`
+                let leads = exact.leading();
+                let keyword = |s: &DFAState| s.accept && literals[s.accept_symbol.index] && leads[s.index];
+                if egt.dfa_states.iter().any(keyword) {
+                    lexicon.words.push(symbol.index);
+                    re = Automaton::new(egt, |s| s.accept && (s.accept_symbol.index == symbol.index || keyword(s)))
+                        .minimize().regex();
+                }
+            }
+            if let Some(re) = re {
+                let pattern = lexicon.convert(&re);
+                lexicon.terminals.push((symbol.index, pattern));
+            }
+        }
+        lexicon
+    }
+
+    /// The definition of the terminal at `index` in the symbol table
+    pub(crate) fn pattern(&self, index: usize) -> Option<&Pattern> {
+        self.terminals.iter().find(|(i, _)| *i == index).map(|(_, p)| p)
+    }
+
+    /// The text up to and including the first `end`, the body of a block group
+    pub(crate) fn until(&self, end: &str) -> Option<Pattern> {
+        let end = end.chars().collect::<Vec<_>>();
+        let all = CharacterSet::new(vec!['\u{1}'..='\u{D7FF}', '\u{E000}'..=char::MAX]);
+        let mut others = all.clone();
+        for ch in &end {
+            others.remove(*ch);
+        }
+        // the automaton matching end, falling back to the longest prefix of end just read
+        let fallback = |read: &[char]| (0..=read.len().min(end.len())).rev()
+            .find(|k| read.ends_with(&end[..*k])).unwrap_or(0);
+        let mut edges = Vec::new();
+        for state in 0..end.len() {
+            let mut moves = Vec::<(CharacterSet, usize)>::new();
+            for ch in &end {
+                let target = fallback(&[&end[..state], &[*ch]].concat());
+                match moves.iter_mut().find(|(_, t)| *t == target) {
+                    Some((chars, _)) => *chars = chars.union(&CharacterSet::new(vec![*ch..=*ch])),
+                    None => moves.push((CharacterSet::new(vec![*ch..=*ch]), target)),
+                }
+            }
+            match moves.iter_mut().find(|(_, t)| *t == 0) {
+                Some((chars, _)) => *chars = chars.union(&others),
+                None => moves.push((others.clone(), 0)),
+            }
+            edges.push(moves);
+        }
+        edges.push(Vec::new());
+        let accept = (0..=end.len()).map(|s| s == end.len()).collect();
+        let re = Automaton { start: 0, accept, edges }.minimize().regex()?;
+        Some(self.convert(&re))
+    }
+
+    /// The character matched by a set, or by both its cases in a case insensitive grammar
+    fn character(&self, chars: &CharacterSet) -> Option<char> {
+        let first = *chars.ranges().first()?.start();
+        let ch = if self.case_sensitive { first } else { first.to_lowercase().next()? };
+        let single = CharacterSet::new(vec![ch..=ch]);
+        let expected = if self.case_sensitive { single } else { sets::fold_case(&single) };
+        (!ch.is_control() && expected == *chars).then_some(ch)
+    }
+
+    /// The text matched by an expression of single characters
+    fn literal(&self, re: &Re) -> Option<String> {
+        match re {
+            Re::Set(chars) => self.character(chars).map(String::from),
+            Re::Seq(items) => items.iter().map(|i| self.literal(i)).collect(),
+            _ => None,
+        }
+    }
+
+    fn convert(&self, re: &Re) -> Pattern {
+        match re {
+            Re::Empty => Pattern::Sequence(Vec::new()),
+            Re::Set(chars) => match self.character(chars) {
+                Some(ch) => Pattern::Literal(ch.to_string()),
+                None => Pattern::Chars(chars.clone()),
+            },
+            Re::Star(item) => Pattern::Star(Box::new(self.convert(item))),
+            Re::Alt(items) => {
+                let mut alternatives = items.iter().filter(|i| **i != Re::Empty).map(|i| self.convert(i)).collect::<Vec<_>>();
+                let pattern = match alternatives.len() {
+                    1 => alternatives.pop().unwrap(),
+                    _ => Pattern::Alternation(alternatives),
+                };
+                match items.contains(&Re::Empty) {
+                    true => Pattern::Optional(Box::new(pattern)),
+                    false => pattern,
+                }
+            },
+            Re::Seq(items) => {
+                // X X* is written X+
+                let (mut written, mut converted) = (Vec::<&Re>::new(), Vec::<Pattern>::new());
+                for item in items {
+                    if let Re::Star(repeated) = item {
+                        let pattern = match &**repeated {
+                            Re::Seq(items) => items.iter().collect(),
+                            re => vec![re],
+                        };
+                        if written.ends_with(&pattern) {
+                            written.truncate(written.len() - pattern.len());
+                            converted.truncate(converted.len() - pattern.len());
+                            written.push(item);
+                            converted.push(Pattern::Plus(Box::new(self.convert(repeated))));
+                            continue;
+                        }
+                    }
+                    written.push(item);
+                    converted.push(self.convert(item));
+                }
+                let mut merged = Vec::<Pattern>::new();
+                for pattern in converted {
+                    match (merged.last_mut(), pattern) {
+                        (Some(Pattern::Literal(text)), Pattern::Literal(more)) => text.push_str(&more),
+                        (_, pattern) => merged.push(pattern),
+                    }
+                }
+                match merged.len() {
+                    1 => merged.pop().unwrap(),
+                    _ => Pattern::Sequence(merged),
+                }
+            },
+        }
+    }
+}
+
+/// Builds the source text of a grammar from its tables
+struct Decompiler<'a> {
+    egt: &'a EnhancedGrammarTable,
+    /// Character sets declared so far, with their names and expressions
+    sets: Vec<(String, CharacterSet, SetExpr)>,
+}
+
+impl<'a> Decompiler<'a> {
+    fn new(egt: &'a EnhancedGrammarTable) -> Self {
+        Decompiler { egt, sets: Vec::new() }
+    }
+
+    /// Items adding up to the set: characters that print in a `[...]` literal, others by name
+    /// or code
+    fn items(chars: &CharacterSet) -> Vec<SetItem> {
+        let mut literal = String::new();
+        let mut items = Vec::new();
+        let code = |ch: char| format!("&{:02X}", ch as u32);
+        for range in chars.ranges() {
+            let (start, end) = (*range.start(), *range.end());
+            let printable = |ch: char| ch == ' ' || ch.is_ascii_graphic();
+            if (end as u32) - (start as u32) < 6 && range.clone().all(printable) {
+                literal.extend(range.clone());
+                continue;
+            }
+            let single = CharacterSet::new(vec![range.clone()]);
+            items.push(match sets::predefined_sets().find(|(_, set)| *set == single) {
+                Some((name, _)) => SetItem::Name(name.to_string()),
+                None if start == end => SetItem::Name(code(start)),
+                None => SetItem::Name(format!("{} .. {}", code(start), code(end))),
+            });
+        }
+        if !literal.is_empty() {
+            items.push(SetItem::Literal(literal));
+        }
+        items
+    }
+
+    /// Writes the set as predefined sets with characters added and removed, adding predefined
+    /// sets while that shortens the expression. Character codes count double.
+    fn expression(chars: &CharacterSet) -> SetExpr {
+        let weight = |items: Vec<SetItem>| items.iter().map(|item| match item {
+            SetItem::Name(name) if name.starts_with('&') => 2,
+            _ => 1,
+        }).sum::<usize>();
+        let cost = |covered: &CharacterSet, bases: usize| {
+            bases + weight(Self::items(&chars.difference(covered))) + weight(Self::items(&covered.difference(chars)))
+        };
+        let (mut bases, mut covered) = (Vec::<&str>::new(), CharacterSet::default());
+        loop {
+            let best = sets::predefined_sets().filter(|(name, _)| !bases.contains(name))
+                .map(|(name, set)| {
+                    let union = covered.union(&set);
+                    (cost(&union, bases.len() + 1), name, union)
+                })
+                .min_by_key(|(cost, ..)| *cost);
+            match best {
+                Some((best, name, union)) if best < cost(&covered, bases.len()) => {
+                    bases.push(name);
+                    covered = union;
+                },
+                _ => break,
+            }
+        }
+        bases.into_iter().map(|name| (SetOp::Add, SetItem::Name(name.to_string())))
+            .chain(Self::items(&chars.difference(&covered)).into_iter().map(|i| (SetOp::Add, i)))
+            .chain(Self::items(&covered.difference(chars)).into_iter().map(|i| (SetOp::Sub, i)))
+            .collect()
+    }
+
+    /// Names a set used by `terminal`: a predefined set, a literal, or a declared set
+    fn set_item(&mut self, chars: &CharacterSet, terminal: &str) -> SetItem {
+        if let Some((name, _)) = sets::predefined_sets().find(|(_, set)| set == chars) {
+            return SetItem::Name(name.to_string());
+        }
+        if let Some((name, ..)) = self.sets.iter().find(|(_, set, _)| set == chars) {
+            return SetItem::Name(name.clone());
+        }
+        let mut items = Self::items(chars);
+        if items.len() == 1 {
+            return items.pop().unwrap();
+        }
+        let base = match terminal.chars().all(|c| c.is_alphanumeric() || c == ' ' || c == '_') {
+            true => format!("{terminal} Chars"),
+            false => "Chars".to_string(),
+        };
+        let name = (1..).map(|n| if n == 1 { base.clone() } else { format!("{base} {n}") })
+            .find(|name| !self.sets.iter().any(|(n, ..)| n.eq_ignore_ascii_case(name)))
+            .unwrap();
+        self.sets.push((name.clone(), chars.clone(), Self::expression(chars)));
+        SetItem::Name(name)
+    }
+
+    /// Names the character sets of a pattern used by `terminal`
+    fn regex(&mut self, pattern: &Pattern, terminal: &str) -> RegExp {
+        let mut regex = |p: &Pattern| Box::new(self.regex(p, terminal));
+        match pattern {
+            Pattern::Literal(text) => RegExp::Literal(text.clone()),
+            Pattern::Chars(chars) => RegExp::Set(self.set_item(chars, terminal)),
+            Pattern::Sequence(items) => RegExp::Sequence(items.iter().map(|p| *regex(p)).collect()),
+            Pattern::Alternation(items) => RegExp::Alternation(items.iter().map(|p| *regex(p)).collect()),
+            Pattern::Star(item) => RegExp::Star(regex(item)),
+            Pattern::Plus(item) => RegExp::Plus(regex(item)),
+            Pattern::Optional(item) => RegExp::Optional(regex(item)),
+        }
+    }
+
+    fn decompile(mut self) -> String {
+        let egt = self.egt;
+        let lexicon = Lexicon::new(egt);
+
+        let in_groups = |index: usize| egt.groups.iter().any(|g| [g.container_idx, g.start_idx, g.end_idx].contains(&index));
+        let used = |index: usize| egt.productions.iter().any(|p| p.symbols.iter().any(|s| s.index == index));
+        let mut regexes = Vec::<(usize, RegExp)>::new();
+        for (index, pattern) in &lexicon.terminals {
+            let regex = self.regex(pattern, &egt.symbols[*index].name);
+            regexes.push((*index, regex));
+        }
+        let regex = |index: usize| regexes.iter().find(|(i, _)| *i == index).map(|(_, r)| r);
+
+        let mut source = String::new();
+        let mut section = |lines: Vec<(String, String)>| {
+            let width = lines.iter().map(|(name, _)| name.chars().count()).max().unwrap_or(0);
+            for (name, value) in &lines {
+                writeln!(source, "{name:width$} {value}").unwrap();
+            }
+            if !lines.is_empty() {
+                source.push('\n');
+            }
+        };
+
+        let mut properties = egt.properties.iter()
+            .filter(|p| !matches!(p.name.as_str(), "Generated By" | "Generated Date" | "Case Sensitive" | "Start Symbol"))
+            .map(|p| (format!("\"{}\"", p.name), format!("= {}", quote(&p.value))))
+            .collect::<Vec<_>>();
+        properties.push(("\"Case Sensitive\"".to_string(), format!("= {}", if lexicon.case_sensitive { "True" } else { "False" })));
+        if let Some(start) = start_symbol(egt) {
+            properties.push(("\"Start Symbol\"".to_string(), format!("= <{}>", start.name)));
+        }
+        section(properties);
+
+        let mut terminals = Vec::new();
+        let mut attributes = Vec::new();
+        for (index, regex) in &regexes {
+            let symbol = &egt.symbols[*index];
+            if in_groups(*index) {
+                continue;
+            }
+            let named = match regex {
+                RegExp::Literal(text) if lexicon.case_sensitive => *text == symbol.name,
+                RegExp::Literal(text) => text.eq_ignore_ascii_case(&symbol.name),
+                _ => false,
+            };
+            if !(named && used(*index) && symbol.kind == SymbolType::Terminal) {
+                terminals.push((name(&symbol.name), format!("= {regex}")));
+            }
+            if symbol.kind == SymbolType::Noise && !symbol.name.eq_ignore_ascii_case("Whitespace") {
+                attributes.push((name(&symbol.name), "@= { Type = Noise }".to_string()));
+            }
+        }
+
+        for group in egt.groups.iter() {
+            let container = &egt.symbols[group.container_idx];
+            let line = group.name.eq_ignore_ascii_case(&format!("{} Line", container.name));
+            let delimiter = |index: usize| regex(index).map_or_else(|| name(&egt.symbols[index].name), |r| r.to_string());
+            if line {
+                terminals.push((format!("{} Line", container.name), format!("= {}", delimiter(group.start_idx))));
+            } else {
+                terminals.push((format!("{} Start", container.name), format!("= {}", delimiter(group.start_idx))));
+                terminals.push((format!("{} End", container.name), format!("= {}", delimiter(group.end_idx))));
+            }
+            let mut values = Vec::new();
+            let nesting = match group.nested.as_slice() {
+                [] => "None",
+                [index] if *index == group.index => "Self",
+                _ => "All",
+            };
+            if nesting != "None" {
+                values.push(format!("Nesting = {nesting}"));
+            }
+            if group.advance_mode == AdvanceMode::Token {
+                values.push("Advance = Token".to_string());
+            }
+            match (line, group.ending_mode) {
+                (true, EndingMode::Closed) => values.push("Ending = Closed".to_string()),
+                (false, EndingMode::Open) => values.push("Ending = Open".to_string()),
+                _ => {},
+            }
+            if !values.is_empty() {
+                attributes.push((group.name.clone(), format!("@= {{ {} }}", values.join(", "))));
+            }
+            let comment = container.name.eq_ignore_ascii_case("Comment");
+            match container.kind {
+                SymbolType::Noise if !comment => attributes.push((name(&container.name), "@= { Type = Noise }".to_string())),
+                SymbolType::Terminal if comment => attributes.push((name(&container.name), "@= { Type = Content }".to_string())),
+                _ => {},
+            }
+        }
+
+        let sets = self.sets.iter().map(|(name, _, expr)| {
+            let items = expr.iter().enumerate().map(|(i, (op, item))| match (i, op) {
+                (0, _) => item.to_string(),
+                (_, SetOp::Add) => format!("+ {item}"),
+                (_, SetOp::Sub) => format!("- {item}"),
+            });
+            (format!("{{{name}}}"), format!("= {}", items.collect::<Vec<_>>().join(" ")))
+        }).collect();
+        section(sets);
+        section(terminals);
+        section(attributes);
+
+        let mut heads = Vec::<&Symbol>::new();
+        for rule in egt.productions.iter() {
+            if !heads.iter().any(|h| h.index == rule.head.index) {
+                heads.push(&rule.head);
+            }
+        }
+        for head in heads {
+            let handles = egt.productions.iter().filter(|r| r.head.index == head.index).map(|rule| {
+                rule.symbols.iter().map(|s| match s.kind {
+                    SymbolType::NonTerminal => format!("<{}>", s.name),
+                    _ => name(&s.name),
+                }).collect::<Vec<_>>().join(" ")
+            });
+            let indent = " ".repeat(head.name.chars().count() + 5);
+            let rule = handles.collect::<Vec<_>>().join(&format!("\n{indent}| "));
+            writeln!(source, "<{}> ::= {}", head.name, rule).unwrap();
+            source.push('\n');
+        }
+        source.truncate(source.trim_end().len());
+        source.push('\n');
+        source
+    }
+}
+
+/// Quotes text, writing each `'` as `''` between quoted pieces
+fn quote(text: &str) -> String {
+    text.split('\'').map(|piece| match piece {
+        "" => String::new(),
+        piece => format!("'{piece}'"),
+    }).collect::<Vec<_>>().join("''")
+}
+
+/// A terminal name as written in a definition or rule: bare when it reads as one word
+fn name(name: &str) -> String {
+    match !name.is_empty() && name != "-" && name.chars().all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.')) {
+        true => name.to_string(),
+        false => quote(name),
+    }
+}
+
+/// Reconstructs the GOLD Meta-Language source of a grammar table
+pub fn decompile(egt: &EnhancedGrammarTable) -> String {
+    Decompiler::new(egt).decompile()
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        engine::{parser::{GPParser, GPMessage}, tables::Table, Builder, CharacterSet, Parser},
+        grammar::compile,
+    };
+
+    use super::{decompile, Decompiler};
+
+    #[test]
+    fn simple() {
+        let gold = Builder::from_path(crate::test::GP_SIMPLE_EGT).unwrap().to_egt().unwrap();
+        let source = decompile(&gold);
+        for line in [
+            "\"Case Sensitive\"    = False",
+            "\"Start Symbol\"      = <Statements>",
+            "{StringLiteral Chars} = {Printable} - ['']",
+            "Id            = {Letter}{AlphaNumeric}*",
+            "NumberLiteral = {Digit}+('.'{Digit}+)?",
+            "StringLiteral = ''{StringLiteral Chars}*''",
+            "<Statements> ::= <Statement> <Statements>\n               | <Statement>\n",
+        ] {
+            assert!(source.contains(line), "{line} missing from\n{source}");
+        }
+        assert!(!source.contains("display ="));
+
+        let egt = compile(&source).unwrap();
+        assert_eq!(egt.productions.to_string(), gold.productions.to_string());
+        assert_eq!(egt.symbols.len(), gold.symbols.len());
+        let mut parser = Parser::from_grammar(egt);
+        parser.load_source_str(&std::fs::read_to_string(crate::test::GP_SIMPLE_SRC).unwrap());
+        assert!(matches!(parser.parse(), GPMessage::Accept));
+    }
+
+    #[test]
+    fn tiny() {
+        let gold = Builder::from_path(crate::test::GP_TINY_EGT).unwrap().to_egt().unwrap();
+        let source = decompile(&gold);
+        assert!(source.contains("Comment Start = '{'\nComment End   = '}'\n"), "{source}");
+        assert!(source.contains("ID            = {Letter}+"), "{source}");
+
+        let egt = compile(&source).unwrap();
+        assert_eq!(egt.productions.to_string(), gold.productions.to_string());
+        let mut parser = Parser::from_grammar(egt);
+        parser.load_source_str(&std::fs::read_to_string(crate::test::GP_TINY_SRC).unwrap());
+        assert!(matches!(parser.parse(), GPMessage::Accept));
+    }
+
+    #[test]
+    fn groups() {
+        let egt = compile(concat!(
+            "\"Start Symbol\" = <List>\n",
+            "\"Case Sensitive\" = True\n",
+            "Hex = '0x'{Digit}+ | '0x'[abcdef]+\n",
+            "Comment Line = '//'\n",
+            "Comment Start = '/*'\n",
+            "Comment End = '*/'\n",
+            "Comment Block @= { Nesting = All }\n",
+            "<List> ::= <List> Hex | nil\n",
+        )).unwrap();
+        let source = decompile(&egt);
+        for line in [
+            "\"Case Sensitive\"    = True",
+            "Hex           = 0x({Digit}+ | [abcdef]+)",
+            "Comment Line  = '//'",
+            "Comment Start = '/*'",
+            "Comment Block @= { Nesting = All }",
+        ] {
+            assert!(source.contains(line), "{line} missing from\n{source}");
+        }
+        assert_eq!(decompile(&compile(&source).unwrap()), source);
+    }
+
+    #[test]
+    fn expressions() {
+        let set = |ranges: Vec<std::ops::RangeInclusive<char>>| CharacterSet::new(ranges);
+        let text = |chars: CharacterSet| Decompiler::expression(&chars).iter()
+            .map(|(op, item)| format!("{op:?} {item}")).collect::<Vec<_>>().join(" ");
+        assert_eq!(text(set(vec!['\t'..='\t', '\u{0B}'..='\u{0C}', ' '..=' ', '\u{85}'..='\u{85}'])),
+            "Add {HT} Add {&0B .. &0C} Add {&85} Add [ ]");
+        assert_eq!(text(set(vec!['0'..='9', 'A'..='F', 'a'..='f'])), "Add {Digit} Add [ABCDEFabcdef]");
+        assert_eq!(text(set(vec!['\u{20}'..='\u{7A}', '\u{7C}'..='\u{7E}', '\u{A0}'..='\u{A0}'])), "Add {Printable} Sub [{]");
+    }
+}
@@ -0,0 +1,475 @@
+//! Lexer DFA construction
+//!
+//! Each terminal's `RegExp` becomes a fragment of one Thompson NFA. Subset construction then
+//! turns the NFA into the DFA the engine tokenizes with. The NFA's character sets usually
+//! overlap (`{Letter}` and `'i'`), so they are first split into disjoint atoms and the DFA
+//! moves on atoms. A DFA edge is the union of the atoms leading to the same target state.
+//!
+//! The DFA is minimized by merging states which accept the same terminal and move to the
+//! same states on every character.
+//!
+//! When a DFA state accepts several terminals, terminals defined only by literal text win
+//! over those using sets or repetition. This is how `if` is read as a keyword and not `Id`.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ops::RangeInclusive;
+
+use crate::engine::{tables::{CharacterSetTable, DFAStateTable}, CharacterSet, DFAEdge, DFAState, Symbol};
+
+use super::{
+    regex::RegExp,
+    sets::{self, CharacterSets},
+    GrammarError,
+};
+
+/// A terminal to be recognized by the DFA
+#[derive(Debug, Clone)]
+pub(crate) struct LexerTerminal {
+    pub symbol: Symbol,
+    pub regex: RegExp,
+    /// Line of the definition, for error messages
+    pub line: usize,
+}
+
+/// The DFA states and the character sets their edges refer to
+pub(crate) struct Dfa {
+    pub states: Vec<DFAState>,
+    pub charsets: Vec<CharacterSet>,
+}
+
+#[derive(Default)]
+struct NfaState {
+    epsilon: Vec<usize>,
+    /// (character class, target)
+    edges: Vec<(usize, usize)>,
+    /// Index into the terminal list
+    accept: Option<usize>,
+}
+
+struct Nfa<'a> {
+    states: Vec<NfaState>,
+    classes: Vec<CharacterSet>,
+    sets: &'a CharacterSets,
+    case_sensitive: bool,
+}
+
+impl<'a> Nfa<'a> {
+    fn state(&mut self) -> usize {
+        self.states.push(NfaState::default());
+        self.states.len() - 1
+    }
+
+    fn class(&mut self, set: CharacterSet) -> usize {
+        let set = if self.case_sensitive { set } else { sets::fold_case(&set) };
+        match self.classes.iter().position(|c| *c == set) {
+            Some(index) => index,
+            None => {
+                self.classes.push(set);
+                self.classes.len() - 1
+            },
+        }
+    }
+
+    /// Adds the states matching `regex` and returns the fragment's (start, end) states.
+    /// An undefined set name is returned as the error.
+    fn fragment(&mut self, regex: &RegExp) -> Result<(usize, usize), String> {
+        let start = self.state();
+        let end = match regex {
+            RegExp::Literal(text) => {
+                let mut from = start;
+                for ch in text.chars() {
+                    let class = self.class(CharacterSet::new(vec![ch..=ch]));
+                    let to = self.state();
+                    self.states[from].edges.push((class, to));
+                    from = to;
+                }
+                from
+            },
+            RegExp::Set(item) => {
+                let class = self.sets.resolve(item)?;
+                let class = self.class(class);
+                let to = self.state();
+                self.states[start].edges.push((class, to));
+                to
+            },
+            RegExp::Sequence(items) => {
+                let mut from = start;
+                for item in items {
+                    let (first, last) = self.fragment(item)?;
+                    self.states[from].epsilon.push(first);
+                    from = last;
+                }
+                from
+            },
+            RegExp::Alternation(alternatives) => {
+                let end = self.state();
+                for alternative in alternatives {
+                    let (first, last) = self.fragment(alternative)?;
+                    self.states[start].epsilon.push(first);
+                    self.states[last].epsilon.push(end);
+                }
+                end
+            },
+            RegExp::Star(item) | RegExp::Plus(item) | RegExp::Optional(item) => {
+                let (first, last) = self.fragment(item)?;
+                let end = self.state();
+                self.states[start].epsilon.push(first);
+                self.states[last].epsilon.push(end);
+                if !matches!(regex, RegExp::Plus(_)) {
+                    self.states[start].epsilon.push(end);
+                }
+                if !matches!(regex, RegExp::Optional(_)) {
+                    self.states[last].epsilon.push(first);
+                }
+                end
+            },
+        };
+        Ok((start, end))
+    }
+
+    /// The states reachable from `states` without reading a character
+    fn closure(&self, states: impl IntoIterator<Item = usize>) -> Vec<usize> {
+        let mut seen = BTreeSet::new();
+        let mut work = states.into_iter().collect::<Vec<_>>();
+        while let Some(state) = work.pop() {
+            if seen.insert(state) {
+                work.extend(self.states[state].epsilon.iter().copied());
+            }
+        }
+        seen.into_iter().collect()
+    }
+
+    /// Splits the classes into disjoint atoms. Returns the atoms and, for each class, the
+    /// atoms it is made of.
+    fn atoms(&self) -> (Vec<CharacterSet>, Vec<Vec<usize>>) {
+        let mut bounds = self.classes.iter()
+            .flat_map(|c| c.ranges().iter().flat_map(|r| [*r.start() as u32, *r.end() as u32 + 1]))
+            .collect::<Vec<_>>();
+        bounds.sort_unstable();
+        bounds.dedup();
+
+        let mut atoms = Vec::<CharacterSet>::new();
+        let mut signatures = HashMap::<Vec<usize>, usize>::new();
+        let mut class_atoms = vec![Vec::new(); self.classes.len()];
+        for pair in bounds.windows(2) {
+            let Some(range) = char_range(pair[0], pair[1] - 1) else { continue; };
+            let signature = (0..self.classes.len())
+                .filter(|c| self.classes[*c].contains(*range.start()))
+                .collect::<Vec<_>>();
+            if signature.is_empty() {
+                continue;
+            }
+            match signatures.get(&signature) {
+                Some(atom) => atoms[*atom].add(range),
+                None => {
+                    let atom = atoms.len();
+                    atoms.push(CharacterSet::new(vec![range]));
+                    for class in &signature {
+                        class_atoms[*class].push(atom);
+                    }
+                    signatures.insert(signature, atom);
+                },
+            }
+        }
+        for atom in atoms.iter_mut() {
+            atom.normalize();
+        }
+        (atoms, class_atoms)
+    }
+}
+
+/// The characters from `start` to `end`, leaving out the surrogate block
+fn char_range(start: u32, end: u32) -> Option<RangeInclusive<char>> {
+    let start = if (0xD800..=0xDFFF).contains(&start) { 0xE000 } else { start };
+    let end = if (0xD800..=0xDFFF).contains(&end) { 0xD7FF } else { end };
+    (start <= end).then(|| char::from_u32(start).zip(char::from_u32(end))).flatten().map(|(s, e)| s..=e)
+}
+
+/// Merges equivalent states by partition refinement (Moore's algorithm). States start out
+/// split by the terminal they accept and are split further while two states of a block move
+/// to different blocks on some atom. Returns the block of each state; the start state is in
+/// block 0.
+fn minimize(moves: &[BTreeMap<usize, usize>], accepts: &[Option<usize>]) -> (Vec<usize>, usize) {
+    let mut blocks = vec![0; moves.len()];
+    let mut count = 0;
+    loop {
+        let mut numbers = HashMap::<(Option<usize>, usize, Vec<(usize, usize)>), usize>::new();
+        let refined = (0..moves.len()).map(|state| {
+            let signature = (
+                accepts[state],
+                blocks[state],
+                moves[state].iter().map(|(atom, target)| (*atom, blocks[*target])).collect(),
+            );
+            let next = numbers.len();
+            *numbers.entry(signature).or_insert(next)
+        }).collect::<Vec<_>>();
+        if numbers.len() == count {
+            return (blocks, count);
+        }
+        count = numbers.len();
+        blocks = refined;
+    }
+}
+
+/// Builds the minimal DFA recognizing `terminals`. Sets are looked up in `sets` and, unless
+/// `case_sensitive`, extended with the other case of each letter.
+pub(crate) fn build(terminals: &[LexerTerminal], sets: &CharacterSets, case_sensitive: bool) -> Result<Dfa, GrammarError> {
+    let mut nfa = Nfa { states: Vec::new(), classes: Vec::new(), sets, case_sensitive };
+    let start = nfa.state();
+    for (index, terminal) in terminals.iter().enumerate() {
+        let (first, last) = nfa.fragment(&terminal.regex)
+            .map_err(|name| GrammarError::UndefinedSet { name, line: terminal.line })?;
+        nfa.states[start].epsilon.push(first);
+        nfa.states[last].accept = Some(index);
+    }
+    let literal = terminals.iter().map(|t| t.regex.as_literal().is_some()).collect::<Vec<_>>();
+    let (atoms, class_atoms) = nfa.atoms();
+
+    // subset construction, moving on atoms
+    let mut subsets = vec![nfa.closure([start])];
+    let mut indices = HashMap::from([(subsets[0].clone(), 0)]);
+    let mut moves = Vec::<BTreeMap<usize, usize>>::new();
+    let mut accepts = Vec::<Option<usize>>::new();
+    let mut index = 0;
+    while index < subsets.len() {
+        let mut targets = BTreeMap::<usize, BTreeSet<usize>>::new();
+        for state in &subsets[index] {
+            for (class, target) in &nfa.states[*state].edges {
+                for atom in &class_atoms[*class] {
+                    targets.entry(*atom).or_default().insert(*target);
+                }
+            }
+        }
+        let mut next = BTreeMap::new();
+        for (atom, states) in targets {
+            let subset = nfa.closure(states);
+            let target = *indices.entry(subset.clone()).or_insert_with(|| {
+                subsets.push(subset);
+                subsets.len() - 1
+            });
+            next.insert(atom, target);
+        }
+        moves.push(next);
+
+        // the accepted terminal, literals first
+        let mut accepted = subsets[index].iter().filter_map(|s| nfa.states[*s].accept).collect::<Vec<_>>();
+        accepted.sort_by_key(|t| (!literal[*t], *t));
+        accepted.dedup();
+        if let [first, second, ..] = accepted[..] {
+            if literal[first] == literal[second] {
+                return Err(GrammarError::LexicalConflict(
+                    terminals[first].symbol.name.clone(), terminals[second].symbol.name.clone()));
+            }
+        }
+        if let (0, Some(terminal)) = (index, accepted.first()) {
+            return Err(GrammarError::EmptyTerminal(terminals[*terminal].symbol.name.clone()));
+        }
+        accepts.push(accepted.first().copied());
+        index += 1;
+    }
+
+    // one state per block, with an edge per target made of the atoms leading there
+    let (blocks, count) = minimize(&moves, &accepts);
+    let mut dfa = Dfa { states: Vec::with_capacity(count), charsets: Vec::new() };
+    for block in 0..count {
+        let state = blocks.iter().position(|b| *b == block).unwrap();
+        let mut targets = BTreeMap::<usize, CharacterSet>::new();
+        for (atom, target) in &moves[state] {
+            let chars = targets.entry(blocks[*target]).or_default();
+            *chars = chars.union(&atoms[*atom]);
+        }
+        let mut edges = targets.into_iter().map(|(target_state, chars)| {
+            let charset_idx = match dfa.charsets.iter().position(|c| *c == chars) {
+                Some(index) => index,
+                None => {
+                    dfa.charsets.push(chars.clone());
+                    dfa.charsets.len() - 1
+                },
+            };
+            DFAEdge { chars, charset_idx, target_state }
+        }).collect::<Vec<_>>();
+        edges.sort_by_key(|e| e.chars.ranges().first().map(|r| *r.start()));
+        let accept = accepts[state].map(|t| terminals[t].symbol.clone());
+        dfa.states.push(DFAState::new(block, accept.is_some(), accept.unwrap_or_default(), edges));
+    }
+    Ok(dfa)
+}
+
+/// Builds the tokenizer tables for terminals written in GOLD's regular expression syntax
+///
+/// The tables tokenize by longest match. When two terminals match the same text, one
+/// defined only by literal text wins, so keywords can be added next to an identifier.
+/// Sets are looked up among those added with `set`, then among GOLD's predefined sets.
+///
+/// ```
+/// use goldparser::engine::{CharacterSet, Symbol, SymbolType};
+/// use goldparser::grammar::LexerBuilder;
+///
+/// let symbol = |index, name: &str| Symbol::new(index, name.to_string(), SymbolType::Terminal);
+/// let (states, charsets) = LexerBuilder::new()
+///     .set("Id Head", CharacterSet::new(vec!['a'..='z', '_'..='_']))
+///     .terminal(symbol(2, "Id"), "{Id Head}{AlphaNumeric}*").unwrap()
+///     .terminal(symbol(3, "Number"), "{Digit}+('.'{Digit}+)?").unwrap()
+///     .terminal(symbol(4, "while"), "while").unwrap()
+///     .build().unwrap();
+/// assert!(charsets.len() > 0);
+/// assert!(states[0].find_edge('w').is_some());
+/// ```
+#[derive(Default)]
+pub struct LexerBuilder {
+    sets: CharacterSets,
+    terminals: Vec<LexerTerminal>,
+    case_sensitive: bool,
+}
+
+impl LexerBuilder {
+    /// A case insensitive lexer, which is GOLD's default
+    pub fn new() -> Self { LexerBuilder::default() }
+
+    pub fn case_sensitive(&mut self, case_sensitive: bool) -> &mut Self {
+        self.case_sensitive = case_sensitive;
+        self
+    }
+    /// Defines the set `{name}`, replacing a set with the same name
+    pub fn set(&mut self, name: &str, set: CharacterSet) -> &mut Self {
+        self.sets.define(name, set);
+        self
+    }
+    /// Adds a terminal matching `regex`, like `{Letter}{AlphaNumeric}*`
+    pub fn terminal(&mut self, symbol: Symbol, regex: &str) -> Result<&mut Self, GrammarError> {
+        let regex = RegExp::parse(regex)?;
+        Ok(self.terminal_regex(symbol, regex))
+    }
+    /// Adds a terminal matching an already parsed `RegExp`
+    pub fn terminal_regex(&mut self, symbol: Symbol, regex: RegExp) -> &mut Self {
+        self.terminals.push(LexerTerminal { symbol, regex, line: 0 });
+        self
+    }
+
+    /// Builds the minimal DFA. State 0 is the initial state.
+    pub fn build(&self) -> Result<(DFAStateTable, CharacterSetTable), GrammarError> {
+        let dfa = build(&self.terminals, &self.sets, self.case_sensitive)?;
+        let mut states = DFAStateTable::new();
+        for state in dfa.states {
+            states.add(state);
+        }
+        let mut charsets = CharacterSetTable::new();
+        charsets.resize(dfa.charsets.len());
+        for (index, set) in dfa.charsets.into_iter().enumerate() {
+            charsets.add(index, set);
+        }
+        Ok((states, charsets))
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::{engine::{Symbol, SymbolType}, grammar::{GrammarError, sets::{CharacterSets, SetItem}}};
+
+    use super::{build, Dfa, LexerBuilder, LexerTerminal, RegExp};
+
+    fn terminal(index: usize, name: &str, regex: RegExp) -> LexerTerminal {
+        LexerTerminal { symbol: Symbol::new(index, name.to_string(), SymbolType::Terminal), regex, line: 1 }
+    }
+
+    /// Runs the DFA over `text` and returns the longest match
+    fn longest(dfa: &Dfa, text: &str) -> Option<(String, usize)> {
+        let (mut state, mut found) = (0, None);
+        for (len, ch) in text.chars().enumerate() {
+            match dfa.states[state].find_edge(ch) {
+                Some(next) => state = next,
+                None => break,
+            }
+            if dfa.states[state].accept {
+                found = Some((dfa.states[state].accept_symbol.name.clone(), len + 1));
+            }
+        }
+        found
+    }
+
+    fn id_and_keyword() -> Vec<LexerTerminal> {
+        let letter = || RegExp::Set(SetItem::Name("Letter".to_string()));
+        vec![
+            terminal(2, "Id", RegExp::Sequence(vec![letter(), RegExp::Star(Box::new(letter()))])),
+            terminal(3, "if", RegExp::Literal("if".to_string())),
+        ]
+    }
+
+    #[test]
+    fn keywords_win() {
+        let dfa = build(&id_and_keyword(), &CharacterSets::new(), true).unwrap();
+        assert_eq!(longest(&dfa, "if("), Some(("if".to_string(), 2)));
+        assert_eq!(longest(&dfa, "iffy "), Some(("Id".to_string(), 4)));
+        assert_eq!(longest(&dfa, "IF"), Some(("Id".to_string(), 2)));
+        assert_eq!(longest(&dfa, "1"), None);
+        // edges of a state never overlap
+        for state in &dfa.states {
+            for (i, a) in state.edges.iter().enumerate() {
+                for b in &state.edges[i + 1..] {
+                    assert!(a.chars.intersection(&b.chars).is_empty());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn case_insensitive() {
+        let dfa = build(&id_and_keyword(), &CharacterSets::new(), false).unwrap();
+        assert_eq!(longest(&dfa, "IF"), Some(("if".to_string(), 2)));
+        assert_eq!(longest(&dfa, "iF"), Some(("if".to_string(), 2)));
+    }
+
+    #[test]
+    fn minimal() {
+        let dfa = build(&[terminal(2, "X", RegExp::parse("ab | cb").unwrap())], &CharacterSets::new(), true).unwrap();
+        assert_eq!(dfa.states.len(), 3);
+        assert_eq!(dfa.states[0].edges.len(), 1);
+        assert_eq!(longest(&dfa, "cb"), Some(("X".to_string(), 2)));
+
+        // {Digit}+ and {Digit}* '.' {Digit}+ share no states after the first digit
+        let number = RegExp::parse("{Digit}+('.'{Digit}+)?").unwrap();
+        let dfa = build(&[terminal(2, "Number", number)], &CharacterSets::new(), true).unwrap();
+        assert_eq!(dfa.states.len(), 4);
+    }
+
+    #[test]
+    fn builder() {
+        let symbol = |index, name: &str| Symbol::new(index, name.to_string(), SymbolType::Terminal);
+        let mut string_ch = crate::grammar::sets::predefined("Printable").unwrap();
+        string_ch.remove('\'');
+        let (states, charsets) = LexerBuilder::new()
+            .set("String Ch", string_ch)
+            .terminal(symbol(2, "Id"), "{Letter}{AlphaNumeric}*").unwrap()
+            .terminal(symbol(3, "String"), "''{String Ch}*''").unwrap()
+            .terminal(symbol(4, "Number"), "{Digit}+('.'{Digit}+)?").unwrap()
+            .terminal(symbol(5, "while"), "while").unwrap()
+            .build().unwrap();
+        let dfa = Dfa { states: states.iter().cloned().collect(), charsets: charsets.iter().cloned().collect() };
+        assert_eq!(longest(&dfa, "'it''s"), Some(("String".to_string(), 4)));
+        assert_eq!(longest(&dfa, "3.14."), Some(("Number".to_string(), 4)));
+        assert_eq!(longest(&dfa, "3."), Some(("Number".to_string(), 1)));
+        assert_eq!(longest(&dfa, "WHILE x"), Some(("while".to_string(), 5)));
+        assert_eq!(longest(&dfa, "whiles"), Some(("Id".to_string(), 6)));
+        for state in states.iter() {
+            for edge in &state.edges {
+                assert_eq!(charsets[edge.charset_idx], edge.chars);
+            }
+        }
+        assert!(matches!(RegExp::parse("{Letter} )"), Err(GrammarError::Syntax { .. })));
+    }
+
+    #[test]
+    fn errors() {
+        let mut terminals = id_and_keyword();
+        terminals.push(terminal(4, "Name", RegExp::Plus(Box::new(RegExp::Set(SetItem::Name("Letter".to_string()))))));
+        assert!(matches!(build(&terminals, &CharacterSets::new(), true),
+            Err(GrammarError::LexicalConflict(a, b)) if a == "Id" && b == "Name"));
+
+        let terminals = vec![terminal(2, "Blank", RegExp::Star(Box::new(RegExp::Literal(" ".to_string()))))];
+        assert!(matches!(build(&terminals, &CharacterSets::new(), true), Err(GrammarError::EmptyTerminal(_))));
+
+        let terminals = vec![terminal(2, "X", RegExp::Set(SetItem::Name("Nope".to_string())))];
+        assert!(matches!(build(&terminals, &CharacterSets::new(), true), Err(GrammarError::UndefinedSet { .. })));
+    }
+}
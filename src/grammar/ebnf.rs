@@ -0,0 +1,129 @@
+//! EBNF desugaring
+//!
+//! Replaces each group in a rule body by a generated nonterminal named after the group as it
+//! is written, such as `<Statement>*` or `(',' <Expression>)?`:
+//! * `X?` becomes `<X?> ::= X |`
+//! * `X*` becomes `<X*> ::= <X*> X |`
+//! * `X+` becomes `<X+> ::= <X+> X | X`
+//! * `(X | Y)` becomes `<(X | Y)> ::= X | Y`
+//!
+//! Repetitions are left recursive so the parser stack does not grow with the list. The parser
+//! splices the tokens of these nonterminals into the reduction using them, see
+//! `Symbol::is_generated`. Rules cannot be named like a group, so the names do not clash.
+
+use super::grm::{Repeat, RuleDecl, RuleSymbol};
+
+/// The name of the nonterminal generated for a group
+fn name(handles: &[Vec<RuleSymbol>], repeat: Repeat) -> String {
+    let handle = |symbols: &[RuleSymbol]| symbols.iter().map(|s| match s {
+        RuleSymbol::Terminal(text) if text.chars().all(char::is_alphanumeric) => text.clone(),
+        RuleSymbol::Terminal(text) => format!("'{}'", text.replace('\'', "''")),
+        RuleSymbol::Nonterminal(name) => format!("<{name}>"),
+        RuleSymbol::Group(handles, repeat) => name(handles, *repeat),
+    }).collect::<Vec<_>>().join(" ");
+    let suffix = match repeat {
+        Repeat::One => "",
+        Repeat::Optional => "?",
+        Repeat::Star => "*",
+        Repeat::Plus => "+",
+    };
+    match handles {
+        [symbols] if symbols.len() == 1 && repeat != Repeat::One && !matches!(symbols[0], RuleSymbol::Group(..)) => {
+            format!("{}{suffix}", handle(symbols))
+        },
+        _ => format!("({}){suffix}", handles.iter().map(|h| handle(h)).collect::<Vec<_>>().join(" | ")),
+    }
+}
+
+struct Desugar {
+    /// Rules for the generated nonterminals
+    generated: Vec<RuleDecl>,
+}
+
+impl Desugar {
+    fn handle(&mut self, symbols: &[RuleSymbol], line: usize) -> Vec<RuleSymbol> {
+        symbols.iter().map(|symbol| match symbol {
+            RuleSymbol::Group(handles, repeat) => RuleSymbol::Nonterminal(self.group(handles, *repeat, line)),
+            symbol => symbol.clone(),
+        }).collect()
+    }
+
+    /// Adds the rules of a group, unless an identical group was already seen
+    fn group(&mut self, handles: &[Vec<RuleSymbol>], repeat: Repeat, line: usize) -> String {
+        let head = name(handles, repeat);
+        if self.generated.iter().any(|r| r.head == head) {
+            return head;
+        }
+        let index = self.generated.len();
+        self.generated.push(RuleDecl { head: head.clone(), handles: Vec::new(), line });
+        let bodies = handles.iter().map(|h| self.handle(h, line)).collect::<Vec<_>>();
+        let recursive = |body: &Vec<RuleSymbol>| [vec![RuleSymbol::Nonterminal(head.clone())], body.clone()].concat();
+        self.generated[index].handles = match repeat {
+            Repeat::One => bodies,
+            Repeat::Optional => bodies.into_iter().chain([Vec::new()]).collect(),
+            Repeat::Star => bodies.iter().map(recursive).chain([Vec::new()]).collect(),
+            Repeat::Plus => bodies.iter().map(recursive).chain(bodies.iter().cloned()).collect(),
+        };
+        head
+    }
+}
+
+/// Returns the rules without groups: the rules as written, then the generated ones
+pub(crate) fn desugar(rules: &[RuleDecl]) -> Vec<RuleDecl> {
+    let mut desugar = Desugar { generated: Vec::new() };
+    let mut plain = rules.iter().map(|rule| RuleDecl {
+        head: rule.head.clone(),
+        handles: rule.handles.iter().map(|h| desugar.handle(h, rule.line)).collect(),
+        line: rule.line,
+    }).collect::<Vec<_>>();
+    plain.append(&mut desugar.generated);
+    plain
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::grammar::grm::{Grammar, RuleSymbol};
+
+    use super::desugar;
+
+    fn rules(source: &str) -> Vec<String> {
+        let grammar = Grammar::parse(source).unwrap();
+        desugar(&grammar.rules).iter().map(|rule| {
+            let handles = rule.handles.iter().map(|h| h.iter().map(|s| match s {
+                RuleSymbol::Terminal(text) => format!("'{text}'"),
+                RuleSymbol::Nonterminal(name) => format!("<{name}>"),
+                RuleSymbol::Group(..) => unreachable!(),
+            }).collect::<Vec<_>>().join(" ")).collect::<Vec<_>>();
+            format!("<{}> ::= {}", rule.head, handles.join(" | "))
+        }).collect()
+    }
+
+    #[test]
+    fn groups() {
+        assert_eq!(rules("<Program> ::= <Statement>*\n<Block> ::= '{' <Statement>* '}'\n"), vec![
+            "<Program> ::= <<Statement>*>",
+            "<Block> ::= '{' <<Statement>*> '}'",
+            "<<Statement>*> ::= <<Statement>*> <Statement> | ",
+        ]);
+        assert_eq!(rules("<Args> ::= <Exp> (',' <Exp>)* | \n<Id> ::= Id+ ( a | b c )?\n"), vec![
+            "<Args> ::= <Exp> <(',' <Exp>)*> | ",
+            "<Id> ::= <Id+> <(a | b c)?>",
+            "<(',' <Exp>)*> ::= <(',' <Exp>)*> ',' <Exp> | ",
+            "<Id+> ::= <Id+> 'Id' | 'Id'",
+            "<(a | b c)?> ::= 'a' | 'b' 'c' | ",
+        ]);
+        assert_eq!(rules("<A> ::= (<B>+ | x)\n"), vec![
+            "<A> ::= <(<B>+ | x)>",
+            "<(<B>+ | x)> ::= <<B>+> | 'x'",
+            "<<B>+> ::= <<B>+> <B> | <B>",
+        ]);
+        // a terminal and a nonterminal of the same name are different groups
+        assert_eq!(rules("<S> ::= '[' Id+ ']' <Id>+\n<Id> ::= '#'\n"), vec![
+            "<S> ::= '[' <Id+> ']' <<Id>+>",
+            "<Id> ::= '#'",
+            "<Id+> ::= <Id+> 'Id' | 'Id'",
+            "<<Id>+> ::= <<Id>+> <Id> | <Id>",
+        ]);
+    }
+}
@@ -0,0 +1,663 @@
+//! Grammar export
+//!
+//! Writes a grammar table in the notation of other parser tools, so the same language
+//! definition can drive editors and documentation:
+//! * `Notation::Ebnf`, the W3C EBNF of the XML specification
+//! * `Notation::Antlr`, an ANTLR 4 combined grammar (`.g4`)
+//! * `Notation::TreeSitter`, a tree-sitter `grammar.js`
+//!
+//! Rules come from the production table and terminal definitions from the DFA, recovered as
+//! `decompile` does. Terminals defined by their text are written inline. GOLD names become
+//! identifiers in the style of each tool: `<Add Exp>` is `AddExp`, `addExp` or `add_exp`.
+//! Noise terminals and comment groups are skipped by the generated lexers; group nesting is
+//! not exported.
+//!
+//! ```no_run
+//! use goldparser::{engine::Builder, grammar::export::{export, Notation}};
+//!
+//! let egt = Builder::from_path("examples/simple.egt").and_then(|mut b| b.to_egt()).unwrap();
+//! std::fs::write("Simple.g4", export(&egt, Notation::Antlr)).unwrap();
+//! ```
+
+use std::fmt::Write;
+
+use crate::engine::{
+    charset::succ, group::EndingMode, CharacterSet, EnhancedGrammarTable, LexicalGroup, Symbol, SymbolType,
+};
+
+use super::{
+    analysis::{start_symbol, Analysis},
+    decompile::{Lexicon, Pattern},
+};
+
+/// The notations a grammar can be exported to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Notation {
+    /// W3C EBNF
+    Ebnf,
+    /// ANTLR 4 combined grammar
+    Antlr,
+    /// tree-sitter `grammar.js`
+    TreeSitter,
+}
+
+/// Writes the grammar table in `notation`
+pub fn export(egt: &EnhancedGrammarTable, notation: Notation) -> String {
+    let exporter = Exporter::new(egt, notation);
+    match notation {
+        Notation::Ebnf => exporter.ebnf(),
+        Notation::Antlr => exporter.antlr(),
+        Notation::TreeSitter => exporter.tree_sitter(),
+    }
+}
+
+/// Identifier styles
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Case {
+    Pascal,
+    Camel,
+    Snake,
+    Upper,
+}
+
+/// Words ANTLR reserves for its own syntax and tokens
+const ANTLR_RESERVED: &[&str] = &[
+    "catch", "channels", "finally", "fragment", "grammar", "import", "lexer", "locals", "mode",
+    "options", "parser", "returns", "throws", "tokens", "EOF",
+];
+
+/// Splits a GOLD name into lowercase words at spaces, punctuation and case changes, so `Add Exp`,
+/// `AddExp` and `add-exp` all give `add`, `exp`. The nonterminals generated for EBNF groups,
+/// like `<Statement>*`, get a word for their repetition.
+fn words(name: &str) -> Vec<String> {
+    let mut words = Vec::<String>::new();
+    let mut previous = None::<char>;
+    for ch in name.chars() {
+        if !ch.is_alphanumeric() {
+            previous = None;
+            continue;
+        }
+        let boundary = match previous {
+            None => true,
+            Some(previous) => ch.is_uppercase() && (previous.is_lowercase() || previous.is_numeric()),
+        };
+        if boundary {
+            words.push(String::new());
+        }
+        words.last_mut().unwrap().extend(ch.to_lowercase());
+        previous = Some(ch);
+    }
+    let repeat = match name.chars().last() {
+        Some('?') => "opt",
+        Some('*') => "star",
+        Some('+') => "plus",
+        Some(')') => "group",
+        _ => "",
+    };
+    if !repeat.is_empty() && name.chars().count() > 1 {
+        words.push(repeat.to_string());
+    }
+    words
+}
+
+/// A GOLD name as an identifier in the given style
+fn identifier(name: &str, case: Case) -> String {
+    let mut words = words(name);
+    if words.is_empty() {
+        words.push("symbol".to_string());
+    }
+    if words[0].starts_with(char::is_numeric) {
+        words.insert(0, "n".to_string());
+    }
+    let capital = |word: &String| {
+        let mut chars = word.chars();
+        chars.next().map(|first| first.to_uppercase().chain(chars).collect::<String>()).unwrap_or_default()
+    };
+    match case {
+        Case::Pascal => words.iter().map(capital).collect(),
+        Case::Camel => words[0].clone() + &words[1..].iter().map(capital).collect::<String>(),
+        Case::Snake => words.join("_"),
+        Case::Upper => words.join("_").to_uppercase(),
+    }
+}
+
+/// Escapes `ch` for a quoted string or character class, where `special` are escaped
+/// with a backslash
+fn escape(ch: char, special: &[char]) -> String {
+    match ch {
+        '\t' => "\\t".to_string(),
+        '\n' => "\\n".to_string(),
+        '\r' => "\\r".to_string(),
+        ch if special.contains(&ch) => format!("\\{ch}"),
+        ch if (ch as u32) > 0xFFFF => format!("\\u{{{:X}}}", ch as u32),
+        ch if ch.is_control() || !ch.is_ascii() => format!("\\u{:04X}", ch as u32),
+        ch => ch.to_string(),
+    }
+}
+
+/// The ranges of a character class as `a-z`, each end written by `write`
+fn ranges(chars: &CharacterSet, write: impl Fn(char) -> String) -> String {
+    chars.ranges().iter().map(|range| {
+        let (start, end) = (*range.start(), *range.end());
+        match succ(start) {
+            _ if start == end => write(start),
+            Some(next) if next == end => format!("{}{}", write(start), write(end)),
+            _ => format!("{}-{}", write(start), write(end)),
+        }
+    }).collect()
+}
+
+/// The characters not in `chars`, when that is written with fewer ranges
+fn complement(chars: &CharacterSet) -> Option<CharacterSet> {
+    let all = CharacterSet::new(vec!['\u{1}'..='\u{D7FF}', '\u{E000}'..=char::MAX]);
+    let complement = all.difference(chars);
+    (complement.ranges().len() < chars.ranges().len()).then_some(complement)
+}
+
+/// The characters a pattern may start with
+fn first(pattern: &Pattern) -> CharacterSet {
+    match pattern {
+        Pattern::Literal(text) => text.chars().next().map(|ch| CharacterSet::new(vec![ch..=ch])).unwrap_or_default(),
+        Pattern::Chars(chars) => chars.clone(),
+        Pattern::Sequence(items) => items.first().map(first).unwrap_or_default(),
+        Pattern::Alternation(items) => items.iter().map(first).fold(CharacterSet::default(), |a, b| a.union(&b)),
+        Pattern::Star(item) | Pattern::Plus(item) | Pattern::Optional(item) => first(item),
+    }
+}
+
+struct Exporter<'a> {
+    egt: &'a EnhancedGrammarTable,
+    notation: Notation,
+    lexicon: Lexicon,
+    start: Option<&'a Symbol>,
+    /// Identifiers by symbol index, for nonterminals and terminals that are not written inline
+    names: Vec<Option<String>>,
+}
+
+impl<'a> Exporter<'a> {
+    fn new(egt: &'a EnhancedGrammarTable, notation: Notation) -> Self {
+        let lexicon = Lexicon::new(egt);
+        let mut exporter = Exporter {
+            egt, notation, lexicon,
+            start: start_symbol(egt),
+            names: vec![None; egt.symbols.iter().count()],
+        };
+        let (rule, token) = match notation {
+            Notation::Ebnf => (Case::Pascal, Case::Pascal),
+            Notation::Antlr => (Case::Camel, Case::Upper),
+            Notation::TreeSitter => (Case::Snake, Case::Snake),
+        };
+        let nonterminals = exporter.nonterminals();
+        let tokens = egt.symbols.iter().filter(|s| s.kind != SymbolType::NonTerminal && exporter.inline(s).is_none())
+            .collect::<Vec<_>>();
+        for (symbol, case) in nonterminals.into_iter().map(|s| (s, rule)).chain(tokens.into_iter().map(|s| (s, token))) {
+            let name = exporter.fresh(&identifier(&symbol.name, case), case);
+            exporter.names[symbol.index] = Some(name);
+        }
+        exporter
+    }
+
+    /// The identifier `name`, numbered when it is reserved or already taken
+    fn fresh(&self, name: &str, case: Case) -> String {
+        let taken = |candidate: &str| self.names.iter().flatten().any(|n| n == candidate)
+            || (self.notation == Notation::Antlr && ANTLR_RESERVED.contains(&candidate));
+        (1..).map(|n| match (n, case) {
+            (1, _) => name.to_string(),
+            (_, Case::Snake | Case::Upper) => format!("{name}_{n}"),
+            _ => format!("{name}{n}"),
+        }).find(|candidate| !taken(candidate)).unwrap()
+    }
+
+    /// The nonterminals with rules, the start symbol first
+    fn nonterminals(&self) -> Vec<&'a Symbol> {
+        let mut heads = self.start.into_iter().collect::<Vec<_>>();
+        for rule in self.egt.productions.iter() {
+            if !heads.iter().any(|h| h.index == rule.head.index) {
+                heads.push(&rule.head);
+            }
+        }
+        heads
+    }
+
+    /// The text of a terminal that is defined by it, written inline in rules
+    fn inline(&self, symbol: &Symbol) -> Option<&str> {
+        match self.lexicon.pattern(symbol.index) {
+            Some(Pattern::Literal(text)) if symbol.kind == SymbolType::Terminal => Some(text),
+            _ => None,
+        }
+    }
+
+    fn name(&self, symbol: &Symbol) -> String {
+        self.names[symbol.index].clone().unwrap_or_else(|| symbol.name.clone())
+    }
+
+    /// A symbol in a rule
+    fn symbol(&self, symbol: &Symbol) -> String {
+        match (self.inline(symbol), self.notation) {
+            (Some(text), _) => self.literal(text),
+            (None, Notation::TreeSitter) => format!("$.{}", self.name(symbol)),
+            (None, _) => self.name(symbol),
+        }
+    }
+
+    /// The text of a group: its start, then everything up to its end
+    fn group(&self, group: &LexicalGroup) -> Option<Pattern> {
+        let start = self.lexicon.pattern(group.start_idx)?.clone();
+        let end = self.lexicon.pattern(group.end_idx)?;
+        let body = match end {
+            Pattern::Literal(text) if group.ending_mode == EndingMode::Closed => self.lexicon.until(text)?,
+            end => {
+                let all = CharacterSet::new(vec!['\u{1}'..='\u{D7FF}', '\u{E000}'..=char::MAX]);
+                Pattern::Star(Box::new(Pattern::Chars(all.difference(&first(end)))))
+            },
+        };
+        Some(Pattern::Sequence(vec![start, body]))
+    }
+
+    /// The terminals and groups that need a definition, as (symbol, pattern, noise)
+    fn tokens(&self) -> Vec<(&'a Symbol, Pattern, bool)> {
+        let delimiter = |index: usize| self.egt.groups.iter().any(|g| g.start_idx == index || g.end_idx == index);
+        let mut tokens = Vec::new();
+        for symbol in self.egt.symbols.iter() {
+            if self.inline(symbol).is_some() || delimiter(symbol.index) {
+                continue;
+            }
+            // a container like Comment may have both a line and a block group
+            let mut groups = self.egt.groups.iter().filter(|g| g.container_idx == symbol.index)
+                .filter_map(|g| self.group(g)).collect::<Vec<_>>();
+            let pattern = match groups.len() {
+                0 => self.lexicon.pattern(symbol.index).cloned(),
+                1 => groups.pop(),
+                _ => Some(Pattern::Alternation(groups)),
+            };
+            if let Some(pattern) = pattern {
+                tokens.push((symbol, pattern, symbol.kind == SymbolType::Noise));
+            }
+        }
+        tokens
+    }
+
+    /// The handles of the rules for `head`, as lists of symbols
+    fn handles(&self, head: &Symbol) -> Vec<Vec<&'a Symbol>> {
+        self.egt.productions.iter().filter(|r| r.head.index == head.index)
+            .map(|r| r.symbols.iter().collect()).collect()
+    }
+
+    /// Text matching a literal terminal
+    fn literal(&self, text: &str) -> String {
+        match self.notation {
+            Notation::Ebnf => {
+                // W3C strings have no escapes: control characters are written as codes, and
+                // text with both quotes is split
+                let quote = |run: &str| match run {
+                    run if !run.contains('\'') => format!("'{run}'"),
+                    run if !run.contains('"') => format!("\"{run}\""),
+                    run => run.split('\'').map(|p| format!("'{p}'")).collect::<Vec<_>>().join(" \"'\" "),
+                };
+                let mut pieces = Vec::<String>::new();
+                let mut run = String::new();
+                for ch in text.chars() {
+                    if ch.is_control() {
+                        if !run.is_empty() {
+                            pieces.push(quote(&std::mem::take(&mut run)));
+                        }
+                        pieces.push(format!("#x{:X}", ch as u32));
+                    } else {
+                        run.push(ch);
+                    }
+                }
+                if !run.is_empty() {
+                    pieces.push(quote(&run));
+                }
+                pieces.join(" ")
+            },
+            Notation::Antlr => format!("'{}'", text.chars().map(|c| escape(c, &['\'', '\\'])).collect::<String>()),
+            Notation::TreeSitter if !self.lexicon.case_sensitive && text.chars().any(char::is_alphabetic) => {
+                let regex = self.regex(&Pattern::Literal(text.to_string()));
+                format!("alias(token(prec(1, /{regex}/)), {})", self.js_string(text))
+            },
+            Notation::TreeSitter => self.js_string(text),
+        }
+    }
+
+    fn js_string(&self, text: &str) -> String {
+        format!("'{}'", text.chars().map(|c| escape(c, &['\'', '\\'])).collect::<String>())
+    }
+
+    /// A terminal definition in the lexer syntax of the notation
+    fn pattern(&self, pattern: &Pattern) -> String {
+        match self.notation {
+            Notation::TreeSitter => format!("/{}/", self.regex(pattern)),
+            _ => self.element(pattern),
+        }
+    }
+
+    /// A pattern as an EBNF or ANTLR lexer expression
+    fn element(&self, pattern: &Pattern) -> String {
+        let atomic = |p: &Pattern| matches!(p, Pattern::Literal(_) | Pattern::Chars(_));
+        match pattern {
+            Pattern::Literal(text) => self.literal(text),
+            Pattern::Chars(chars) => {
+                let (negated, chars) = match complement(chars) {
+                    Some(complement) => (true, complement),
+                    None => (false, chars.clone()),
+                };
+                match self.notation {
+                    Notation::Ebnf => {
+                        let code = |ch: char| match ch {
+                            ch if ch.is_ascii_graphic() && !"[]^-#".contains(ch) => ch.to_string(),
+                            ch => format!("#x{:X}", ch as u32),
+                        };
+                        format!("[{}{}]", if negated { "^" } else { "" }, ranges(&chars, code))
+                    },
+                    _ => {
+                        let code = |ch: char| escape(ch, &[']', '\\', '-']);
+                        format!("{}[{}]", if negated { "~" } else { "" }, ranges(&chars, code))
+                    },
+                }
+            },
+            Pattern::Sequence(items) => items.iter().map(|item| match item {
+                Pattern::Alternation(_) => format!("({})", self.element(item)),
+                item => self.element(item),
+            }).collect::<Vec<_>>().join(" "),
+            Pattern::Alternation(items) => items.iter().map(|i| self.element(i)).collect::<Vec<_>>().join(" | "),
+            Pattern::Star(item) | Pattern::Plus(item) | Pattern::Optional(item) => {
+                let suffix = match pattern {
+                    Pattern::Star(_) => '*',
+                    Pattern::Plus(_) => '+',
+                    _ => '?',
+                };
+                match atomic(item) {
+                    true => format!("{}{suffix}", self.element(item)),
+                    false => format!("({}){suffix}", self.element(item)),
+                }
+            },
+        }
+    }
+
+    /// A pattern as a JavaScript regular expression, for tree-sitter
+    fn regex(&self, pattern: &Pattern) -> String {
+        const SPECIAL: &[char] = &['\\', '^', '$', '.', '|', '?', '*', '+', '(', ')', '[', ']', '{', '}', '/'];
+        let class = |chars: &CharacterSet| match complement(chars) {
+            Some(complement) => format!("[^{}]", ranges(&complement, |c| escape(c, &['\\', ']', '[', '^', '-', '/']))),
+            None => format!("[{}]", ranges(chars, |c| escape(c, &['\\', ']', '[', '^', '-', '/']))),
+        };
+        match pattern {
+            Pattern::Literal(text) => text.chars().map(|ch| match ch {
+                ch if !self.lexicon.case_sensitive && ch.is_alphabetic() => class(&super::sets::fold_case(&CharacterSet::new(vec![ch..=ch]))),
+                ch => escape(ch, SPECIAL),
+            }).collect(),
+            Pattern::Chars(chars) => class(chars),
+            Pattern::Sequence(items) => items.iter().map(|item| match item {
+                Pattern::Alternation(_) => format!("({})", self.regex(item)),
+                item => self.regex(item),
+            }).collect(),
+            Pattern::Alternation(items) => items.iter().map(|i| self.regex(i)).collect::<Vec<_>>().join("|"),
+            Pattern::Star(item) | Pattern::Plus(item) | Pattern::Optional(item) => {
+                let suffix = match pattern {
+                    Pattern::Star(_) => '*',
+                    Pattern::Plus(_) => '+',
+                    _ => '?',
+                };
+                match &**item {
+                    Pattern::Chars(_) => format!("{}{suffix}", self.regex(item)),
+                    Pattern::Literal(text) if text.chars().count() == 1 => format!("{}{suffix}", self.regex(item)),
+                    item => format!("({}){suffix}", self.regex(item)),
+                }
+            },
+        }
+    }
+
+    fn ebnf(&self) -> String {
+        let mut source = String::new();
+        if !self.lexicon.case_sensitive {
+            source.push_str("/* Literals match text in any case */\n\n");
+        }
+        let alternatives = |handles: Vec<String>, indent: usize| {
+            let (empty, handles): (Vec<_>, Vec<_>) = handles.into_iter().partition(|h| h.is_empty());
+            let body = handles.join(&format!("\n{}| ", " ".repeat(indent)));
+            match (empty.is_empty(), handles.len()) {
+                (true, _) => body,
+                (false, 0) => "/* empty */".to_string(),
+                (false, 1) if !body.contains(' ') => format!("{body}?"),
+                (false, _) => format!("({})?", body.replace(&format!("\n{}| ", " ".repeat(indent)), " | ")),
+            }
+        };
+        for head in self.nonterminals() {
+            let name = self.name(head);
+            let handles = self.handles(head).into_iter()
+                .map(|h| h.into_iter().map(|s| self.symbol(s)).collect::<Vec<_>>().join(" ")).collect();
+            writeln!(source, "{name} ::= {}", alternatives(handles, name.chars().count() + 3)).unwrap();
+        }
+
+        let tokens = self.tokens();
+        for noise in [false, true] {
+            let tokens = tokens.iter().filter(|(.., n)| *n == noise).collect::<Vec<_>>();
+            if tokens.is_empty() {
+                continue;
+            }
+            source.push('\n');
+            if noise {
+                source.push_str("/* Ignored between symbols */\n");
+            }
+            let width = tokens.iter().map(|(s, ..)| self.name(s).chars().count()).max().unwrap_or(0);
+            for (symbol, pattern, _) in tokens {
+                writeln!(source, "{:width$} ::= {}", self.name(symbol), self.pattern(pattern)).unwrap();
+            }
+        }
+        source
+    }
+
+    fn antlr(&self) -> String {
+        let mut source = String::new();
+        let name = self.egt.properties.name().unwrap_or("Grammar");
+        writeln!(source, "grammar {};\n", identifier(name, Case::Pascal)).unwrap();
+        if !self.lexicon.case_sensitive {
+            source.push_str("options { caseInsensitive = true; }\n\n");
+        }
+        let rule = |source: &mut String, name: &str, alternatives: Vec<String>| {
+            writeln!(source, "{name}\n    : {}\n    ;\n", alternatives.join("\n    | ")).unwrap();
+        };
+        // GOLD accepts at the end of the input, ANTLR only when told
+        if let Some(start) = self.start {
+            rule(&mut source, &self.fresh("start", Case::Camel), vec![format!("{} EOF", self.name(start))]);
+        }
+        for head in self.nonterminals() {
+            let handles = self.handles(head).into_iter()
+                .map(|h| h.into_iter().map(|s| self.symbol(s)).collect::<Vec<_>>().join(" ")).collect();
+            rule(&mut source, &self.name(head), handles);
+        }
+        for (symbol, pattern, noise) in self.tokens() {
+            // lexer commands apply per alternative
+            let skip = if noise { " -> skip" } else { "" };
+            let alternatives = match &pattern {
+                Pattern::Alternation(items) => items.iter().map(|item| format!("{}{skip}", self.pattern(item))).collect(),
+                pattern => vec![format!("{}{skip}", self.pattern(pattern))],
+            };
+            rule(&mut source, &self.name(symbol), alternatives);
+        }
+        source.truncate(source.trim_end().len());
+        source.push('\n');
+        source
+    }
+
+    /// tree-sitter rules may not match the empty string, except the start rule. Empty handles
+    /// are dropped, nullable nonterminals are made optional where they are used, and handles of
+    /// nullable symbols only are split by their first symbol present.
+    fn tree_sitter(&self) -> String {
+        let analysis = Analysis::with_start(self.egt, self.start.map(|s| s.index));
+        let nullable = analysis.nullable().iter().map(|s| s.index).collect::<Vec<_>>();
+        // nonterminals matching only the empty string are left out
+        let mut empty = Vec::<usize>::new();
+        loop {
+            let more = self.nonterminals().into_iter().filter(|h| !empty.contains(&h.index))
+                .filter(|h| self.handles(h).iter().all(|handle| handle.iter().all(|s| empty.contains(&s.index))))
+                .map(|h| h.index).collect::<Vec<_>>();
+            if more.is_empty() {
+                break;
+            }
+            empty.extend(more);
+        }
+
+        let item = |symbol: &Symbol, required: bool| match nullable.contains(&symbol.index) && !required {
+            true => format!("optional({})", self.symbol(symbol)),
+            false => self.symbol(symbol),
+        };
+        let seq = |items: Vec<String>| match items.len() {
+            1 => items.into_iter().next().unwrap(),
+            _ => format!("seq({})", items.join(", ")),
+        };
+        let choice = |alternatives: Vec<String>, indent: &str| match alternatives.len() {
+            0 => "blank()".to_string(),
+            1 => alternatives.into_iter().next().unwrap(),
+            _ => format!("choice(\n{}\n{indent})", alternatives.iter().map(|a| format!("{indent}  {a},")).collect::<Vec<_>>().join("\n")),
+        };
+
+        let mut rules = Vec::<String>::new();
+        for head in self.nonterminals() {
+            if empty.contains(&head.index) && self.start.is_none_or(|s| s.index != head.index) {
+                continue;
+            }
+            let mut alternatives = Vec::new();
+            for handle in self.handles(head) {
+                let handle = handle.into_iter().filter(|s| !empty.contains(&s.index)).collect::<Vec<_>>();
+                if handle.iter().all(|s| nullable.contains(&s.index)) {
+                    for (i, required) in handle.iter().enumerate() {
+                        let rest = handle[i + 1..].iter().map(|s| item(s, false));
+                        alternatives.push(seq(std::iter::once(item(required, true)).chain(rest).collect()));
+                    }
+                } else {
+                    alternatives.push(seq(handle.iter().map(|s| item(s, false)).collect()));
+                }
+            }
+            alternatives.dedup();
+            let mut body = choice(alternatives, "    ");
+            // only the start rule may match the empty string, other rules are made optional at their uses
+            if nullable.contains(&head.index) && body != "blank()" && self.start.is_some_and(|s| s.index == head.index) {
+                body = format!("optional({body})");
+            }
+            rules.push(format!("    {}: $ => {body},", self.name(head)));
+        }
+
+        let mut extras = Vec::new();
+        for (symbol, pattern, noise) in self.tokens() {
+            let grouped = self.egt.groups.iter().any(|g| g.container_idx == symbol.index);
+            match (noise, grouped) {
+                (true, false) => extras.push(self.pattern(&pattern)),
+                (true, true) => {
+                    extras.push(format!("$.{}", self.name(symbol)));
+                    rules.push(format!("    {}: $ => token({}),", self.name(symbol), self.pattern(&pattern)));
+                },
+                (false, _) => rules.push(format!("    {}: $ => {},", self.name(symbol), self.pattern(&pattern))),
+            }
+        }
+
+        let mut source = String::from("module.exports = grammar({\n");
+        let name = self.egt.properties.name().unwrap_or("grammar");
+        writeln!(source, "  name: '{}',\n", identifier(name, Case::Snake)).unwrap();
+        let word = self.lexicon.words.iter().map(|i| &self.egt.symbols[*i]).find(|s| s.kind == SymbolType::Terminal);
+        if let Some(word) = word {
+            writeln!(source, "  word: $ => $.{},\n", self.name(word)).unwrap();
+        }
+        writeln!(source, "  extras: $ => [\n{}\n  ],\n", extras.iter().map(|e| format!("    {e},")).collect::<Vec<_>>().join("\n")).unwrap();
+        writeln!(source, "  rules: {{\n{}\n  }},", rules.join("\n")).unwrap();
+        source.push_str("});\n");
+        source
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::{engine::Builder, grammar::compile};
+
+    use super::{export, identifier, Case, Notation};
+
+    #[test]
+    fn identifiers() {
+        assert_eq!(identifier("Add Exp", Case::Pascal), "AddExp");
+        assert_eq!(identifier("Add Exp", Case::Camel), "addExp");
+        assert_eq!(identifier("StringLiteral", Case::Snake), "string_literal");
+        assert_eq!(identifier("stmt seq", Case::Upper), "STMT_SEQ");
+        assert_eq!(identifier("<Statement>*", Case::Snake), "statement_star");
+        assert_eq!(identifier("(',' Exp)?", Case::Camel), "expOpt");
+        assert_eq!(identifier("2nd", Case::Pascal), "N2nd");
+    }
+
+    #[test]
+    fn simple() {
+        let egt = Builder::from_path(crate::test::GP_SIMPLE_EGT).unwrap().to_egt().unwrap();
+
+        let ebnf = export(&egt, Notation::Ebnf);
+        for line in [
+            "Statements ::= Statement Statements\n             | Statement\n",
+            "Expression ::= Expression '>' AddExp\n",
+            "Value ::= Id\n        | StringLiteral\n",
+            "Id            ::= [A-Za-z] [0-9A-Za-z]*\n",
+            "NumberLiteral ::= [0-9]+ ('.' [0-9]+)?\n",
+            "StringLiteral ::= \"'\" [#x20-&(-~#xA0]* \"'\"\n",
+        ] {
+            assert!(ebnf.contains(line), "{line} missing from\n{ebnf}");
+        }
+
+        let antlr = export(&egt, Notation::Antlr);
+        for line in [
+            "grammar Simple;\n\noptions { caseInsensitive = true; }\n",
+            "start\n    : statements EOF\n    ;\n",
+            "addExp\n    : addExp '+' multExp\n",
+            "ID\n    : [A-Za-z] [0-9A-Za-z]*\n    ;\n",
+            "WHITESPACE\n    : [\\t-\\r \\u0085\\u00A0",
+        ] {
+            assert!(antlr.contains(line), "{line} missing from\n{antlr}");
+        }
+
+        let tree_sitter = export(&egt, Notation::TreeSitter);
+        for line in [
+            "  name: 'simple',\n",
+            "  word: $ => $.id,\n",
+            "    statements: $ => choice(\n      seq($.statement, $.statements),\n      $.statement,\n    ),\n",
+            "alias(token(prec(1, /[Dd][Ii][Ss][Pp][Ll][Aa][Yy]/)), 'display')",
+            "    id: $ => /[A-Za-z][0-9A-Za-z]*/,\n",
+            "    number_literal: $ => /[0-9]+(\\.[0-9]+)?/,\n",
+        ] {
+            assert!(tree_sitter.contains(line), "{line} missing from\n{tree_sitter}");
+        }
+    }
+
+    #[test]
+    fn groups_and_empty_rules() {
+        let egt = compile(concat!(
+            "\"Name\" = 'Lists'\n",
+            "\"Start Symbol\" = <List>\n",
+            "\"Case Sensitive\" = True\n",
+            "Comment Line = '//'\n",
+            "Comment Start = '/*'\n",
+            "Comment End = '*/'\n",
+            "<List> ::= <List> <Item> |\n",
+            "<Item> ::= '[' <Pair> ']' | Id\n",
+            "<Pair> ::= <Opt> <Opt2>\n",
+            "<Opt> ::= a |\n",
+            "<Opt2> ::= b |\n",
+            "Id = {Letter}+\n",
+        )).unwrap();
+
+        let tree_sitter = export(&egt, Notation::TreeSitter);
+        for line in [
+            "    list: $ => optional(seq(optional($.list), $.item)),\n",
+            "    item: $ => choice(\n      seq('[', optional($.pair), ']'),\n      $.id,\n    ),\n",
+            "    pair: $ => choice(\n      seq($.opt, optional($.opt2)),\n      $.opt2,\n    ),\n",
+            "    opt: $ => 'a',\n",
+            "    comment: $ => token(/\\/\\*[^*]*\\*(\\*|[^*\\/][^*]*\\*)*\\/|\\/\\/[^\\n\\r]*/),\n",
+            "    $.comment,\n",
+        ] {
+            assert!(tree_sitter.contains(line), "{line} missing from\n{tree_sitter}");
+        }
+
+        let antlr = export(&egt, Notation::Antlr);
+        assert!(antlr.contains("list\n    : list item\n    | \n    ;\n"), "{antlr}");
+        assert!(antlr.contains("COMMENT\n    : '/*' ~[*]* '*' ('*' | ~[*/] ~[*]* '*')* '/' -> skip\n    | '//' ~[\\n\\r]* -> skip\n    ;\n"), "{antlr}");
+
+        let ebnf = export(&egt, Notation::Ebnf);
+        assert!(ebnf.contains("List ::= (List Item)?\n"), "{ebnf}");
+    }
+}
@@ -0,0 +1,742 @@
+//! GOLD Meta-Language reader
+//!
+//! Reads the text of a `.grm` file into a `Grammar`. A grammar is a list of definitions,
+//! each ending at a line break. A definition continues on the next line when that line
+//! starts with `|`, `+`, `-`, `=` or `::=`.
+//!
+//! ```text
+//! "Name"         = 'Simple'                   ! property
+//! {String Ch}    = {Printable} - ['']         ! character set
+//! Id             = {Letter}{AlphaNumeric}*    ! terminal
+//! Comment Start  = '{'                        ! lexical group
+//! Comment Block @= { Nesting = All }          ! attributes
+//! <Statements> ::= <Statement> <Statements>   ! rule
+//!                | <Statement>
+//! ```
+//!
+//! `!` starts a line comment and `!* ... *!` a block comment.
+//!
+//! As an extension, yacc style precedence declarations resolve shift-reduce conflicts. Each
+//! line is one level, binding tighter than the lines before it:
+//!
+//! ```text
+//! %left     '+' '-'
+//! %left     '*' '/'
+//! %right    '^'
+//! %nonassoc '<' '='
+//! ```
+//!
+//! Rule bodies may also use EBNF: `( ... | ... )` groups symbols, and `?`, `*` and `+` make the
+//! preceding symbol or group optional or repeated. The compiler replaces them with generated
+//! nonterminals, see `Symbol::is_generated`.
+//!
+//! ```text
+//! <Program>   ::= <Statement>*
+//! <Arguments> ::= <Expression> (',' <Expression>)*
+//! ```
+//!
+//! http://goldparser.org/doc/grammars/index.htm
+
+use crate::engine::GrammarProperties;
+
+use super::{
+    lalr::Associativity,
+    regex::RegExp,
+    sets::{SetExpr, SetItem, SetOp},
+    GrammarError,
+};
+
+/// A character set declaration, `{Name} = ...`
+#[derive(Debug, Clone, PartialEq)]
+pub struct SetDecl {
+    pub name: String,
+    pub expr: SetExpr,
+    pub line: usize,
+}
+
+/// A terminal or group part declaration, `Name = ...`
+#[derive(Debug, Clone, PartialEq)]
+pub struct TerminalDecl {
+    pub name: String,
+    pub regex: RegExp,
+    pub line: usize,
+}
+
+/// Attributes of a terminal or group, `Name @= { Key = Value, ... }`
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttributeDecl {
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    pub line: usize,
+}
+
+/// A symbol in the body of a rule
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuleSymbol {
+    Terminal(String),
+    Nonterminal(String),
+    /// `( a b | c )`, a symbol followed by `?`, `*` or `+` is a group with one handle
+    Group(Vec<Vec<RuleSymbol>>, Repeat),
+}
+
+/// How many times the handles of a `RuleSymbol::Group` are matched
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Repeat {
+    /// Exactly once, for a group without a suffix
+    One,
+    /// `?`
+    Optional,
+    /// `*`
+    Star,
+    /// `+`
+    Plus,
+}
+
+/// `<Head> ::= ... | ...`, one handle per alternative
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleDecl {
+    pub head: String,
+    pub handles: Vec<Vec<RuleSymbol>>,
+    pub line: usize,
+}
+
+/// `%left`, `%right` or `%nonassoc` followed by terminals
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrecedenceDecl {
+    pub associativity: Associativity,
+    pub terminals: Vec<String>,
+    pub line: usize,
+}
+
+/// The definitions of a `.grm` file, in file order
+#[derive(Debug, Default)]
+pub struct Grammar {
+    pub properties: GrammarProperties,
+    pub sets: Vec<SetDecl>,
+    pub terminals: Vec<TerminalDecl>,
+    pub attributes: Vec<AttributeDecl>,
+    pub rules: Vec<RuleDecl>,
+    /// Lowest precedence first
+    pub precedence: Vec<PrecedenceDecl>,
+}
+
+impl Grammar {
+    /// Parses the text of a `.grm` file
+    pub fn parse(source: &str) -> Result<Grammar, GrammarError> {
+        let tokens = Lexer::new(source).tokenize()?;
+        GrmParser { tokens, pos: 0, grammar: Grammar::default() }.parse()
+    }
+}
+
+/// Parses the right side of a terminal definition on its own
+pub(crate) fn parse_regex(source: &str) -> Result<RegExp, GrammarError> {
+    let tokens = Lexer::new(source).tokenize()?;
+    let mut parser = GrmParser { tokens, pos: 0, grammar: Grammar::default() };
+    let regex = parser.regex(false)?;
+    parser.skip_newlines();
+    match parser.peek() {
+        Tok::End => Ok(regex),
+        _ => Err(parser.error("end of expression")),
+    }
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+enum Tok {
+    /// `"Name"`
+    Parameter(String),
+    /// `<Name>`
+    Nonterminal(String),
+    /// A bare word or `'quoted text'`
+    Terminal(String),
+    /// `[chars]`
+    SetLiteral(String),
+    /// `{Name}`
+    SetName(String),
+    NewLine,
+    /// `::=`
+    Define,
+    /// `@=`
+    Attributes,
+    /// `%left`, `%right` or `%nonassoc`
+    Precedence(Associativity),
+    Eq,
+    Pipe,
+    Plus,
+    Minus,
+    Star,
+    Question,
+    LParen,
+    RParen,
+    End,
+}
+
+impl Tok {
+    fn describe(&self) -> String {
+        match self {
+            Tok::Parameter(name) => format!("\"{name}\""),
+            Tok::Nonterminal(name) => format!("<{name}>"),
+            Tok::Terminal(text) => format!("'{text}'"),
+            Tok::SetLiteral(chars) => format!("[{chars}]"),
+            Tok::SetName(name) => format!("{{{name}}}"),
+            Tok::NewLine => "end of line".to_string(),
+            Tok::Define => "'::='".to_string(),
+            Tok::Attributes => "'@='".to_string(),
+            Tok::Precedence(Associativity::Left) => "'%left'".to_string(),
+            Tok::Precedence(Associativity::Right) => "'%right'".to_string(),
+            Tok::Precedence(Associativity::NonAssoc) => "'%nonassoc'".to_string(),
+            Tok::Eq => "'='".to_string(),
+            Tok::Pipe => "'|'".to_string(),
+            Tok::Plus => "'+'".to_string(),
+            Tok::Minus => "'-'".to_string(),
+            Tok::Star => "'*'".to_string(),
+            Tok::Question => "'?'".to_string(),
+            Tok::LParen => "'('".to_string(),
+            Tok::RParen => "')'".to_string(),
+            Tok::End => "end of file".to_string(),
+        }
+    }
+}
+
+/// A token and the line and column it starts at
+#[derive(Debug, Clone)]
+struct Spanned {
+    tok: Tok,
+    line: usize,
+    column: usize,
+}
+
+struct Lexer<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(source: &'a str) -> Self {
+        Lexer { chars: source.chars().peekable(), line: 1, column: 1 }
+    }
+
+    fn error(&self, message: String) -> GrammarError {
+        GrammarError::Syntax { line: self.line, column: self.column, message }
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let ch = self.chars.next()?;
+        if ch == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(ch)
+    }
+
+    /// Reads up to and including `close`, which must appear before the end of the line
+    fn delimited(&mut self, close: char, what: &str) -> Result<String, GrammarError> {
+        let mut text = String::new();
+        loop {
+            match self.next_in_line() {
+                Some(ch) if ch == close => return Ok(text),
+                Some(ch) => text.push(ch),
+                None => return Err(self.error(format!("unterminated {what}"))),
+            }
+        }
+    }
+
+    /// The next character, or `None` at the end of the line
+    fn next_in_line(&mut self) -> Option<char> {
+        match self.chars.peek() {
+            Some('\r') | Some('\n') | None => None,
+            _ => self.bump(),
+        }
+    }
+
+    /// The text of a quoted literal after its opening quote. `''` is a single quote.
+    fn quoted(&mut self) -> Result<String, GrammarError> {
+        let text = self.delimited('\'', "literal")?;
+        Ok(if text.is_empty() { "'".to_string() } else { text })
+    }
+
+    fn block_comment(&mut self) -> Result<(), GrammarError> {
+        let mut depth = 1;
+        while depth > 0 {
+            match self.bump() {
+                Some('!') if self.chars.peek() == Some(&'*') => { self.bump(); depth += 1; },
+                Some('*') if self.chars.peek() == Some(&'!') => { self.bump(); depth -= 1; },
+                Some(_) => {},
+                None => return Err(self.error("unterminated block comment".to_string())),
+            }
+        }
+        Ok(())
+    }
+
+    fn is_terminal_char(ch: char) -> bool {
+        ch.is_alphanumeric() || matches!(ch, '_' | '-' | '.')
+    }
+
+    fn tokenize(mut self) -> Result<Vec<Spanned>, GrammarError> {
+        let mut tokens = Vec::<Spanned>::new();
+        loop {
+            let (line, column) = (self.line, self.column);
+            let Some(ch) = self.bump() else { break; };
+            let tok = match ch {
+                ' ' | '\t' | '\u{0C}' => continue,
+                '\r' | '\n' => {
+                    if matches!(tokens.last(), Some(Spanned { tok: Tok::NewLine, .. }) | None) {
+                        continue;
+                    }
+                    Tok::NewLine
+                },
+                '!' if self.chars.peek() == Some(&'*') => {
+                    self.bump();
+                    self.block_comment()?;
+                    continue;
+                },
+                '!' => {
+                    while !matches!(self.chars.peek(), Some('\r') | Some('\n') | None) {
+                        self.bump();
+                    }
+                    continue;
+                },
+                '"' => Tok::Parameter(self.delimited('"', "property name")?),
+                '<' => Tok::Nonterminal(self.delimited('>', "nonterminal")?),
+                '{' => Tok::SetName(self.delimited('}', "set name")?),
+                '\'' => Tok::Terminal(self.quoted()?),
+                '[' => {
+                    let mut chars = String::new();
+                    loop {
+                        match self.next_in_line() {
+                            Some(']') => break,
+                            Some('\'') => chars.push_str(&self.quoted()?),
+                            Some(ch) => chars.push(ch),
+                            None => return Err(self.error("unterminated set literal".to_string())),
+                        }
+                    }
+                    Tok::SetLiteral(chars)
+                },
+                ':' if self.chars.peek() == Some(&':') => {
+                    self.bump();
+                    if self.bump() != Some('=') {
+                        return Err(self.error("expected '::='".to_string()));
+                    }
+                    Tok::Define
+                },
+                '@' if self.chars.peek() == Some(&'=') => {
+                    self.bump();
+                    Tok::Attributes
+                },
+                '%' => {
+                    let mut word = String::new();
+                    while let Some(&next) = self.chars.peek().filter(|c| c.is_ascii_alphabetic()) {
+                        word.push(next);
+                        self.bump();
+                    }
+                    match word.as_str() {
+                        "left" => Tok::Precedence(Associativity::Left),
+                        "right" => Tok::Precedence(Associativity::Right),
+                        "nonassoc" => Tok::Precedence(Associativity::NonAssoc),
+                        _ => return Err(GrammarError::Syntax { line, column, message: format!("unknown declaration %{word}") }),
+                    }
+                },
+                '=' => Tok::Eq,
+                '|' => Tok::Pipe,
+                '+' => Tok::Plus,
+                '*' => Tok::Star,
+                '?' => Tok::Question,
+                '(' => Tok::LParen,
+                ')' => Tok::RParen,
+                ch if Self::is_terminal_char(ch) => {
+                    let mut text = ch.to_string();
+                    while let Some(&next) = self.chars.peek().filter(|c| Self::is_terminal_char(**c)) {
+                        text.push(next);
+                        self.bump();
+                    }
+                    if text == "-" { Tok::Minus } else { Tok::Terminal(text) }
+                },
+                ch => return Err(GrammarError::Syntax { line, column, message: format!("unexpected character {ch:?}") }),
+            };
+            tokens.push(Spanned { tok, line, column });
+        }
+        tokens.push(Spanned { tok: Tok::End, line: self.line, column: self.column });
+        Ok(tokens)
+    }
+}
+
+
+struct GrmParser {
+    tokens: Vec<Spanned>,
+    pos: usize,
+    grammar: Grammar,
+}
+
+impl GrmParser {
+    fn peek(&self) -> &Tok {
+        &self.tokens[self.pos].tok
+    }
+    fn line(&self) -> usize {
+        self.tokens[self.pos].line
+    }
+    fn next(&mut self) -> Tok {
+        let tok = self.tokens[self.pos].tok.clone();
+        if tok != Tok::End {
+            self.pos += 1;
+        }
+        tok
+    }
+
+    fn error(&self, expected: &str) -> GrammarError {
+        let Spanned { tok, line, column } = &self.tokens[self.pos];
+        GrammarError::Syntax { line: *line, column: *column, message: format!("expected {expected}, found {}", tok.describe()) }
+    }
+
+    fn expect(&mut self, tok: Tok) -> Result<(), GrammarError> {
+        if *self.peek() == tok {
+            self.next();
+            Ok(())
+        } else {
+            Err(self.error(&tok.describe()))
+        }
+    }
+
+    fn skip_newlines(&mut self) {
+        while *self.peek() == Tok::NewLine {
+            self.pos += 1;
+        }
+    }
+
+    /// Returns true and moves to the next token if, after any line breaks, it is one of `toks`
+    fn continues(&mut self, toks: &[Tok]) -> bool {
+        let mut pos = self.pos;
+        while self.tokens[pos].tok == Tok::NewLine {
+            pos += 1;
+        }
+        if toks.contains(&self.tokens[pos].tok) {
+            self.pos = pos + 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// The end of a definition
+    fn end_of_line(&mut self) -> Result<(), GrammarError> {
+        match self.peek() {
+            Tok::NewLine => { self.next(); Ok(()) },
+            Tok::End => Ok(()),
+            _ => Err(self.error("end of line")),
+        }
+    }
+
+    fn parse(mut self) -> Result<Grammar, GrammarError> {
+        loop {
+            self.skip_newlines();
+            let line = self.line();
+            match self.next() {
+                Tok::End => break,
+                Tok::Parameter(name) => self.property(name)?,
+                Tok::SetName(name) => self.set_decl(name, line)?,
+                Tok::Nonterminal(head) => self.rule_decl(head, line)?,
+                Tok::Terminal(word) => self.terminal_decl(word, line)?,
+                Tok::Precedence(associativity) => self.precedence_decl(associativity, line)?,
+                _ => {
+                    self.pos -= 1;
+                    return Err(self.error("a definition"));
+                },
+            }
+        }
+        Ok(self.grammar)
+    }
+
+    /// `"Name" = item item | item ...`. Items are joined with spaces, alternatives with line breaks.
+    fn property(&mut self, name: String) -> Result<(), GrammarError> {
+        self.skip_newlines();
+        self.expect(Tok::Eq)?;
+        let mut lines = Vec::new();
+        loop {
+            let mut items = Vec::new();
+            loop {
+                match self.peek().clone() {
+                    Tok::Parameter(text) | Tok::Terminal(text) => items.push(text),
+                    Tok::Nonterminal(text) => items.push(format!("<{text}>")),
+                    Tok::SetName(text) => items.push(format!("{{{text}}}")),
+                    Tok::SetLiteral(text) => items.push(format!("[{text}]")),
+                    _ => break,
+                }
+                self.next();
+            }
+            if items.is_empty() {
+                return Err(self.error("a property value"));
+            }
+            lines.push(items.join(" "));
+            if !self.continues(&[Tok::Pipe]) {
+                break;
+            }
+        }
+        self.grammar.properties.set(&name, &lines.join("\n"));
+        self.end_of_line()
+    }
+
+    /// `{Name} = {Set} + [chars] - ...`
+    fn set_decl(&mut self, name: String, line: usize) -> Result<(), GrammarError> {
+        self.skip_newlines();
+        self.expect(Tok::Eq)?;
+        let mut expr = vec![(SetOp::Add, self.set_item()?)];
+        loop {
+            if self.continues(&[Tok::Plus]) {
+                expr.push((SetOp::Add, self.set_item()?));
+            } else if self.continues(&[Tok::Minus]) {
+                expr.push((SetOp::Sub, self.set_item()?));
+            } else {
+                break;
+            }
+        }
+        self.grammar.sets.push(SetDecl { name, expr, line });
+        self.end_of_line()
+    }
+
+    fn set_item(&mut self) -> Result<SetItem, GrammarError> {
+        match self.peek().clone() {
+            Tok::SetName(name) => { self.next(); Ok(SetItem::Name(name)) },
+            Tok::SetLiteral(chars) => { self.next(); Ok(SetItem::Literal(chars)) },
+            _ => Err(self.error("a set name or set literal")),
+        }
+    }
+
+    /// `Name = regex` or `Name @= { ... }`. Names may span several words, e.g. `Comment Start`.
+    fn terminal_decl(&mut self, first: String, line: usize) -> Result<(), GrammarError> {
+        let mut words = vec![first];
+        while let Tok::Terminal(word) = self.peek().clone() {
+            words.push(word);
+            self.next();
+        }
+        let name = words.join(" ");
+        self.skip_newlines();
+        match self.next() {
+            Tok::Eq => {
+                let regex = self.regex(false)?;
+                self.grammar.terminals.push(TerminalDecl { name, regex, line });
+            },
+            Tok::Attributes => {
+                let attributes = self.attributes()?;
+                self.grammar.attributes.push(AttributeDecl { name, attributes, line });
+            },
+            _ => {
+                self.pos -= 1;
+                return Err(self.error("'=' or '@='"));
+            },
+        }
+        self.end_of_line()
+    }
+
+    /// `{ Key = Value, ... }`, read by the lexer as a set name
+    fn attributes(&mut self) -> Result<Vec<(String, String)>, GrammarError> {
+        let Tok::SetName(list) = self.peek().clone() else {
+            return Err(self.error("an attribute list"));
+        };
+        let mut attributes = Vec::new();
+        for attr in list.split(',').filter(|a| !a.trim().is_empty()) {
+            match attr.split_once('=') {
+                Some((key, value)) => attributes.push((key.trim().to_string(), value.trim().to_string())),
+                None => return Err(self.error("'Name = Value' attributes")),
+            }
+        }
+        self.next();
+        Ok(attributes)
+    }
+
+    /// Alternatives separated by `|`. Inside parentheses line breaks are ignored.
+    fn regex(&mut self, nested: bool) -> Result<RegExp, GrammarError> {
+        let mut alternatives = vec![self.regex_sequence(nested)?];
+        while self.continues(&[Tok::Pipe]) {
+            alternatives.push(self.regex_sequence(nested)?);
+        }
+        Ok(if alternatives.len() == 1 { alternatives.pop().unwrap() } else { RegExp::Alternation(alternatives) })
+    }
+
+    fn regex_sequence(&mut self, nested: bool) -> Result<RegExp, GrammarError> {
+        let mut items = Vec::new();
+        loop {
+            if nested {
+                self.skip_newlines();
+            }
+            let item = match self.peek().clone() {
+                Tok::Terminal(text) => RegExp::Literal(text),
+                Tok::Minus => RegExp::Literal("-".to_string()),
+                Tok::SetName(name) => RegExp::Set(SetItem::Name(name)),
+                Tok::SetLiteral(chars) => RegExp::Set(SetItem::Literal(chars)),
+                Tok::LParen => {
+                    self.next();
+                    let group = self.regex(true)?;
+                    self.skip_newlines();
+                    if *self.peek() != Tok::RParen {
+                        return Err(self.error("')'"));
+                    }
+                    group
+                },
+                _ => break,
+            };
+            self.next();
+            items.push(match self.peek() {
+                Tok::Star => { self.next(); RegExp::Star(Box::new(item)) },
+                Tok::Plus => { self.next(); RegExp::Plus(Box::new(item)) },
+                Tok::Question => { self.next(); RegExp::Optional(Box::new(item)) },
+                _ => item,
+            });
+        }
+        match items.len() {
+            0 => Err(self.error("a regular expression")),
+            1 => Ok(items.pop().unwrap()),
+            _ => Ok(RegExp::Sequence(items)),
+        }
+    }
+
+    /// `<Head> ::= handle | handle ...`
+    fn rule_decl(&mut self, head: String, line: usize) -> Result<(), GrammarError> {
+        self.skip_newlines();
+        self.expect(Tok::Define)?;
+        let mut handles = vec![self.handle(false)?];
+        while self.continues(&[Tok::Pipe]) {
+            handles.push(self.handle(false)?);
+        }
+        self.grammar.rules.push(RuleDecl { head, handles, line });
+        self.end_of_line()
+    }
+
+    /// `%left 'a' b ...`
+    fn precedence_decl(&mut self, associativity: Associativity, line: usize) -> Result<(), GrammarError> {
+        let mut terminals = Vec::new();
+        loop {
+            match self.peek().clone() {
+                Tok::Terminal(text) => terminals.push(text),
+                Tok::Minus => terminals.push("-".to_string()),
+                _ if terminals.is_empty() => return Err(self.error("a terminal")),
+                _ => break,
+            }
+            self.next();
+        }
+        self.grammar.precedence.push(PrecedenceDecl { associativity, terminals, line });
+        self.end_of_line()
+    }
+
+    /// The symbols of one alternative. Inside parentheses line breaks are ignored.
+    fn handle(&mut self, nested: bool) -> Result<Vec<RuleSymbol>, GrammarError> {
+        let mut symbols = Vec::new();
+        loop {
+            if nested {
+                self.skip_newlines();
+            }
+            let symbol = match self.peek().clone() {
+                Tok::Terminal(text) => RuleSymbol::Terminal(text),
+                Tok::Minus => RuleSymbol::Terminal("-".to_string()),
+                Tok::Nonterminal(name) => RuleSymbol::Nonterminal(name),
+                Tok::LParen => {
+                    self.next();
+                    let mut handles = vec![self.handle(true)?];
+                    while self.continues(&[Tok::Pipe]) {
+                        handles.push(self.handle(true)?);
+                    }
+                    self.skip_newlines();
+                    if *self.peek() != Tok::RParen {
+                        return Err(self.error("')'"));
+                    }
+                    RuleSymbol::Group(handles, Repeat::One)
+                },
+                _ => return Ok(symbols),
+            };
+            self.next();
+            let repeat = match self.peek() {
+                Tok::Question => Repeat::Optional,
+                Tok::Star => Repeat::Star,
+                Tok::Plus => Repeat::Plus,
+                _ => {
+                    symbols.push(symbol);
+                    continue;
+                },
+            };
+            self.next();
+            symbols.push(match symbol {
+                RuleSymbol::Group(handles, _) => RuleSymbol::Group(handles, repeat),
+                symbol => RuleSymbol::Group(vec![vec![symbol]], repeat),
+            });
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::grammar::{GrammarError, lalr::Associativity, regex::RegExp, sets::{SetItem, SetOp}};
+
+    use super::{Grammar, RuleSymbol};
+
+    #[test]
+    fn simple() {
+        let grammar = Grammar::parse(&std::fs::read_to_string(crate::test::GP_SIMPLE_GRM).unwrap()).unwrap();
+        assert_eq!(grammar.properties.name(), Some("Simple"));
+        assert_eq!(grammar.properties.start_symbol(), Some("Statements"));
+        assert_eq!(grammar.properties.case_sensitive(), Some(false));
+        assert_eq!(grammar.sets[0].expr, vec![
+            (SetOp::Add, SetItem::Name("Printable".to_string())),
+            (SetOp::Sub, SetItem::Literal("'".to_string())),
+        ]);
+        assert_eq!(grammar.terminals.iter().map(|t| t.name.as_str()).collect::<Vec<_>>(),
+            vec!["Id", "StringLiteral", "NumberLiteral"]);
+        // NumberLiteral = {Digit}+('.'{Digit}+)?
+        let digits = RegExp::Plus(Box::new(RegExp::Set(SetItem::Name("Digit".to_string()))));
+        assert_eq!(grammar.terminals[2].regex, RegExp::Sequence(vec![
+            digits.clone(),
+            RegExp::Optional(Box::new(RegExp::Sequence(vec![RegExp::Literal(".".to_string()), digits]))),
+        ]));
+        assert_eq!(grammar.rules[0].handles, vec![
+            vec![RuleSymbol::Nonterminal("Statement".to_string()), RuleSymbol::Nonterminal("Statements".to_string())],
+            vec![RuleSymbol::Nonterminal("Statement".to_string())],
+        ]);
+        assert_eq!(grammar.rules[1].handles[1], vec![
+            RuleSymbol::Terminal("display".to_string()), RuleSymbol::Nonterminal("Expression".to_string()),
+            RuleSymbol::Terminal("read".to_string()), RuleSymbol::Terminal("ID".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn precedence() {
+        let grammar = Grammar::parse("%left '+' -\n%nonassoc '<'\n<E> ::= <E> '+' <E>\n").unwrap();
+        assert_eq!(grammar.precedence.len(), 2);
+        assert_eq!(grammar.precedence[0].associativity, Associativity::Left);
+        assert_eq!(grammar.precedence[0].terminals, vec!["+", "-"]);
+        assert_eq!((grammar.precedence[1].associativity, grammar.precedence[1].line), (Associativity::NonAssoc, 2));
+        assert!(matches!(Grammar::parse("%lft '+'\n"), Err(GrammarError::Syntax { line: 1, column: 1, .. })));
+        assert!(matches!(Grammar::parse("%right\n"), Err(GrammarError::Syntax { .. })));
+    }
+
+    #[test]
+    fn groups_and_attributes() {
+        let grammar = Grammar::parse(concat!(
+            "!* a block\n   comment *!\n",
+            "\"About\" = 'first line'\n   | 'second' 'line'\n",
+            "Comment Line = '//'\n",
+            "Comment Block @= { Nesting = All, Advance = Token }\n",
+            "<A> ::= \n  | <A> x\n",
+        )).unwrap();
+        assert_eq!(grammar.properties.about(), Some("first line\nsecond line"));
+        assert_eq!(grammar.terminals[0].name, "Comment Line");
+        assert_eq!(grammar.terminals[0].regex, RegExp::Literal("//".to_string()));
+        assert_eq!(grammar.attributes[0].name, "Comment Block");
+        assert_eq!(grammar.attributes[0].attributes, vec![
+            ("Nesting".to_string(), "All".to_string()), ("Advance".to_string(), "Token".to_string()),
+        ]);
+        assert_eq!(grammar.rules[0].handles, vec![
+            vec![], vec![RuleSymbol::Nonterminal("A".to_string()), RuleSymbol::Terminal("x".to_string())],
+        ]);
+    }
+
+    #[test]
+    fn syntax_errors() {
+        let err = Grammar::parse("\"Name\" = 'x'\n<A> ::= 'unterminated\n").unwrap_err();
+        assert!(matches!(err, GrammarError::Syntax { line: 2, .. }), "{err}");
+        let err = Grammar::parse("Id = {Letter}+ )\n").unwrap_err();
+        assert_eq!(err.to_string(), "line 1, column 16: expected end of line, found ')'");
+        let err = Grammar::parse("<A> ::= (<B> | c\n").unwrap_err();
+        assert_eq!(err.to_string(), "line 2, column 1: expected ')', found end of file");
+    }
+}
@@ -0,0 +1,613 @@
+//! LALR(1) state construction
+//!
+//! Builds the canonical collection of LR(0) item sets for the grammar augmented with
+//! `S' ::= <Start>`, then computes LALR(1) lookaheads by the spontaneous generation and
+//! propagation method (Aho, Sethi, Ullman; algorithm 4.13). The augmented rule is not added
+//! to the production table: its completed item becomes the `Accept` action on `EOF`.
+
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::iter;
+use std::fmt::Display;
+
+use super::analysis::{start_symbol, FirstSets};
+
+use crate::engine::{
+    states::ActionType,
+    tables::{LALRStateTable, ProductionTable, Table},
+    EnhancedGrammarTable, LALRAction, LALRState, ProductionRule, Symbol, SymbolTable, SymbolType,
+};
+
+/// Lookahead placeholder used while finding which lookaheads propagate
+const PROPAGATE: usize = usize::MAX;
+
+/// How operators of the same precedence group, as declared by yacc's `%left`, `%right`
+/// and `%nonassoc`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Associativity {
+    /// `a - b - c` is `(a - b) - c`: reduce
+    Left,
+    /// `a ^ b ^ c` is `a ^ (b ^ c)`: shift
+    Right,
+    /// `a < b < c` is a syntax error
+    NonAssoc,
+}
+
+/// Several actions for the same lookahead symbol in one LALR state
+#[derive(Debug, Clone)]
+pub struct Conflict {
+    pub state: usize,
+    pub symbol: Symbol,
+    pub actions: Vec<LALRAction>,
+    /// The rules being reduced, then the rules which shift `symbol`
+    pub rules: Vec<ProductionRule>,
+    /// The shortest sequence of symbols leading from the initial state to `state`
+    pub prefix: Vec<Symbol>,
+    /// `prefix` with each nonterminal replaced by the shortest text it derives
+    pub example: Vec<Symbol>,
+}
+
+impl Conflict {
+    /// `true` for shift-reduce conflicts, `false` for reduce-reduce conflicts
+    pub fn is_shift_reduce(&self) -> bool {
+        self.actions.iter().any(|a| a.action == ActionType::Shift)
+    }
+
+    /// Describes the conflict over several lines: the competing rules and an input reaching it
+    pub fn report(&self) -> String {
+        let handle = |symbols: &[Symbol]| symbols.iter().map(Symbol::as_handle).collect::<Vec<_>>().join(" ");
+        let reduces = self.actions.iter().filter(|a| a.action == ActionType::Reduce).count();
+        let mut report = format!("{self}\n");
+        for (index, rule) in self.rules.iter().enumerate() {
+            let action = if index < reduces { "Reduce" } else { "Shift" };
+            let body = rule.symbols.iter().cloned().collect::<Vec<_>>();
+            report += &format!("  {action:6} {} ::= {}\n", rule.head.as_handle(), handle(&body));
+        }
+        report += &format!("  Prefix:  {}\n", handle(&self.prefix));
+        report += &format!("  Example: {} | {}\n", handle(&self.example), self.symbol.as_handle());
+        report
+    }
+}
+
+impl Display for Conflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = if self.is_shift_reduce() { "shift-reduce" } else { "reduce-reduce" };
+        write!(f, "LALR state {}: {kind} conflict on {}", self.state, self.symbol.as_handle())
+    }
+}
+
+/// An LR(0) item: a rule and the position of the dot in its body
+type LRItem = (usize, usize);
+
+struct Grammar<'a> {
+    symbols: &'a SymbolTable,
+    /// (head, body) for each production, followed by the augmented start rule
+    rules: Vec<(usize, Vec<usize>)>,
+    by_head: HashMap<usize, Vec<usize>>,
+    sets: FirstSets,
+}
+
+impl<'a> Grammar<'a> {
+    fn new(symbols: &'a SymbolTable, start: usize, productions: &ProductionTable) -> Self {
+        let mut rules = productions.iter()
+            .map(|r| (r.head.index, r.symbols.iter().map(|s| s.index).collect::<Vec<_>>()))
+            .collect::<Vec<_>>();
+        rules.push((symbols.len(), vec![start]));
+        let mut by_head = HashMap::<usize, Vec<usize>>::new();
+        for (index, (head, _)) in rules.iter().enumerate() {
+            by_head.entry(*head).or_default().push(index);
+        }
+        // the augmented start symbol is numbered after the last symbol
+        let terminal = |s: usize| s < symbols.len() && symbols[s].kind != SymbolType::NonTerminal;
+        let sets = FirstSets::new(symbols.len() + 1, terminal, rules.iter().map(|(head, body)| (*head, body.as_slice())));
+        Grammar { symbols, rules, by_head, sets }
+    }
+
+    fn is_nonterminal(&self, symbol: usize) -> bool {
+        symbol == self.symbols.len() || self.symbols[symbol].kind == SymbolType::NonTerminal
+    }
+
+    fn next_symbol(&self, (rule, dot): LRItem) -> Option<usize> {
+        self.rules[rule].1.get(dot).copied()
+    }
+
+    /// The shortest string of terminals each symbol derives, if it derives any
+    fn shortest(&self) -> Vec<Option<Vec<usize>>> {
+        let mut shortest = (0..=self.symbols.len())
+            .map(|s| (!self.is_nonterminal(s)).then(|| vec![s]))
+            .collect::<Vec<_>>();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (head, body) in &self.rules {
+                let Some(text) = body.iter().map(|s| shortest[*s].clone()).collect::<Option<Vec<_>>>() else { continue; };
+                let text = text.concat();
+                if shortest[*head].as_ref().is_none_or(|t| text.len() < t.len()) {
+                    shortest[*head] = Some(text);
+                    changed = true;
+                }
+            }
+        }
+        shortest
+    }
+
+    fn closure(&self, kernel: &[LRItem]) -> Vec<LRItem> {
+        let mut items = kernel.to_vec();
+        let mut seen = kernel.iter().copied().collect::<BTreeSet<_>>();
+        let mut index = 0;
+        while index < items.len() {
+            if let Some(next) = self.next_symbol(items[index]).filter(|s| self.is_nonterminal(*s)) {
+                for rule in self.by_head.get(&next).into_iter().flatten() {
+                    if seen.insert((*rule, 0)) {
+                        items.push((*rule, 0));
+                    }
+                }
+            }
+            index += 1;
+        }
+        items
+    }
+
+    /// LR(1) closure of items paired with lookaheads
+    fn closure1(&self, kernel: Vec<(LRItem, usize)>) -> BTreeSet<(LRItem, usize)> {
+        let mut seen = BTreeSet::new();
+        let mut work = kernel;
+        while let Some(entry @ ((rule, dot), lookahead)) = work.pop() {
+            if !seen.insert(entry) {
+                continue;
+            }
+            let Some(next) = self.next_symbol((rule, dot)).filter(|s| self.is_nonterminal(*s)) else { continue; };
+            let (mut first, nullable) = self.sets.first_of(&self.rules[rule].1[dot + 1..]);
+            if nullable {
+                first.insert(lookahead);
+            }
+            for rule in self.by_head.get(&next).into_iter().flatten() {
+                work.extend(first.iter().map(|a| ((*rule, 0), *a)));
+            }
+        }
+        seen
+    }
+}
+
+/// An item in the kernel of an `ItemSet`, with its LALR(1) lookaheads
+#[derive(Debug, Clone)]
+pub struct Item {
+    /// Index of the production, `None` for the augmented rule `S' ::= <Start>`
+    pub rule: Option<usize>,
+    /// Number of handle symbols before the dot
+    pub position: usize,
+    pub lookaheads: Vec<Symbol>,
+}
+
+/// The kernel of one LR(0) item set, which becomes the LALR state with the same index
+#[derive(Debug, Clone)]
+pub struct ItemSet {
+    pub index: usize,
+    pub items: Vec<Item>,
+    /// The item set reached on each symbol
+    pub transitions: Vec<(Symbol, usize)>,
+}
+
+/// LR(0) item sets with the lookaheads of their kernel items
+struct Automaton {
+    kernels: Vec<Vec<LRItem>>,
+    gotos: Vec<BTreeMap<usize, usize>>,
+    lookaheads: Vec<Vec<BTreeSet<usize>>>,
+}
+
+impl Automaton {
+    fn new(grammar: &Grammar, eof: usize) -> Self {
+        let accept_rule = grammar.rules.len() - 1;
+        let mut kernels = vec![vec![(accept_rule, 0)]];
+        let mut indices = HashMap::from([(kernels[0].clone(), 0)]);
+        let mut gotos = Vec::<BTreeMap<usize, usize>>::new();
+        let mut state = 0;
+        while state < kernels.len() {
+            let mut next = BTreeMap::<usize, Vec<LRItem>>::new();
+            for (rule, dot) in grammar.closure(&kernels[state]) {
+                if let Some(symbol) = grammar.next_symbol((rule, dot)) {
+                    next.entry(symbol).or_default().push((rule, dot + 1));
+                }
+            }
+            let mut goto = BTreeMap::new();
+            for (symbol, mut kernel) in next {
+                kernel.sort_unstable();
+                let target = *indices.entry(kernel.clone()).or_insert_with(|| {
+                    kernels.push(kernel);
+                    kernels.len() - 1
+                });
+                goto.insert(symbol, target);
+            }
+            gotos.push(goto);
+            state += 1;
+        }
+
+        let mut lookaheads = kernels.iter().map(|k| vec![BTreeSet::new(); k.len()]).collect::<Vec<_>>();
+        let mut propagate = Vec::<((usize, usize), (usize, usize))>::new();
+        lookaheads[0][0].insert(eof);
+        for (state, kernel) in kernels.iter().enumerate() {
+            for (index, item) in kernel.iter().enumerate() {
+                for ((rule, dot), lookahead) in grammar.closure1(vec![(*item, PROPAGATE)]) {
+                    let Some(symbol) = grammar.next_symbol((rule, dot)) else { continue; };
+                    let target = gotos[state][&symbol];
+                    let position = kernels[target].binary_search(&(rule, dot + 1)).unwrap();
+                    if lookahead == PROPAGATE {
+                        propagate.push(((state, index), (target, position)));
+                    } else {
+                        lookaheads[target][position].insert(lookahead);
+                    }
+                }
+            }
+        }
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for ((from, i), (to, j)) in &propagate {
+                let missing = lookaheads[*from][*i].difference(&lookaheads[*to][*j]).copied().collect::<Vec<_>>();
+                if !missing.is_empty() {
+                    lookaheads[*to][*j].extend(missing);
+                    changed = true;
+                }
+            }
+        }
+        Automaton { kernels, gotos, lookaheads }
+    }
+
+    /// The shortest sequence of symbols leading to each state
+    fn prefixes(&self) -> Vec<Vec<usize>> {
+        let mut prefixes = vec![None; self.kernels.len()];
+        prefixes[0] = Some(Vec::new());
+        let mut queue = VecDeque::from([0]);
+        while let Some(state) = queue.pop_front() {
+            for (symbol, target) in &self.gotos[state] {
+                if prefixes[*target].is_none() {
+                    let mut prefix = prefixes[state].clone().unwrap_or_default();
+                    prefix.push(*symbol);
+                    prefixes[*target] = Some(prefix);
+                    queue.push_back(*target);
+                }
+            }
+        }
+        prefixes.into_iter().map(Option::unwrap_or_default).collect()
+    }
+}
+
+/// Builds the LALR(1) states of a grammar given as a `ProductionTable`
+///
+/// Symbols are identified by their `index`. `new` collects them from the productions and
+/// adds `EOF` as symbol 0, like GOLD Builder does, unless a production already uses that
+/// index. Use `with_symbols` to number the actions from an existing `SymbolTable` instead.
+///
+/// ```
+/// use goldparser::engine::{tables::{ProductionTable, Table}, ProductionRule, Symbol, SymbolTable, SymbolType};
+/// use goldparser::grammar::LALRBuilder;
+///
+/// // <List> ::= <List> item | item
+/// let list = Symbol::new(2, "List".to_string(), SymbolType::NonTerminal);
+/// let item = Symbol::new(1, "item".to_string(), SymbolType::Terminal);
+/// let mut rules = ProductionTable::new();
+/// rules.push(ProductionRule::new(0, list.clone(), SymbolTable::from(vec![list.clone(), item.clone()])));
+/// rules.push(ProductionRule::new(1, list.clone(), SymbolTable::from(vec![item])));
+///
+/// let builder = LALRBuilder::new(&list, &rules);
+/// assert_eq!(builder.symbols()[0].name, "EOF");
+/// assert_eq!(builder.build().unwrap().len(), 4);
+/// ```
+pub struct LALRBuilder<'a> {
+    symbols: SymbolTable,
+    start: usize,
+    productions: &'a ProductionTable,
+    /// Level and associativity of terminals, by symbol index
+    precedence: HashMap<usize, (usize, Associativity)>,
+}
+
+impl<'a> LALRBuilder<'a> {
+    pub fn new(start: &Symbol, productions: &'a ProductionTable) -> Self {
+        let mut found = BTreeMap::<usize, Symbol>::new();
+        let used = productions.iter().flat_map(|r| iter::once(&r.head).chain(r.symbols.iter()));
+        for symbol in iter::once(start).chain(used) {
+            found.entry(symbol.index).or_insert_with(|| symbol.clone());
+        }
+        if !found.values().any(|s| s.kind == SymbolType::EndOfFile) {
+            let index = match found.last_key_value() {
+                Some((last, _)) if found.contains_key(&0) => last + 1,
+                _ => 0,
+            };
+            found.insert(index, Symbol::new(index, "EOF".to_string(), SymbolType::EndOfFile));
+        }
+        let len = found.last_key_value().map_or(0, |(last, _)| last + 1);
+        let mut symbols = SymbolTable::with_capacity(len);
+        for index in 0..len {
+            symbols.push(found.remove(&index).unwrap_or_else(|| Symbol::new(index, String::new(), SymbolType::Undefined)));
+        }
+        LALRBuilder { symbols, start: start.index, productions, precedence: HashMap::new() }
+    }
+    /// Uses `symbols`, which must hold an `EOF` symbol, for the symbols of the actions
+    pub fn with_symbols(symbols: &SymbolTable, start: &Symbol, productions: &'a ProductionTable) -> Self {
+        LALRBuilder { symbols: symbols.clone(), start: start.index, productions, precedence: HashMap::new() }
+    }
+    /// The symbols the actions refer to
+    pub fn symbols(&self) -> &SymbolTable {
+        &self.symbols
+    }
+
+    /// Declares a precedence level, like a yacc `%left` line. Each call binds tighter than the
+    /// calls before it. A rule has the precedence of its last terminal which has one.
+    ///
+    /// A shift-reduce conflict is settled when both the lookahead and the rule have a precedence:
+    /// the higher one wins and, at the same level, the associativity decides.
+    pub fn precedence(&mut self, associativity: Associativity, terminals: &[Symbol]) -> &mut Self {
+        let level = self.precedence.values().map(|(level, _)| level + 1).max().unwrap_or_default();
+        for terminal in terminals {
+            self.precedence.insert(terminal.index, (level, associativity));
+        }
+        self
+    }
+
+    /// The actions kept when precedence settles a conflict between a shift and a reduction
+    fn resolve(&self, symbol: usize, candidates: &[LALRAction]) -> Option<Vec<LALRAction>> {
+        let [shift, reduce] = candidates else { return None; };
+        if shift.action != ActionType::Shift || reduce.action != ActionType::Reduce {
+            return None;
+        }
+        let (level, associativity) = self.precedence.get(&symbol)?;
+        let (rule_level, _) = self.productions[reduce.target_idx].symbols.iter().rev()
+            .filter(|s| s.kind != SymbolType::NonTerminal)
+            .find_map(|s| self.precedence.get(&s.index))?;
+        Some(match (level.cmp(rule_level), associativity) {
+            (Ordering::Greater, _) | (Ordering::Equal, Associativity::Right) => vec![shift.clone()],
+            (Ordering::Less, _) | (Ordering::Equal, Associativity::Left) => vec![reduce.clone()],
+            (Ordering::Equal, Associativity::NonAssoc) => Vec::new(),
+        })
+    }
+
+    fn eof(&self) -> usize {
+        self.symbols.get_by_type(SymbolType::EndOfFile).map(|s| s.index).unwrap_or_default()
+    }
+
+    /// The canonical LR(0) item sets with LALR(1) lookaheads
+    pub fn item_sets(&self) -> Vec<ItemSet> {
+        let grammar = Grammar::new(&self.symbols, self.start, self.productions);
+        let automaton = Automaton::new(&grammar, self.eof());
+        automaton.kernels.iter().zip(&automaton.lookaheads).zip(&automaton.gotos).enumerate()
+            .map(|(index, ((kernel, lookaheads), gotos))| ItemSet {
+                index,
+                items: kernel.iter().zip(lookaheads).map(|((rule, dot), la)| Item {
+                    rule: Some(*rule).filter(|r| *r < self.productions.len()),
+                    position: *dot,
+                    lookaheads: la.iter().map(|a| self.symbols[*a].clone()).collect(),
+                }).collect(),
+                transitions: gotos.iter().map(|(symbol, target)| (self.symbols[*symbol].clone(), *target)).collect(),
+            })
+            .collect()
+    }
+
+    /// Builds the state table, or returns every state and symbol with more than one action
+    pub fn build(&self) -> Result<LALRStateTable, Vec<Conflict>> {
+        let grammar = Grammar::new(&self.symbols, self.start, self.productions);
+        let automaton = Automaton::new(&grammar, self.eof());
+        let accept_rule = grammar.rules.len() - 1;
+        let symbols = &self.symbols;
+        let mut states = LALRStateTable::new();
+        let mut conflicts = Vec::new();
+        for (index, kernel) in automaton.kernels.iter().enumerate() {
+            let mut actions = BTreeMap::<usize, Vec<LALRAction>>::new();
+            for (symbol, target) in &automaton.gotos[index] {
+                let kind = if grammar.is_nonterminal(*symbol) { ActionType::Goto } else { ActionType::Shift };
+                actions.entry(*symbol).or_default().push(LALRAction::new(symbols[*symbol].clone(), kind, *target));
+            }
+            let items = kernel.iter().zip(&automaton.lookaheads[index])
+                .flat_map(|(item, la)| la.iter().map(|a| (*item, *a)))
+                .collect();
+            for ((rule, dot), lookahead) in grammar.closure1(items) {
+                if dot < grammar.rules[rule].1.len() {
+                    continue;
+                }
+                let action = if rule == accept_rule {
+                    LALRAction::new(symbols[lookahead].clone(), ActionType::Accept, 0)
+                } else {
+                    LALRAction::new(symbols[lookahead].clone(), ActionType::Reduce, rule)
+                };
+                actions.entry(lookahead).or_default().push(action);
+            }
+            let mut state = LALRState::new(index, Vec::with_capacity(actions.len()));
+            for (symbol, mut candidates) in actions {
+                if let Some(kept) = self.resolve(symbol, &candidates) {
+                    candidates = kept;
+                }
+                if candidates.len() > 1 {
+                    conflicts.push((index, symbol, candidates.clone()));
+                }
+                if !candidates.is_empty() {
+                    state.actions.push(candidates.swap_remove(0));
+                }
+            }
+            states.add(state);
+        }
+        if conflicts.is_empty() {
+            return Ok(states);
+        }
+
+        let prefixes = automaton.prefixes();
+        let shortest = grammar.shortest();
+        Err(conflicts.into_iter().map(|(state, symbol, actions)| {
+            let mut rules = actions.iter()
+                .filter(|a| a.action == ActionType::Reduce)
+                .map(|a| a.target_idx)
+                .collect::<Vec<_>>();
+            let mut shifted = grammar.closure(&automaton.kernels[state]).into_iter()
+                .filter(|item| item.0 != accept_rule && grammar.next_symbol(*item) == Some(symbol))
+                .map(|(rule, _)| rule)
+                .collect::<Vec<_>>();
+            shifted.sort_unstable();
+            shifted.dedup();
+            rules.extend(shifted);
+            let prefix = &prefixes[state];
+            let example = prefix.iter()
+                .flat_map(|s| shortest[*s].clone().unwrap_or_else(|| vec![*s]))
+                .map(|s| symbols[s].clone())
+                .collect();
+            Conflict {
+                state, symbol: symbols[symbol].clone(), actions,
+                rules: rules.into_iter().map(|r| self.productions[r].clone()).collect(),
+                prefix: prefix.iter().map(|s| symbols[*s].clone()).collect(),
+                example,
+            }
+        }).collect())
+    }
+}
+
+/// Finds the conflicts of a loaded grammar. A state table holds one action per symbol, so the
+/// states are rebuilt from the productions, starting from `analysis::start_symbol`.
+pub fn conflicts(egt: &EnhancedGrammarTable) -> Vec<Conflict> {
+    let Some(start) = start_symbol(egt) else { return Vec::new(); };
+    LALRBuilder::with_symbols(&egt.symbols, start, &egt.productions).build().err().unwrap_or_default()
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::engine::{
+        states::ActionType, tables::{ProductionTable, Table}, Builder, ProductionRule, Symbol, SymbolTable, SymbolType,
+    };
+
+    use super::{conflicts, Associativity, LALRBuilder};
+
+    /// `<E> ::= <E> '+' <T> | <T>`, `<T> ::= id | '(' <E> ')'`
+    fn expressions() -> (SymbolTable, ProductionTable) {
+        let names = [("EOF", SymbolType::EndOfFile), ("Error", SymbolType::Error), ("+", SymbolType::Terminal),
+            ("(", SymbolType::Terminal), (")", SymbolType::Terminal), ("id", SymbolType::Terminal),
+            ("E", SymbolType::NonTerminal), ("T", SymbolType::NonTerminal)];
+        let symbols = SymbolTable::from(names.iter().enumerate()
+            .map(|(i, (n, k))| Symbol::new(i, n.to_string(), *k)).collect::<Vec<_>>());
+        let rule = |index, head: usize, body: &[usize]| ProductionRule::new(index, symbols[head].clone(),
+            SymbolTable::from(body.iter().map(|s| symbols[*s].clone()).collect::<Vec<_>>()));
+        let mut productions = ProductionTable::new();
+        productions.resize(4);
+        productions.add(rule(0, 6, &[6, 2, 7]));
+        productions.add(rule(1, 6, &[7]));
+        productions.add(rule(2, 7, &[5]));
+        productions.add(rule(3, 7, &[3, 6, 4]));
+        (symbols, productions)
+    }
+
+    #[test]
+    fn expression_states() {
+        let (symbols, productions) = expressions();
+        let states = LALRBuilder::with_symbols(&symbols, &symbols[6], &productions).build().unwrap();
+        // 0: start, E, T, id, (, E+, (E, E+T, (E)
+        assert_eq!(states.len(), 9);
+        let accept = states.iter().flat_map(|s| &s.actions).filter(|a| a.action == ActionType::Accept).count();
+        assert_eq!(accept, 1);
+        // after `id`, reduce T ::= id on + ) and EOF
+        let id = states[0].find_action(&symbols[5]).unwrap().target_idx;
+        let mut reduce = states[id].actions.iter().map(|a| (a.symbol.name.as_str(), a.action, a.target_idx)).collect::<Vec<_>>();
+        reduce.sort_by_key(|a| a.0);
+        assert_eq!(reduce, vec![(")", ActionType::Reduce, 2), ("+", ActionType::Reduce, 2), ("EOF", ActionType::Reduce, 2)]);
+    }
+
+    #[test]
+    fn item_sets() {
+        let (symbols, productions) = expressions();
+        let sets = LALRBuilder::with_symbols(&symbols, &symbols[6], &productions).item_sets();
+        assert_eq!(sets.len(), 9);
+        let start = &sets[0].items[0];
+        assert_eq!((start.rule, start.position), (None, 0));
+        assert_eq!(start.lookaheads.iter().map(|s| s.name.as_str()).collect::<Vec<_>>(), vec!["EOF"]);
+        // E ::= E . '+' T and the augmented S' ::= E . share a state
+        let (_, after_e) = sets[0].transitions.iter().find(|(s, _)| s.name == "E").unwrap();
+        let rules = sets[*after_e].items.iter().map(|i| (i.rule, i.position)).collect::<Vec<_>>();
+        assert_eq!(rules, vec![(Some(0), 1), (None, 1)]);
+        let lookaheads = &sets[*after_e].items[0].lookaheads;
+        assert_eq!(lookaheads.iter().map(|s| s.name.as_str()).collect::<Vec<_>>(), vec!["EOF", "+"]);
+    }
+
+    #[test]
+    fn derived_symbols() {
+        let (symbols, productions) = expressions();
+        // EOF is not used by any rule, so it is added at index 0
+        let builder = LALRBuilder::new(&symbols[6], &productions);
+        assert_eq!(builder.symbols().len(), 8);
+        assert_eq!(builder.symbols()[0].kind, SymbolType::EndOfFile);
+        assert_eq!(builder.symbols()[1].kind, SymbolType::Undefined);
+        assert_eq!(builder.build().unwrap().len(), 9);
+
+        let mut shifted = ProductionTable::new();
+        for rule in productions.iter() {
+            let shift = |s: &Symbol| Symbol::new(s.index - 2, s.name.clone(), s.kind);
+            shifted.push(ProductionRule::new(rule.index, shift(&rule.head),
+                SymbolTable::from(rule.symbols.iter().map(shift).collect::<Vec<_>>())));
+        }
+        let builder = LALRBuilder::new(&shifted[0].head, &shifted);
+        assert_eq!(builder.symbols()[6].kind, SymbolType::EndOfFile);
+        assert_eq!(builder.build().unwrap().len(), 9);
+    }
+
+    #[test]
+    fn ambiguous() {
+        // <E> ::= <E> '+' <E> | id
+        let (symbols, mut productions) = expressions();
+        productions.resize(2);
+        productions[0].symbols = SymbolTable::from(vec![symbols[6].clone(), symbols[2].clone(), symbols[6].clone()]);
+        productions[1].symbols = SymbolTable::from(vec![symbols[5].clone()]);
+        let conflicts = LALRBuilder::with_symbols(&symbols, &symbols[6], &productions).build().err().unwrap();
+        assert_eq!(conflicts.len(), 1);
+        assert!(conflicts[0].is_shift_reduce());
+        assert_eq!(conflicts[0].symbol.name, "+");
+        assert!(conflicts[0].to_string().ends_with("shift-reduce conflict on '+'"), "{}", conflicts[0]);
+
+        let names = |symbols: &[Symbol]| symbols.iter().map(|s| s.name.as_str()).collect::<Vec<_>>().join(" ");
+        assert_eq!(conflicts[0].rules.iter().map(|r| r.index).collect::<Vec<_>>(), vec![0, 0]);
+        assert_eq!(names(&conflicts[0].prefix), "E + E");
+        assert_eq!(names(&conflicts[0].example), "id + id");
+        let report = conflicts[0].report();
+        assert!(report.contains("  Reduce <E> ::= <E> '+' <E>\n  Shift  <E> ::= <E> '+' <E>\n"), "{report}");
+        assert!(report.ends_with("Example: 'id' '+' 'id' | '+'\n"), "{report}");
+    }
+
+    #[test]
+    fn precedence() {
+        // <E> ::= <E> '+' <E> | <E> '*' <E> | <E> '<' <E> | id
+        let names = [("EOF", SymbolType::EndOfFile), ("+", SymbolType::Terminal), ("*", SymbolType::Terminal),
+            ("<", SymbolType::Terminal), ("id", SymbolType::Terminal), ("E", SymbolType::NonTerminal)];
+        let symbols = SymbolTable::from(names.iter().enumerate()
+            .map(|(i, (n, k))| Symbol::new(i, n.to_string(), *k)).collect::<Vec<_>>());
+        let mut productions = ProductionTable::new();
+        for body in [vec![5, 1, 5], vec![5, 2, 5], vec![5, 3, 5], vec![4]] {
+            let body = SymbolTable::from(body.iter().map(|s| symbols[*s].clone()).collect::<Vec<_>>());
+            productions.push(ProductionRule::new(productions.len(), symbols[5].clone(), body));
+        }
+        assert_eq!(LALRBuilder::new(&symbols[5], &productions).build().err().unwrap().len(), 9);
+
+        let states = LALRBuilder::new(&symbols[5], &productions)
+            .precedence(Associativity::NonAssoc, &[symbols[3].clone()])
+            .precedence(Associativity::Left, &[symbols[1].clone()])
+            .precedence(Associativity::Right, &[symbols[2].clone()])
+            .build().unwrap();
+        let after = |op: usize| {
+            let state = states[0].find_action(&symbols[5]).unwrap().target_idx;
+            let state = states[state].find_action(&symbols[op]).unwrap().target_idx;
+            let state = states[state].find_action(&symbols[5]).unwrap().target_idx;
+            [1, 2, 3].map(|next| states[state].find_action(&symbols[next]).map(|a| a.action))
+        };
+        use ActionType::{Reduce, Shift};
+        assert_eq!(after(1), [Some(Reduce), Some(Shift), Some(Reduce)]);
+        assert_eq!(after(2), [Some(Reduce), Some(Shift), Some(Reduce)]);
+        assert_eq!(after(3), [Some(Shift), Some(Shift), None]);
+    }
+
+    #[test]
+    fn loaded_tables() {
+        let egt = Builder::from_path(crate::test::GP_SIMPLE_EGT).unwrap().to_egt().unwrap();
+        assert!(conflicts(&egt).is_empty());
+
+        // make the expression rules ambiguous
+        let mut egt = Builder::from_path(crate::test::GP_TINY_EGT).unwrap().to_egt().unwrap();
+        let index = egt.productions.iter().position(|r| r.to_string().contains("<simple exp> '+' <term>")).unwrap();
+        let simple = egt.productions[index].head.clone();
+        egt.productions[index].symbols = SymbolTable::from(vec![simple.clone(), egt.productions[index].symbols[1].clone(), simple]);
+        let found = conflicts(&egt);
+        assert!(!found.is_empty());
+        assert!(found.iter().all(|c| c.rules.iter().any(|r| r.index == index)));
+    }
+}
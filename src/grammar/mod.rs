@@ -0,0 +1,99 @@
+//! GOLD Meta-Language compiler
+//!
+//! Compiles grammar source (`.grm`) into an `EnhancedGrammarTable` without GOLD Builder:
+//! * `grm` reads the source into a `Grammar`
+//! * `sets` evaluates character set definitions, including the sets GOLD predefines
+//! * `dfa` builds the minimal tokenizer DFA from the terminal definitions, and can be used on
+//!   its own through `LexerBuilder` for terminals added in code
+//! * `ebnf` replaces optional, repeated and grouped symbols in rules by generated nonterminals
+//! * `analysis` computes FIRST and FOLLOW sets and finds unused symbols and left recursion
+//!   in a grammar table
+//! * `decompile` reconstructs `.grm` source from an `EnhancedGrammarTable`
+//! * `export` writes a grammar table as W3C EBNF, an ANTLR 4 grammar or a tree-sitter
+//!   `grammar.js`
+//! * `railroad` draws syntax diagrams of the rules as SVG in an HTML page
+//! * `lalr` builds the LALR(1) parse states from the rules, and can be used on its own
+//!   through `LALRBuilder` for productions built in code
+//!
+//! ```no_run
+//! use goldparser::{grammar, engine::parser::GPParser, Parser};
+//!
+//! let egt = grammar::compile_file("examples/simple.grm").unwrap();
+//! let mut parser = Parser::from_grammar(egt);
+//! parser.load_source("examples/simple.src".to_string()).unwrap();
+//! parser.parse();
+//! ```
+//!
+//! http://goldparser.org/doc/grammars/index.htm
+
+use std::{fs, io, path::Path};
+
+use thiserror::Error;
+
+use crate::engine::{EnhancedGrammarTable, Finding};
+
+pub mod grm;
+pub mod sets;
+pub mod regex;
+pub mod lalr;
+pub mod dfa;
+pub mod ebnf;
+pub mod analysis;
+pub mod decompile;
+pub mod export;
+pub mod railroad;
+mod compile;
+
+pub use grm::Grammar;
+pub use sets::CharacterSets;
+pub use regex::RegExp;
+pub use lalr::{Associativity, Conflict, LALRBuilder};
+pub use dfa::LexerBuilder;
+pub use decompile::decompile;
+
+/// Errors raised while reading or compiling grammar source
+#[derive(Debug, Error)]
+pub enum GrammarError {
+    #[error("unable to read grammar: {0}")]
+    Io(#[from] io::Error),
+    #[error("line {line}, column {column}: {message}")]
+    Syntax { line: usize, column: usize, message: String },
+    #[error("line {line}: undefined character set {{{name}}}")]
+    UndefinedSet { name: String, line: usize },
+    #[error("line {line}: {name} is already defined")]
+    Redefined { name: String, line: usize },
+    #[error("line {line}: <{name}> has no rules")]
+    UndefinedNonterminal { name: String, line: usize },
+    #[error("line {line}: <{name}> is named like an EBNF group")]
+    GroupName { name: String, line: usize },
+    #[error("line {line}: terminal {name} is not defined or used by any rule")]
+    UndefinedTerminal { name: String, line: usize },
+    #[error("the \"Start Symbol\" property is not set")]
+    NoStartSymbol,
+    #[error("start symbol <{0}> has no rules")]
+    UndefinedStartSymbol(String),
+    #[error("line {line}: group {name} has no end")]
+    IncompleteGroup { name: String, line: usize },
+    #[error("line {line}: invalid attribute {attribute} = {value} for {name}")]
+    BadAttribute { name: String, attribute: String, value: String, line: usize },
+    #[error("terminal {0} accepts the empty string")]
+    EmptyTerminal(String),
+    #[error("terminals {0} and {1} accept the same text")]
+    LexicalConflict(String, String),
+    #[error("the grammar is not LALR(1): {}", .0.iter().map(|c| c.to_string()).collect::<Vec<_>>().join("; "))]
+    Conflicts(Vec<Conflict>),
+    #[error("the {table} has {count} entries, more than an EGT can hold")]
+    TooLarge { table: &'static str, count: usize },
+    #[error("compiled tables are invalid: {}", .0.iter().map(|f| f.to_string()).collect::<Vec<_>>().join("; "))]
+    Invalid(Vec<Finding>),
+}
+
+/// Compiles the text of a `.grm` file
+pub fn compile(source: &str) -> Result<EnhancedGrammarTable, GrammarError> {
+    Grammar::parse(source)?.compile()
+}
+
+/// Reads and compiles a `.grm` file
+pub fn compile_file<P: AsRef<Path>>(path: P) -> Result<EnhancedGrammarTable, GrammarError> {
+    compile(&fs::read_to_string(path)?)
+}
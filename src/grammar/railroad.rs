@@ -0,0 +1,368 @@
+//! Railroad diagrams
+//!
+//! Draws a syntax diagram for every nonterminal of a grammar table, in a self-contained HTML
+//! page of inline SVG. The alternatives of a head are collapsed into one diagram: symbols
+//! alternatives start or end with alike are drawn once, and direct left or right recursion is
+//! drawn as a loop. Terminals are drawn in rounded boxes, nonterminals in square
+//! boxes linking to their own diagram.
+//!
+//! ```no_run
+//! use goldparser::{engine::Builder, grammar::railroad};
+//!
+//! let egt = Builder::from_path("examples/simple.egt").and_then(|mut b| b.to_egt()).unwrap();
+//! std::fs::write("simple.html", railroad::html(&egt)).unwrap();
+//! ```
+
+use std::fmt::Write;
+
+use crate::engine::{EnhancedGrammarTable, Symbol, SymbolType};
+
+use super::analysis::start_symbol;
+
+/// Width of a character of the box labels
+const CHAR: i32 = 8;
+/// Height of a box
+const BOX: i32 = 24;
+/// Space left and right of a label, and between the items of a sequence
+const GAP: i32 = 10;
+/// Space between the alternatives of a choice
+const VGAP: i32 = 8;
+/// Radius of the curves joining and leaving the main line
+const R: i32 = 10;
+/// Margin around a diagram
+const MARGIN: i32 = 10;
+
+const STYLE: &str = "\
+svg.railroad path { stroke: #333; stroke-width: 2; fill: none; }
+svg.railroad rect { stroke: #333; stroke-width: 2; fill: #eef4ff; }
+svg.railroad rect.terminal { fill: #fff8e0; }
+svg.railroad text { font: 13px monospace; text-anchor: middle; }
+svg.railroad a text { fill: #0645ad; }
+svg.railroad a:hover rect { fill: #d8e6ff; }";
+
+/// An element of a diagram
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Terminal(String),
+    NonTerminal(String),
+    /// The empty path
+    Skip,
+    Sequence(Vec<Node>),
+    /// Alternatives below each other, the first on the main line
+    Choice(Vec<Node>),
+    /// One or more times, returning below the item
+    Repeat(Box<Node>),
+}
+
+impl Node {
+    fn symbol(symbol: &Symbol) -> Node {
+        match symbol.kind {
+            SymbolType::NonTerminal => Node::NonTerminal(symbol.name.clone()),
+            _ => Node::Terminal(symbol.name.clone()),
+        }
+    }
+
+    /// Flattens nested sequences and drops empty paths. A repeated item preceded or followed
+    /// by itself is a repetition of one or more.
+    fn sequence(items: Vec<Node>) -> Node {
+        let mut flat = Vec::<Node>::new();
+        for item in items {
+            match item {
+                Node::Skip => {},
+                Node::Sequence(items) => flat.extend(items),
+                item => flat.push(item),
+            }
+        }
+        let mut result = Vec::<Node>::new();
+        for item in flat {
+            match (result.last(), &item) {
+                (Some(last), Node::Choice(c)) if c.len() == 2 && c[0] == Node::Skip
+                    && matches!(&c[1], Node::Repeat(r) if **r == *last) => {
+                    let last = result.pop().unwrap();
+                    result.push(Node::Repeat(Box::new(last)));
+                },
+                (Some(Node::Choice(c)), item) if c.len() == 2 && c[0] == Node::Skip
+                    && matches!(&c[1], Node::Repeat(r) if **r == *item) => {
+                    result.pop();
+                    result.push(Node::Repeat(Box::new(item.clone())));
+                },
+                _ => result.push(item),
+            }
+        }
+        match result.len() {
+            0 => Node::Skip,
+            1 => result.pop().unwrap(),
+            _ => Node::Sequence(result),
+        }
+    }
+
+    /// A choice between sequences. Items all alternatives start or end with are taken out of
+    /// the choice, alternatives starting alike are factored in turn, and an empty alternative
+    /// is moved to the main line.
+    fn choice(mut alternatives: Vec<Vec<Node>>) -> Node {
+        let mut unique = Vec::<Vec<Node>>::new();
+        for alternative in alternatives.drain(..) {
+            if !unique.contains(&alternative) {
+                unique.push(alternative);
+            }
+        }
+        if unique.len() == 1 {
+            return Node::sequence(unique.pop().unwrap());
+        }
+        let shortest = unique.iter().map(Vec::len).min().unwrap_or(0);
+        let prefix = (0..shortest).take_while(|&i| unique.iter().all(|a| a[i] == unique[0][i])).count();
+        let suffix = (0..shortest - prefix)
+            .take_while(|&i| unique.iter().all(|a| a[a.len() - 1 - i] == unique[0][unique[0].len() - 1 - i]))
+            .count();
+        if prefix + suffix > 0 {
+            let head = unique[0][..prefix].to_vec();
+            let tail = unique[0][unique[0].len() - suffix..].to_vec();
+            let middle = unique.into_iter().map(|a| a[prefix..a.len() - suffix].to_vec()).collect();
+            return Node::sequence(head.into_iter().chain(std::iter::once(Node::choice(middle))).chain(tail).collect());
+        }
+
+        let mut groups = Vec::<Vec<Vec<Node>>>::new();
+        for alternative in unique {
+            match groups.iter_mut().find(|g| g[0].first() == alternative.first()) {
+                Some(group) => group.push(alternative),
+                None => groups.push(vec![alternative]),
+            }
+        }
+        let mut nodes = groups.into_iter().map(Node::choice).collect::<Vec<_>>();
+        if let Some(skip) = nodes.iter().position(|n| *n == Node::Skip) {
+            nodes.remove(skip);
+            nodes.insert(0, Node::Skip);
+        }
+        Node::Choice(nodes)
+    }
+
+    /// The diagram of a nonterminal from the handles of its rules
+    fn rule(head: &Symbol, handles: &[Vec<&Symbol>]) -> Node {
+        let nodes = |handle: &[&Symbol]| handle.iter().map(|s| Node::symbol(s)).collect::<Vec<_>>();
+        let recursive = |handle: &[&Symbol], at: usize| handle.len() > 1 && handle[at].index == head.index;
+        let (left, rest): (Vec<_>, Vec<_>) = handles.iter().partition(|h| recursive(h, 0));
+        if !left.is_empty() && !rest.is_empty() {
+            // head ::= head x | y is y x*
+            let base = Node::choice(rest.iter().map(|h| nodes(h)).collect());
+            let repeated = Node::choice(left.iter().map(|h| nodes(&h[1..])).collect());
+            return Node::sequence(vec![base, Node::Choice(vec![Node::Skip, Node::Repeat(Box::new(repeated))])]);
+        }
+        let (right, rest): (Vec<_>, Vec<_>) = handles.iter().partition(|h| recursive(h, h.len().saturating_sub(1)));
+        if !right.is_empty() && !rest.is_empty() {
+            // head ::= x head | y is x* y
+            let repeated = Node::choice(right.iter().map(|h| nodes(&h[..h.len() - 1])).collect());
+            let base = Node::choice(rest.iter().map(|h| nodes(h)).collect());
+            return Node::sequence(vec![Node::Choice(vec![Node::Skip, Node::Repeat(Box::new(repeated))]), base]);
+        }
+        Node::choice(handles.iter().map(|h| nodes(h)).collect())
+    }
+
+    /// Width, and extent above and below the main line
+    fn size(&self) -> (i32, i32, i32) {
+        match self {
+            Node::Terminal(label) | Node::NonTerminal(label) => (label.chars().count() as i32 * CHAR + 2 * GAP, BOX / 2, BOX / 2),
+            Node::Skip => (0, 0, 0),
+            Node::Sequence(items) => items.iter().map(Node::size)
+                .fold((-GAP, 0, 0), |(w, u, d), (iw, iu, id)| (w + iw + GAP, u.max(iu), d.max(id))),
+            Node::Choice(alternatives) => {
+                let offsets = Node::offsets(alternatives);
+                let width = alternatives.iter().map(|a| a.size().0).max().unwrap_or(0);
+                let last = alternatives.last().map_or(0, |a| a.size().2);
+                (width + 4 * R, alternatives[0].size().1, offsets.last().unwrap_or(&0) + last)
+            },
+            Node::Repeat(item) => {
+                let (width, up, down) = item.size();
+                (width + 2 * R, up, Node::loop_depth(down))
+            },
+        }
+    }
+
+    /// Distances of the alternatives of a choice below the main line
+    fn offsets(alternatives: &[Node]) -> Vec<i32> {
+        let mut offsets = vec![0];
+        for pair in alternatives.windows(2) {
+            let last = *offsets.last().unwrap();
+            offsets.push((last + pair[0].size().2 + VGAP + pair[1].size().1).max(last + 2 * R));
+        }
+        offsets
+    }
+
+    /// Distance of the return path of a repetition below the main line
+    fn loop_depth(down: i32) -> i32 {
+        (down + VGAP).max(2 * R)
+    }
+
+    /// Writes the node with its main line entering at `(x, y)`
+    fn draw(&self, svg: &mut String, x: i32, y: i32) {
+        match self {
+            Node::Terminal(label) | Node::NonTerminal(label) => {
+                let (width, _, _) = self.size();
+                let text = escape(label);
+                let boxed = format!(
+                    "<rect x=\"{x}\" y=\"{}\" width=\"{width}\" height=\"{BOX}\"{}/><text x=\"{}\" y=\"{}\">{text}</text>",
+                    y - BOX / 2,
+                    if matches!(self, Node::Terminal(_)) { " rx=\"10\" class=\"terminal\"" } else { "" },
+                    x + width / 2, y + 4,
+                );
+                match self {
+                    Node::NonTerminal(_) => writeln!(svg, "<a href=\"#{}\">{boxed}</a>", anchor(label)).unwrap(),
+                    _ => writeln!(svg, "{boxed}").unwrap(),
+                }
+            },
+            Node::Skip => {},
+            Node::Sequence(items) => {
+                let mut x = x;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        writeln!(svg, "<path d=\"M{x} {y}h{GAP}\"/>").unwrap();
+                        x += GAP;
+                    }
+                    item.draw(svg, x, y);
+                    x += item.size().0;
+                }
+            },
+            Node::Choice(alternatives) => {
+                let (width, _, _) = self.size();
+                for (alternative, offset) in alternatives.iter().zip(Node::offsets(alternatives)) {
+                    let inner = alternative.size().0;
+                    let rest = width - 4 * R - inner;
+                    match offset {
+                        0 => writeln!(svg, "<path d=\"M{x} {y}h{}\"/><path d=\"M{} {y}h{}\"/>", 2 * R, x + 2 * R + inner, rest + 2 * R),
+                        _ => writeln!(
+                            svg,
+                            "<path d=\"M{x} {y}a{R} {R} 0 0 1 {R} {R}v{}a{R} {R} 0 0 0 {R} {R}\"/>\
+                             <path d=\"M{} {}h{rest}a{R} {R} 0 0 0 {R} -{R}v-{}a{R} {R} 0 0 1 {R} -{R}\"/>",
+                            offset - 2 * R, x + 2 * R + inner, y + offset, offset - 2 * R,
+                        ),
+                    }.unwrap();
+                    alternative.draw(svg, x + 2 * R, y + offset);
+                }
+            },
+            Node::Repeat(item) => {
+                let (inner, _, down) = item.size();
+                let depth = Node::loop_depth(down);
+                writeln!(
+                    svg,
+                    "<path d=\"M{x} {y}h{R}\"/><path d=\"M{} {y}h{R}\"/>\
+                     <path d=\"M{} {y}a{R} {R} 0 0 1 {R} {R}v{}a{R} {R} 0 0 1 -{R} {R}h-{inner}a{R} {R} 0 0 1 -{R} -{R}v-{}a{R} {R} 0 0 1 {R} -{R}\"/>",
+                    x + R + inner, x + R + inner, depth - 2 * R, depth - 2 * R,
+                ).unwrap();
+                item.draw(svg, x + R, y);
+            },
+        }
+    }
+}
+
+/// Escapes text for HTML and SVG
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// The fragment identifier of the diagram of a nonterminal
+fn anchor(name: &str) -> String {
+    name.chars().map(|ch| if ch.is_alphanumeric() || ch == '_' || ch == '-' { ch } else { '_' }).collect()
+}
+
+/// The nonterminals with rules, the start symbol first
+fn nonterminals(egt: &EnhancedGrammarTable) -> Vec<&Symbol> {
+    let mut heads = start_symbol(egt).into_iter().collect::<Vec<_>>();
+    for rule in egt.productions.iter() {
+        if !heads.iter().any(|h| h.index == rule.head.index) {
+            heads.push(&rule.head);
+        }
+    }
+    heads
+}
+
+/// An SVG railroad diagram of the rules of `head`
+pub fn diagram(egt: &EnhancedGrammarTable, head: &Symbol) -> String {
+    let handles = egt.productions.iter().filter(|r| r.head.index == head.index)
+        .map(|r| r.symbols.iter().collect::<Vec<_>>()).collect::<Vec<_>>();
+    let node = Node::rule(head, &handles);
+    let (width, up, down) = node.size();
+    let y = MARGIN + up.max(BOX / 2);
+    let total = width + 2 * MARGIN + 4 * GAP;
+    let height = y + down.max(BOX / 2) + MARGIN;
+
+    let mut svg = format!(
+        "<svg class=\"railroad\" xmlns=\"http://www.w3.org/2000/svg\" width=\"{total}\" height=\"{height}\" viewBox=\"0 0 {total} {height}\">\n"
+    );
+    // the entry and exit of the diagram are marked by a double bar
+    let entry = MARGIN;
+    writeln!(svg, "<path d=\"M{entry} {}v{BOX}m5 -{BOX}v{BOX}m0 -{}h{}\"/>", y - BOX / 2, BOX / 2, 2 * GAP - 5).unwrap();
+    node.draw(&mut svg, entry + 2 * GAP, y);
+    let exit = entry + 2 * GAP + width;
+    writeln!(svg, "<path d=\"M{exit} {y}h{}m0 -{}v{BOX}m5 -{BOX}v{BOX}\"/>", 2 * GAP - 5, BOX / 2).unwrap();
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// An HTML page with the railroad diagram of every nonterminal, the start symbol first
+pub fn html(egt: &EnhancedGrammarTable) -> String {
+    let title = escape(egt.properties.name().unwrap_or("Grammar"));
+    let mut page = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n<style>\n{STYLE}\n</style>\n</head>\n<body>\n<h1>{title}</h1>\n"
+    );
+    for head in nonterminals(egt) {
+        writeln!(page, "<h2 id=\"{}\">{}</h2>", anchor(&head.name), escape(&head.as_handle())).unwrap();
+        page.push_str(&diagram(egt, head));
+    }
+    page.push_str("</body>\n</html>\n");
+    page
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::engine::{Builder, Symbol, SymbolType};
+
+    use super::{html, Node};
+
+    fn symbol(index: usize, name: &str, kind: SymbolType) -> Symbol {
+        Symbol { index, name: name.to_string(), kind }
+    }
+
+    #[test]
+    fn collapse() {
+        let list = symbol(0, "List", SymbolType::NonTerminal);
+        let item = symbol(1, "Item", SymbolType::NonTerminal);
+        let comma = symbol(2, ",", SymbolType::Terminal);
+        let t = |s: &Symbol| Node::symbol(s);
+
+        // <List> ::= <List> ',' <Item> | <Item>
+        let node = Node::rule(&list, &[vec![&list, &comma, &item], vec![&item]]);
+        assert_eq!(node, Node::Sequence(vec![
+            t(&item),
+            Node::Choice(vec![Node::Skip, Node::Repeat(Box::new(Node::Sequence(vec![t(&comma), t(&item)])))]),
+        ]));
+
+        // <List> ::= <Item> <List> | <Item>
+        let node = Node::rule(&list, &[vec![&item, &list], vec![&item]]);
+        assert_eq!(node, Node::Repeat(Box::new(t(&item))));
+
+        // <List> ::= <Item> ',' <List> | <Item> |
+        let node = Node::rule(&list, &[vec![&item], vec![&item, &comma, &list], vec![]]);
+        assert!(matches!(&node, Node::Sequence(items) if items.len() == 2));
+
+        // common prefixes are drawn once, the empty alternative on the main line
+        let node = Node::rule(&list, &[vec![&item, &comma], vec![&item]]);
+        assert_eq!(node, Node::Sequence(vec![t(&item), Node::Choice(vec![Node::Skip, t(&comma)])]));
+    }
+
+    #[test]
+    fn simple() {
+        let egt = Builder::from_path(crate::test::GP_SIMPLE_EGT).unwrap().to_egt().unwrap();
+        let page = html(&egt);
+        assert!(page.starts_with("<!DOCTYPE html>"));
+        assert!(page.contains("<title>Simple</title>"));
+        for head in ["Statements", "Statement", "Expression", "Add_Exp", "Mult_Exp", "Negate_Exp", "Value"] {
+            assert!(page.contains(&format!("<h2 id=\"{head}\">")), "{head}");
+            assert!(page.contains(&format!("<a href=\"#{head}\">")), "{head}");
+        }
+        assert_eq!(page.matches("<svg ").count(), 7);
+        assert!(page.find("id=\"Statements\"") < page.find("id=\"Value\""));
+        assert!(page.contains(">&lt;&gt;</text>"));
+        // the alternatives of <Statement> starting with if are drawn as one path
+        assert_eq!(page.matches(">if</text>").count(), 1);
+    }
+}
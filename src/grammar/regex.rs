@@ -0,0 +1,93 @@
+//! Terminal definitions
+//!
+//! GOLD defines terminals with a small regular expression language:
+//! * `'text'` or a bare word matches the text literally. `''` is a single quote.
+//! * `{Name}` and `[chars]` match one character of a set
+//! * items in sequence are concatenated, `|` separates alternatives
+//! * `*`, `+` and `?` repeat the preceding item or parenthesised `( )` group
+//!
+//! http://goldparser.org/doc/grammars/define-terminals.htm
+
+use std::fmt::Display;
+
+use super::{sets::SetItem, GrammarError};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegExp {
+    /// Matches the text exactly
+    Literal(String),
+    /// Matches one character of the set
+    Set(SetItem),
+    Sequence(Vec<RegExp>),
+    Alternation(Vec<RegExp>),
+    /// Zero or more
+    Star(Box<RegExp>),
+    /// One or more
+    Plus(Box<RegExp>),
+    /// Zero or one
+    Optional(Box<RegExp>),
+}
+
+impl RegExp {
+    /// Parses an expression written as in a terminal definition, like `{Digit}+('.'{Digit}+)?`
+    pub fn parse(source: &str) -> Result<RegExp, GrammarError> {
+        super::grm::parse_regex(source)
+    }
+
+    /// Returns the text matched by an expression built only from literals. Terminals
+    /// defined this way take priority over other terminals accepting the same text.
+    pub fn as_literal(&self) -> Option<String> {
+        match self {
+            RegExp::Literal(text) => Some(text.clone()),
+            RegExp::Sequence(items) => items.iter().map(RegExp::as_literal).collect(),
+            _ => None,
+        }
+    }
+}
+
+/// Writes the expression as in a terminal definition. Text is quoted unless it is a word.
+impl Display for RegExp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RegExp::Literal(text) if !text.is_empty() && text.chars().all(|c| c.is_alphanumeric() || c == '_') => {
+                write!(f, "{text}")
+            },
+            // `''` is a single quote, so quotes end up between quoted pieces: it's -> 'it'''s'
+            RegExp::Literal(text) => write!(f, "{}", text.split('\'').map(|piece| match piece {
+                "" => String::new(),
+                piece => format!("'{piece}'"),
+            }).collect::<Vec<_>>().join("''")),
+            RegExp::Set(item) => write!(f, "{item}"),
+            RegExp::Sequence(items) => {
+                let mut text = String::new();
+                for item in items {
+                    let item = match item {
+                        RegExp::Alternation(_) => format!("({item})"),
+                        item => item.to_string(),
+                    };
+                    // adjacent words would read as one
+                    let word = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+                    if word(text.chars().last()) && word(item.chars().next()) {
+                        text.push(' ');
+                    }
+                    text.push_str(&item);
+                }
+                write!(f, "{text}")
+            },
+            RegExp::Alternation(items) => {
+                write!(f, "{}", items.iter().map(|i| i.to_string()).collect::<Vec<_>>().join(" | "))
+            },
+            RegExp::Star(item) | RegExp::Plus(item) | RegExp::Optional(item) => {
+                let suffix = match self {
+                    RegExp::Star(_) => '*',
+                    RegExp::Plus(_) => '+',
+                    _ => '?',
+                };
+                match **item {
+                    RegExp::Literal(_) | RegExp::Set(_) => write!(f, "{item}{suffix}"),
+                    _ => write!(f, "({item}){suffix}"),
+                }
+            },
+        }
+    }
+}
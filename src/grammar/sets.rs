@@ -0,0 +1,272 @@
+//! Character set definitions
+//!
+//! A `.grm` file names character sets with `{Name}`. A name is either one of the sets GOLD
+//! predefines, a set declared earlier in the grammar with `{Name} = ...`, or a character code:
+//! `{#65}` (decimal), `{&41}` (hexadecimal) or a range such as `{&41 .. &5A}`.
+//!
+//! http://goldparser.org/doc/grammars/character-sets.htm
+
+use std::{fmt::Display, ops::RangeInclusive};
+
+use crate::engine::CharacterSet;
+
+/// An operand of a set expression
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SetItem {
+    /// `{Name}`
+    Name(String),
+    /// `[abc]`, holding the member characters
+    Literal(String),
+}
+
+/// Writes the item as in a set expression. `'` and `]` are quoted inside literals.
+impl Display for SetItem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SetItem::Name(name) => write!(f, "{{{name}}}"),
+            SetItem::Literal(chars) => {
+                let chars = chars.chars().map(|ch| match ch {
+                    '\'' => "''".to_string(),
+                    ']' => "']'".to_string(),
+                    ch => ch.to_string(),
+                }).collect::<String>();
+                write!(f, "[{chars}]")
+            },
+        }
+    }
+}
+
+/// Operator joining the items of a set expression
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetOp {
+    Add,
+    Sub,
+}
+
+/// `{Letter} + [_] - [x]`. The first item is always joined with `SetOp::Add`.
+pub type SetExpr = Vec<(SetOp, SetItem)>;
+
+/// The sets GOLD Builder predefines, as (name, ranges)
+const PREDEFINED: &[(&str, &[RangeInclusive<u32>])] = &[
+    ("HT", &[0x09..=0x09]),
+    ("LF", &[0x0A..=0x0A]),
+    ("VT", &[0x0B..=0x0B]),
+    ("FF", &[0x0C..=0x0C]),
+    ("CR", &[0x0D..=0x0D]),
+    ("Space", &[0x20..=0x20]),
+    ("NBSP", &[0xA0..=0xA0]),
+    ("LS", &[0x2028..=0x2028]),
+    ("PS", &[0x2029..=0x2029]),
+    ("Euro Sign", &[0x20AC..=0x20AC]),
+    ("Digit", &[0x30..=0x39]),
+    ("Number", &[0x30..=0x39]),
+    ("Letter", &[0x41..=0x5A, 0x61..=0x7A]),
+    ("AlphaNumeric", &[0x30..=0x39, 0x41..=0x5A, 0x61..=0x7A]),
+    ("Printable", &[0x20..=0x7E, 0xA0..=0xA0]),
+    ("Letter Extended", &[0xC0..=0xD6, 0xD8..=0xF6, 0xF8..=0xFF]),
+    ("Printable Extended", &[0xA1..=0xFF]),
+    ("Control Codes", &[0x00..=0x1F, 0x7F..=0x9F]),
+    // as defined by GOLD Builder 5.2, including U+2026
+    ("Whitespace", &[0x09..=0x0D, 0x20..=0x20, 0x85..=0x85, 0xA0..=0xA0, 0x1680..=0x1680, 0x180E..=0x180E,
+        0x2000..=0x200A, 0x2026..=0x2026, 0x2028..=0x2029, 0x202F..=0x202F, 0x205F..=0x205F, 0x3000..=0x3000]),
+    ("Formatting", &[0x200B..=0x200F, 0x202A..=0x202E, 0x2060..=0x2064, 0x206A..=0x206F, 0xFEFF..=0xFEFF,
+        0xFFF9..=0xFFFB]),
+    // the Unicode characters Windows-1252 maps 0x80-0x9F to
+    ("ANSI Mapped", &[0x152..=0x153, 0x160..=0x161, 0x178..=0x178, 0x17D..=0x17E, 0x192..=0x192,
+        0x2C6..=0x2C6, 0x2DC..=0x2DC, 0x2013..=0x2014, 0x2018..=0x201A, 0x201C..=0x201E, 0x2020..=0x2022,
+        0x2026..=0x2026, 0x2030..=0x2030, 0x2039..=0x203A, 0x20AC..=0x20AC, 0x2122..=0x2122]),
+    ("ANSI Printable", &[0x20..=0x7E, 0xA0..=0xFF, 0x152..=0x153, 0x160..=0x161, 0x178..=0x178,
+        0x17D..=0x17E, 0x192..=0x192, 0x2C6..=0x2C6, 0x2DC..=0x2DC, 0x2013..=0x2014, 0x2018..=0x201A,
+        0x201C..=0x201E, 0x2020..=0x2022, 0x2026..=0x2026, 0x2030..=0x2030, 0x2039..=0x203A,
+        0x20AC..=0x20AC, 0x2122..=0x2122]),
+    ("All Valid", &[0x01..=0xD7FF, 0xE000..=0xFFEF]),
+    ("All Latin", &[0x41..=0x5A, 0x61..=0x7A, 0xAA..=0xAA, 0xBA..=0xBA, 0xC0..=0xD6, 0xD8..=0xF6,
+        0xF8..=0x24F, 0x1E00..=0x1EFF]),
+    ("Basic Latin", &[0x00..=0x7F]),
+    ("Latin-1 Supplement", &[0x80..=0xFF]),
+    ("Latin Extended-A", &[0x100..=0x17F]),
+    ("Latin Extended-B", &[0x180..=0x24F]),
+    ("IPA Extensions", &[0x250..=0x2AF]),
+    ("Greek and Coptic", &[0x370..=0x3FF]),
+    ("Cyrillic", &[0x400..=0x4FF]),
+    ("Armenian", &[0x530..=0x58F]),
+    ("Hebrew", &[0x590..=0x5FF]),
+    ("Arabic", &[0x600..=0x6FF]),
+    ("Devanagari", &[0x900..=0x97F]),
+    ("Thai", &[0xE00..=0xE7F]),
+    ("Hangul Jamo", &[0x1100..=0x11FF]),
+    ("Latin Extended Additional", &[0x1E00..=0x1EFF]),
+    ("Greek Extended", &[0x1F00..=0x1FFF]),
+    ("General Punctuation", &[0x2000..=0x206F]),
+    ("Currency Symbols", &[0x20A0..=0x20CF]),
+    ("Letterlike Symbols", &[0x2100..=0x214F]),
+    ("Number Forms", &[0x2150..=0x218F]),
+    ("Arrows", &[0x2190..=0x21FF]),
+    ("Mathematical Operators", &[0x2200..=0x22FF]),
+    ("Box Drawing", &[0x2500..=0x257F]),
+    ("Hiragana", &[0x3040..=0x309F]),
+    ("Katakana", &[0x30A0..=0x30FF]),
+    ("CJK Unified Ideographs", &[0x4E00..=0x9FFF]),
+    ("Hangul Syllables", &[0xAC00..=0xD7AF]),
+    ("Private Use Area", &[0xE000..=0xF8FF]),
+];
+
+/// Looks up a predefined set by name, ignoring case
+pub fn predefined(name: &str) -> Option<CharacterSet> {
+    let (_, ranges) = PREDEFINED.iter().find(|(n, _)| n.eq_ignore_ascii_case(name))?;
+    let mut set = CharacterSet::default();
+    for range in ranges.iter() {
+        // the surrogate block is not part of any predefined set
+        set.add(char::from_u32(*range.start())?..=char::from_u32(*range.end())?);
+    }
+    Some(set)
+}
+
+/// All predefined sets, as (name, set)
+pub(crate) fn predefined_sets() -> impl Iterator<Item = (&'static str, CharacterSet)> {
+    PREDEFINED.iter().filter_map(|(name, _)| Some((*name, predefined(name)?)))
+}
+
+/// Decodes `#65`, `&41` and ranges such as `&41 .. &5A`
+fn character_code(name: &str) -> Option<CharacterSet> {
+    let code = |v: &str| -> Option<char> {
+        let v = v.trim();
+        let value = match v.chars().next()? {
+            '#' => v[1..].parse().ok()?,
+            '&' => u32::from_str_radix(&v[1..], 16).ok()?,
+            _ => return None,
+        };
+        char::from_u32(value)
+    };
+    let (start, end) = match name.split_once("..") {
+        Some((start, end)) => (code(start)?, code(end)?),
+        None => (code(name)?, code(name)?),
+    };
+    let mut set = CharacterSet::new(vec![start..=end]);
+    set.normalize();
+    Some(set)
+}
+
+/// The character sets visible to a grammar: its own declarations followed by the
+/// predefined sets. Names are compared ignoring case.
+#[derive(Debug, Default, Clone)]
+pub struct CharacterSets {
+    defined: Vec<(String, CharacterSet)>,
+}
+
+impl CharacterSets {
+    pub fn new() -> Self { CharacterSets::default() }
+
+    /// Declares `{name}`, replacing an earlier declaration of the same name
+    pub fn define(&mut self, name: &str, set: CharacterSet) {
+        match self.defined.iter_mut().find(|(n, _)| n.eq_ignore_ascii_case(name)) {
+            Some((_, existing)) => *existing = set,
+            None => self.defined.push((name.to_string(), set)),
+        }
+    }
+
+    /// The set named `name`, or `None` if it is not declared, predefined or a character code
+    pub fn get(&self, name: &str) -> Option<CharacterSet> {
+        let name = name.trim();
+        self.defined.iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, set)| set.clone())
+            .or_else(|| predefined(name))
+            .or_else(|| character_code(name))
+    }
+
+    /// The sets declared by the grammar, in declaration order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &CharacterSet)> {
+        self.defined.iter().map(|(n, s)| (n.as_str(), s))
+    }
+
+    /// Resolves a single item. Returns the name of an undefined set as the error.
+    pub fn resolve(&self, item: &SetItem) -> Result<CharacterSet, String> {
+        match item {
+            SetItem::Name(name) => self.get(name).ok_or_else(|| name.clone()),
+            SetItem::Literal(chars) => Ok(CharacterSet::from(chars.as_str())),
+        }
+    }
+
+    /// Evaluates a set expression left to right
+    pub fn evaluate(&self, expr: &SetExpr) -> Result<CharacterSet, String> {
+        let mut set = CharacterSet::default();
+        for (op, item) in expr {
+            let operand = self.resolve(item)?;
+            set = match op {
+                SetOp::Add => set.union(&operand),
+                SetOp::Sub => set.difference(&operand),
+            };
+        }
+        Ok(set)
+    }
+}
+
+/// Adds the other case of every cased character in `set`, for case insensitive grammars
+pub fn fold_case(set: &CharacterSet) -> CharacterSet {
+    let mut folded = set.clone();
+    for range in set.ranges() {
+        for ch in range.clone().filter(|c| c.is_alphabetic()) {
+            for other in [single(ch.to_lowercase()), single(ch.to_uppercase())].into_iter().flatten() {
+                if other != ch {
+                    folded.add(other..=other);
+                }
+            }
+        }
+    }
+    folded.normalize();
+    folded
+}
+
+/// The only character of a case mapping, ignoring mappings to several characters
+fn single(mut chars: impl Iterator<Item = char>) -> Option<char> {
+    match (chars.next(), chars.next()) {
+        (Some(ch), None) => Some(ch),
+        _ => None,
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::engine::CharacterSet;
+
+    use super::{CharacterSets, SetItem, SetOp, fold_case};
+
+    #[test]
+    fn predefined() {
+        let sets = CharacterSets::new();
+        let letter = sets.get("letter").unwrap();
+        assert!(letter.contains('q') && letter.contains('Q') && !letter.contains('1'));
+        assert!(sets.get("Whitespace").unwrap().contains('\u{3000}'));
+        assert!(sets.get("No Such Set").is_none());
+    }
+
+    #[test]
+    fn character_codes() {
+        let sets = CharacterSets::new();
+        assert_eq!(sets.get("#65"), Some(CharacterSet::new(vec!['A'..='A'])));
+        assert_eq!(sets.get("&41 .. &5A"), Some(CharacterSet::new(vec!['A'..='Z'])));
+        assert_eq!(sets.get("&1F600"), Some(CharacterSet::new(vec!['\u{1F600}'..='\u{1F600}'])));
+    }
+
+    #[test]
+    fn evaluate() {
+        let mut sets = CharacterSets::new();
+        // {String Ch} = {Printable} - ['']
+        let string_ch = sets.evaluate(&vec![
+            (SetOp::Add, SetItem::Name("Printable".to_string())),
+            (SetOp::Sub, SetItem::Literal("'".to_string())),
+        ]).unwrap();
+        assert!(string_ch.contains('a') && string_ch.contains('\u{A0}') && !string_ch.contains('\''));
+        sets.define("String Ch", string_ch.clone());
+        assert_eq!(sets.get("string ch"), Some(string_ch));
+        assert_eq!(sets.evaluate(&vec![(SetOp::Add, SetItem::Name("Nope".to_string()))]), Err("Nope".to_string()));
+    }
+
+    #[test]
+    fn case_folding() {
+        let folded = fold_case(&CharacterSet::from("if_"));
+        assert_eq!(folded.ranges(), &vec!['F'..='F', 'I'..='I', '_'..='_', 'f'..='f', 'i'..='i']);
+    }
+}
@@ -10,6 +10,7 @@ extern crate num_traits;
 
 pub mod engine;
 pub mod parser;
+pub mod grammar;
 
 pub use engine::Parser;
 pub use parser::GOLDParser;
@@ -18,10 +19,12 @@ pub use parser::GOLDParser;
 pub mod test {
     pub const GP_TEST_EGT: &str = r"D:\Users\Gian\prog\repos\RUST\goldparser-rs\.ref\goldparser-test-new.egt";
     pub const GP_TEST_SRC: &str = r"D:\Users\Gian\prog\repos\RUST\goldparser-rs\.ref\goldparser-test.asm";
-    pub const GP_SIMPLE_EGT: &str = r"D:\Users\Gian\prog\repos\RUST\goldparser-rs\examples\simple.egt";
-    pub const GP_SIMPLE_SRC: &str = r"D:\Users\Gian\prog\repos\RUST\goldparser-rs\examples\simple.src";
-    pub const GP_TINY_EGT: &str = r"D:\Users\Gian\prog\repos\RUST\goldparser-rs\examples\tiny.egt";
-    pub const GP_TINY_SRC: &str = r"D:\Users\Gian\prog\repos\RUST\goldparser-rs\examples\tiny.src";
+    pub const GP_SIMPLE_EGT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/examples/simple.egt");
+    pub const GP_SIMPLE_SRC: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/examples/simple.src");
+    pub const GP_TINY_EGT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/examples/Tiny.egt");
+    pub const GP_TINY_SRC: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/examples/sample.tiny");
+    pub const GP_SIMPLE_GRM: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/examples/simple.grm");
+    pub const GP_TINY_GRM: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/examples/Tiny.grm");
     
     pub fn init_logger() {
         let _ = env_logger::builder()
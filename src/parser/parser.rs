@@ -28,28 +28,31 @@ impl GOLDParser {
     const VT_INDENT_INC: &str = "IndentIncrease";
     const VT_INDENT_DEC: &str = "IndentDecrease";
 
-    pub fn new(egt: &str, src: &str, trim: bool, case: bool) -> Self {
-        let mut parser = Parser::new(String::from(egt));
+    /// Loads the grammar `egt` and the source file `src`. Variable names are case
+    /// insensitive unless the grammar's `Case Sensitive` property is `True`.
+    pub fn new(egt: &str, src: &str, trim: bool) -> Result<Self, ParserError> {
+        let mut parser = Parser::new(String::from(egt))?;
+        let ignore_case = !parser.grammar.properties.case_sensitive().unwrap_or(false);
         let mut ignore_indent = true;
         //println!("Parser tables loaded.");
         if let Some(_) = parser.symbol_by_name(Self::VT_INDENT_INC) {
             ignore_indent = false;
         }
-        parser.load_source(String::from(src)).expect(src);
+        parser.load_source(String::from(src))?;
         parser.trim_reductions = trim;
         let mut scopes = HashMap::new();
         scopes.insert(Scope::GLOBAL_SCOPE.to_string(),Scope::default());
 
-        GOLDParser {
+        Ok(GOLDParser {
             parser,
             // TODO fix reduction
             root: None,
             scopes,
             curr_scope: Scope::default(),
-            ignore_case: case,
+            ignore_case,
             ignore_indent,
             generate_tree: false,
-        }
+        })
     }
     /// Top-level method to begin parsing. If something very custom is needed, the
     /// `GOLDParser` can use an overridden method.
@@ -207,7 +210,7 @@ impl GOLDParser {
     pub fn parser(&mut self) -> &mut Parser {
         &mut self.parser
     }
-    pub fn source_size(&self) -> usize { self.parser.source.src.len() }
+    pub fn source_size(&self) -> usize { self.parser.source.len() }
     pub fn source_pos(&self) -> Position { self.parser.source.pos }
     pub fn source_abs_pos(&self) -> usize { self.parser.source.get_abs_pos() }
     
@@ -229,8 +232,36 @@ impl GOLDParser {
 mod test {
 
 
+    use crate::engine::parser::{GPMessage, GPParser};
+
+    use super::GOLDParser;
+
     #[test]
     fn new() {
 
     }
+    #[test]
+    fn case_sensitive() {
+        let parser = GOLDParser::new(crate::test::GP_TINY_EGT, crate::test::GP_TINY_SRC, true).unwrap();
+        // Tiny.egt has no Case Sensitive property, GOLD's default is False
+        assert!(parser.ignore_case);
+
+        let path = |ext: &str| std::env::temp_dir().join(format!("case-sensitive-{}.{ext}", std::process::id()));
+        let parse = |sensitive: &str, source: &str| {
+            let egt = crate::grammar::compile(&format!(
+                "\"Start Symbol\" = <S>\n\"Case Sensitive\" = {sensitive}\nId = {{Letter}}+\n<S> ::= read Id\n",
+            )).unwrap();
+            let (egt_path, src_path) = (path("egt"), path("txt"));
+            std::fs::write(&egt_path, crate::engine::writer::write(&egt).unwrap()).unwrap();
+            std::fs::write(&src_path, source).unwrap();
+            let mut parser = GOLDParser::new(egt_path.to_str().unwrap(), src_path.to_str().unwrap(), true).unwrap();
+            let result = parser.parser().parse();
+            std::fs::remove_file(egt_path).unwrap();
+            std::fs::remove_file(src_path).unwrap();
+            (parser.ignore_case, matches!(result, GPMessage::Accept))
+        };
+        assert_eq!(parse("True", "read x"), (false, true));
+        assert_eq!(parse("True", "READ x"), (false, false));
+        assert_eq!(parse("False", "READ x"), (true, true));
+    }
 }
\ No newline at end of file